@@ -0,0 +1,146 @@
+//Attendance: who voted what and who was in voice when the event started, read by /stats
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serenity::all::{Context, Timestamp, UserId};
+
+use crate::config;
+use crate::i18n::{self, tr};
+use crate::poll_sources::{OptionKind, PollOption};
+use crate::storage::{self, AttendanceRecord, MemberStats, PollStore, StoredPoll};
+use crate::utils::{self, PollRef};
+
+// how often the event times are checked, in seconds
+const CHECK_INTERVAL: u64 = 60;
+// the voice channels tell nothing if we come back online long after the event started
+const MAX_LATE: i64 = 30 * 60;
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+
+// starts taking the attendance in the background, only the first call does anything
+pub fn start(ctx: Context) {
+    if !config::get().attendance.enabled || STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(CHECK_INTERVAL));
+        loop {
+            interval.tick().await;
+            take_due(&ctx).await;
+        }
+    });
+}
+
+async fn take_due(ctx: &Context) {
+    let Some(store) = storage::get(ctx).await else {return;};
+    let now = Timestamp::now().unix_timestamp();
+    let delay = config::get().attendance.delay_minutes as i64 * 60;
+    let polls = match store.polls_to_take_attendance(now - delay) {
+        Ok(p) => p,
+        Err(e) => {
+            println!("attendance: store error {e}");
+            return;
+        },
+    };
+    for (poll, event_at) in polls {
+        let result = if now - (event_at + delay) > MAX_LATE {
+            Err("too late to tell who was there".to_string())
+        } else {
+            take(ctx, &store, &poll, now).await
+        };
+        match result {
+            Ok(r) => println!("attendance: poll {}: {r}", poll.message_id),
+            Err(e) => {
+                println!("attendance: poll {}: {e}, skipped", poll.message_id);
+                // not trying again, the poll just doesn't count
+                if let Err(e) = store.record_attendance(poll.message_id, poll.guild_id, now, &[]) {
+                    println!("attendance: store error {e}");
+                }
+            },
+        }
+    }
+}
+
+// records the attendance of the poll's channel members right now
+async fn take(ctx: &Context, store: &PollStore, poll: &StoredPoll, now: i64) -> Result<String, String> {
+    let link = poll.message_id.link(poll.channel_id, Some(poll.guild_id));
+    let loc = i18n::guild_locale(ctx, poll.guild_id);
+    let results = utils::get_poll_results(ctx, poll.guild_id, poll.channel_id, PollRef::Given(&link), loc).await?;
+    let in_voice: HashSet<UserId> = utils::get_members_in_voice(ctx, poll.guild_id, None)
+        .ok_or("the guild is not in the cache".to_string())?;
+    // the members who might have voted under a name they share are left out, they are neither voters nor non-voters
+    let maybe = results.might_have_voted(&results.channel_members);
+    let members: Vec<UserId> = results.channel_members.iter().map(|m| m.user.id).filter(|u| !maybe.contains(u)).collect();
+    let records = attendance_records(&members, &results.options, &results.responses, &in_voice);
+    store.record_attendance(poll.message_id, poll.guild_id, now, &records).map_err(|e| format!("store error {e}"))?;
+
+    let accepted: Vec<&AttendanceRecord> = records.iter().filter(|r| r.kind.as_deref() == Some(OptionKind::Accepted.name())).collect();
+    let showed_up = accepted.iter().filter(|r| r.in_voice).count();
+    let summary = tr!(loc, "log.attendance", n = showed_up, of = accepted.len());
+    if let Err(e) = utils::log_to_thread(ctx, &summary, &poll.guild_id, &poll.channel_id, &poll.message_id.to_string()).await {
+        println!("{e}");
+    }
+    Ok(summary)
+}
+
+
+// one record per member, members who voted for several options count with the most committing one
+pub fn attendance_records(members: &[UserId], options: &[PollOption], responses: &[Vec<UserId>], in_voice: &HashSet<UserId>)
+    -> Vec<AttendanceRecord>
+{
+    const PRIORITY: [OptionKind; 4] = [OptionKind::Accepted, OptionKind::Tentative, OptionKind::Declined, OptionKind::Other];
+    members.iter().map(|u| {
+        let voted: Vec<OptionKind> = options.iter().zip(responses)
+            .filter(|(_, voters)| voters.contains(u))
+            .map(|(o, _)| o.kind)
+            .collect();
+        AttendanceRecord {
+            user_id: *u,
+            kind: PRIORITY.iter().find(|k| voted.contains(k)).map(|k| k.name().to_string()),
+            in_voice: in_voice.contains(u),
+        }
+    }).collect()
+}
+
+
+// "accepted 80%, no-show 25% (1/4), no vote 20%"
+pub fn describe_stats(s: &MemberStats, loc: &str) -> String {
+    let percent = |n: u32, of: u32| (n * 100 + of / 2).checked_div(of).map_or("-".to_string(), |p| format!("{p}%"));
+    tr!(loc, "stats.rates", accepted = percent(s.accepted, s.polls), no_show = percent(s.no_shows, s.accepted),
+        no_shows = s.no_shows, of = s.accepted, no_vote = percent(s.no_votes, s.polls))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_take_the_most_committing_vote() {
+        let options = vec![
+            PollOption { label: "Declined".to_string(), kind: OptionKind::Declined },
+            PollOption { label: "Tank".to_string(), kind: OptionKind::Accepted },
+            PollOption { label: "Maybe".to_string(), kind: OptionKind::Tentative },
+        ];
+        let (a, b, c) = (UserId::new(1), UserId::new(2), UserId::new(3));
+        let responses = vec![vec![a, b], vec![a], vec![]];
+        let in_voice: HashSet<UserId> = [a, c].into_iter().collect();
+        let records = attendance_records(&[a, b, c], &options, &responses, &in_voice);
+        assert_eq!(records, vec![
+            AttendanceRecord { user_id: a, kind: Some("accepted".to_string()), in_voice: true },
+            AttendanceRecord { user_id: b, kind: Some("declined".to_string()), in_voice: false },
+            AttendanceRecord { user_id: c, kind: None, in_voice: true },
+        ]);
+    }
+
+    #[test]
+    fn rates() {
+        let s = MemberStats { user_id: UserId::new(1), polls: 5, accepted: 4, no_shows: 1, no_votes: 1 };
+        assert_eq!(describe_stats(&s, "en"), "accepted 80%, no-show 25% (1/4), no vote 20%");
+        let s = MemberStats { user_id: UserId::new(1), polls: 3, accepted: 0, no_shows: 0, no_votes: 3 };
+        assert_eq!(describe_stats(&s, "en"), "accepted 0%, no-show - (0/0), no vote 100%");
+    }
+}
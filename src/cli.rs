@@ -0,0 +1,353 @@
+//Offline tools for debugging the poll parsers, run instead of the bot:
+//  pollbot analyse <message.json> [members.json]                  parses a saved poll, matches the names to the saved members
+//  pollbot dump <channel id> [message id] [--out <dir>]           saves the poll (the last one by default) and the channel's members
+//the message is the JSON Discord sends (or what dump wrote), the members are a list of guild members
+
+use serenity::all::{ChannelId, GetMessages, GuildChannel, Http, Member, Message, MessageId, UserId};
+
+use crate::poll_sources;
+
+const USAGE: &str = "Usage:
+  pollbot analyse <message.json> [members.json]
+  pollbot dump <channel id> [message id] [--out <dir>]    (the token is taken from DISCORD_TOKEN or --token)";
+// members are listed 1000 at a time
+const MEMBERS_PAGE: u64 = 1000;
+// how far back dump looks for a poll
+const MAX_MESSAGES: usize = 1000;
+
+pub enum Command {
+    Analyse { message: String, members: Option<String> },
+    Dump { channel: ChannelId, message: Option<MessageId>, out: String },
+}
+
+
+// None if the arguments are not one of the commands (the bot runs then), the usage if they are wrong
+pub fn parse(args: &[String]) -> Option<Result<Command, String>>
+{
+    let (name, rest) = args.split_first()?;
+    let mut positional: Vec<&String> = Vec::new();
+    let mut out = ".".to_string();
+    let mut rest = rest.iter();
+    while let Some(a) = rest.next() {
+        if a == "--out" {
+            match rest.next() {
+                Some(dir) => out = dir.clone(),
+                None => return Some(Err(USAGE.to_string())),
+            }
+        } else {
+            positional.push(a);
+        }
+    }
+    let id = |s: &String| s.parse::<u64>().ok().filter(|id| *id != 0);
+    let command = match (name.as_str(), positional.as_slice()) {
+        ("analyse" | "analyze", [message]) => Command::Analyse { message: message.to_string(), members: None },
+        ("analyse" | "analyze", [message, members]) =>
+            Command::Analyse { message: message.to_string(), members: Some(members.to_string()) },
+        ("dump", [channel]) => match id(channel) {
+            Some(ch) => Command::Dump { channel: ChannelId::new(ch), message: None, out },
+            None => return Some(Err(USAGE.to_string())),
+        },
+        ("dump", [channel, message]) => match (id(channel), id(message)) {
+            (Some(ch), Some(m)) => Command::Dump { channel: ChannelId::new(ch), message: Some(MessageId::new(m)), out },
+            _ => return Some(Err(USAGE.to_string())),
+        },
+        ("analyse" | "analyze" | "dump", _) => return Some(Err(USAGE.to_string())),
+        _ => return None,
+    };
+    Some(Ok(command))
+}
+
+
+// runs the command, the token is only needed for dump
+pub async fn run(command: Command, token: Option<String>) -> Result<(), String>
+{
+    match command {
+        Command::Analyse { message, members } => {
+            let msg: Message = read_json(&message)?;
+            let members: Option<Vec<Member>> = match members {
+                Some(path) => Some(read_json(&path)?),
+                None => None,
+            };
+            println!("{}", analyse(&msg, members.as_deref())?);
+            Ok(())
+        },
+        Command::Dump { channel, message, out } => {
+            let Some(token) = token else {
+                return Err("dump needs a token, set DISCORD_TOKEN or use --token".to_string());
+            };
+            dump(&Http::new(&token), channel, message, &out).await
+        },
+    }
+}
+
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &str) -> Result<T, String>
+{
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Can't read {path}: {e}"))?;
+    serde_json::from_str(&text).map_err(|e| format!("{path} is not what was expected: {e}"))
+}
+
+fn write_json<T: serde::Serialize>(path: &str, value: &T) -> Result<(), String>
+{
+    let text = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    std::fs::write(path, text).map_err(|e| format!("Can't write {path}: {e}"))
+}
+
+
+// every option with the names listed there and, given the members, who they were matched to
+#[cfg(feature = "third_party_bots")]
+pub fn analyse(msg: &Message, members: Option<&[Member]>) -> Result<String, String>
+{
+    use crate::tpbot_utils::{convert_names_to_ids, parse_tp_bot_embed, resolve_name, Hints, MemberNames, Resolution, Tiebreak};
+
+    let Some(bot) = crate::config::get().bot(msg.author.id.get()) else {
+        let known: Vec<String> = crate::config::get().bots.iter().map(|b| format!("{} ({})", b.name, b.id)).collect();
+        return Err(format!("The message is by {}, not by any of the configured bots: {}", msg.author.id, known.join(", ")));
+    };
+    let Some(embed) = msg.embeds.first() else {
+        return Err(format!("The {} message has no embeds.", bot.name));
+    };
+    let names = parse_tp_bot_embed(bot, embed, crate::i18n::DEFAULT_LOCALE)?;
+    let options = bot.options();
+    let mut report = format!("Poll by {} ({})\n", bot.name, describe_message(msg));
+
+    let Some(members) = members else {
+        for (i, names) in names.iter().enumerate() {
+            report += &option_header(&options, i, names.len());
+            for n in names {
+                report += &format!("  {n}\n");
+            }
+        }
+        return Ok(report);
+    };
+
+    let members: Vec<Member> = members.iter().filter(|m| !m.user.bot).cloned().collect();
+    let member_names: Vec<MemberNames> = members.iter().map(MemberNames::from).collect();
+    let converted = convert_names_to_ids(names.clone(), &members, &Hints::default());
+    let member_name = |u: &UserId| match members.iter().find(|m| m.user.id == *u) {
+        Some(m) => format!("{} ({u})", m.display_name()),
+        None => u.to_string(),
+    };
+    for (i, names) in names.iter().enumerate() {
+        report += &option_header(&options, i, names.len());
+        for n in names {
+            report += &match resolve_name(n, &member_names) {
+                Resolution::Found(m) => format!("  {n} → {} ({:?})\n", member_name(&m.user_id), m.stage),
+                Resolution::Ambiguous(stage, candidates) => format!("  {n} → one of {} ({stage:?})\n", candidates.len()),
+                Resolution::NotFound => format!("  {n} → -\n"),
+            };
+        }
+    }
+    report += &format!("Matched {} of {} names to {} members\n",
+        converted.ids.iter().map(|v| v.len()).sum::<usize>(), names.iter().map(|v| v.len()).sum::<usize>(), members.len());
+    for (i, ids) in converted.ids.iter().enumerate() {
+        let twice: Vec<String> = ids.iter().enumerate()
+            .filter(|(j, u)| ids[..*j].contains(u) && !ids[j + 1..].contains(u))
+            .map(|(_, u)| member_name(u))
+            .collect();
+        if !twice.is_empty() {
+            report += &format!("Listed more than once under {}:\n  {}\n", label(&options, i), twice.join("\n  "));
+        }
+    }
+    if !converted.not_found.is_empty() {
+        report += &format!("Not found:\n  {}\n", converted.not_found.join("\n  "));
+    }
+    if !converted.unattributed.is_empty() {
+        report += "Several members go by the name (not counted):\n";
+        for v in &converted.unattributed {
+            let candidates: Vec<String> = v.candidates.iter().map(member_name).collect();
+            report += &format!("  {} ({}): {}\n", v.name, label(&options, v.option), candidates.join(", "));
+        }
+    }
+    if !converted.told_apart.is_empty() {
+        report += "Same names told apart:\n";
+        for (n, u, how) in &converted.told_apart {
+            let how = match how {
+                Tiebreak::AllVoted => "everyone with the name voted",
+                Tiebreak::Reaction => "by the reactions",
+                Tiebreak::ChannelRole => "by the channel's role",
+            };
+            report += &format!("  {n} → {} ({how})\n", member_name(u));
+        }
+    }
+    if !converted.uncertain.is_empty() {
+        report += "Uncertain matches:\n";
+        for (n, m) in &converted.uncertain {
+            report += &format!("  {n} → {} ({:?}, {:.0}%)\n", member_name(&m.user_id), m.stage, m.confidence * 100.0);
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(not(feature = "third_party_bots"))]
+pub fn analyse(_msg: &Message, _members: Option<&[Member]>) -> Result<String, String>
+{
+    Err("pollbot was built without the third_party_bots feature, there is nothing to analyse.".to_string())
+}
+
+fn describe_message(msg: &Message) -> String
+{
+    match msg.guild_id {
+        Some(g_id) => msg.id.link(msg.channel_id, Some(g_id)),
+        None => format!("message {} in channel {}", msg.id, msg.channel_id),
+    }
+}
+
+#[cfg(feature = "third_party_bots")]
+fn label(options: &[poll_sources::PollOption], i: usize) -> &str
+{
+    options.get(i).map_or("?", |o| o.label.as_str())
+}
+
+#[cfg(feature = "third_party_bots")]
+fn option_header(options: &[poll_sources::PollOption], i: usize, n: usize) -> String
+{
+    format!("{} ({n}):\n", label(options, i))
+}
+
+
+// saves message-<id>.json and members-<channel id>.json (the non-bot members who can see the channel) in the out directory
+async fn dump(http: &Http, ch_id: ChannelId, m_id: Option<MessageId>, out: &str) -> Result<(), String>
+{
+    let channel = guild_channel(http, ch_id).await?;
+    let msg = match m_id {
+        Some(m_id) => ch_id.message(http, m_id).await.map_err(|e| format!("Can't get message {m_id}: {e}"))?,
+        None => last_poll(http, ch_id).await?,
+    };
+
+    // threads are seen by whoever sees their parent channel
+    let visible_in = match channel.parent_id {
+        Some(parent) if channel.thread_metadata.is_some() => guild_channel(http, parent).await?,
+        _ => channel.clone(),
+    };
+    let guild = channel.guild_id.to_partial_guild(http).await.map_err(|e| format!("Can't get the server: {e}"))?;
+    let mut members: Vec<Member> = Vec::new();
+    let mut after: Option<UserId> = None;
+    loop {
+        let page = channel.guild_id.members(http, Some(MEMBERS_PAGE), after).await
+            .map_err(|e| format!("Can't list the members (does the bot have the Server Members intent?): {e}"))?;
+        after = page.last().map(|m| m.user.id);
+        let n = page.len();
+        members.extend(page.into_iter().filter(|m| !m.user.bot && guild.user_permissions_in(&visible_in, m).view_channel()));
+        if n < MEMBERS_PAGE as usize {break;}
+    }
+
+    let message_path = format!("{out}/message-{}.json", msg.id);
+    let members_path = format!("{out}/members-{ch_id}.json");
+    write_json(&message_path, &msg)?;
+    write_json(&members_path, &members)?;
+    println!("Saved the message by {} ({}) to {message_path}", msg.author.name, describe_message(&msg));
+    println!("Saved {} members of #{} to {members_path}", members.len(), channel.name);
+    println!("Run: pollbot analyse {message_path} {members_path}");
+    Ok(())
+}
+
+async fn guild_channel(http: &Http, ch_id: ChannelId) -> Result<GuildChannel, String>
+{
+    match ch_id.to_channel(http).await {
+        Ok(ch) => ch.guild().ok_or(format!("{ch_id} is not a server channel")),
+        Err(e) => Err(format!("Can't get channel {ch_id}: {e}")),
+    }
+}
+
+// the last message any of the poll sources recognises
+async fn last_poll(http: &Http, ch_id: ChannelId) -> Result<Message, String>
+{
+    let own_id = http.get_current_user().await.map_err(|e| format!("Can't get the bot's user: {e}"))?.id;
+    let mut before: Option<MessageId> = None;
+    let mut seen = 0;
+    while seen < MAX_MESSAGES {
+        let builder = match before {
+            Some(m_id) => GetMessages::new().before(m_id).limit(100),
+            None => GetMessages::new().limit(100),
+        };
+        let page = ch_id.messages(http, builder).await.map_err(|e| format!("Can't get the messages of {ch_id}: {e}"))?;
+        if let Some(msg) = page.iter().find(|m| poll_sources::find_source(m, own_id).is_some()) {
+            return Ok(msg.clone());
+        }
+        if page.len() < 100 {break;}
+        seen += page.len();
+        before = page.last().map(|m| m.id);
+    }
+    Err(format!("No poll in the last {MAX_MESSAGES} messages of {ch_id}, give the message id"))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn commands_are_told_from_the_token() {
+        assert!(parse(&args("")).is_none());
+        assert!(parse(&args("MTIz.token")).is_none());
+        assert!(matches!(parse(&args("analyse m.json")), Some(Ok(Command::Analyse { members: None, .. }))));
+        assert!(matches!(parse(&args("analyse m.json members.json")), Some(Ok(Command::Analyse { members: Some(_), .. }))));
+        assert!(matches!(parse(&args("dump 123 --out /tmp")), Some(Ok(Command::Dump { message: None, out, .. })) if out == "/tmp"));
+        assert!(matches!(parse(&args("dump 123 456")), Some(Ok(Command::Dump { message: Some(_), .. }))));
+        assert!(matches!(parse(&args("dump #general")), Some(Err(_))));
+        assert!(matches!(parse(&args("analyse")), Some(Err(_))));
+    }
+
+    // members the way Discord sends them (and dump saves them)
+    #[cfg(feature = "third_party_bots")]
+    fn members() -> Vec<Member> {
+        let member = |id: u64, username: &str, nick: Option<&str>| serde_json::json!({
+            "user": {"id": id.to_string(), "username": username, "discriminator": "0", "global_name": null, "avatar": null},
+            "nick": nick,
+            "roles": [],
+            "joined_at": "2024-01-01T00:00:00Z",
+            "deaf": false,
+            "mute": false,
+            "flags": 0,
+        });
+        let members: Vec<Member> = serde_json::from_value(serde_json::json!([
+            member(1, "alice", None),
+            member(2, "bob_the_builder", None),
+            member(3, "zhenya", Some("Женя")),
+            member(4, "carol", Some("Carol")),
+            member(5, "carol.b", Some("Carol")),
+        ])).unwrap();
+        // what dump writes can be read back
+        serde_json::from_str(&serde_json::to_string(&members).unwrap()).unwrap()
+    }
+
+    #[cfg(feature = "third_party_bots")]
+    fn apollo_poll() -> Message {
+        let mut msg = Message::default();
+        msg.id = MessageId::new(500);
+        msg.channel_id = ChannelId::new(10);
+        msg.author.id = UserId::new(crate::poll_sources::apollo::default_config().id);
+        msg.embeds.push(serde_json::from_str(include_str!("../tests/fixtures/tpbot/apollo.json")).unwrap());
+        msg
+    }
+
+    #[cfg(feature = "third_party_bots")]
+    #[test]
+    fn names_are_listed_by_option() {
+        let report = analyse(&apollo_poll(), None).unwrap();
+        assert_eq!(report, "Poll by Apollo (message 500 in channel 10)\n\
+            Accepted (3):\n  Alice\n  Bob\\_the\\_builder\n  Женя\n\
+            Declined (1):\n  Carol\n\
+            Tentative (0):\n");
+    }
+
+    #[cfg(feature = "third_party_bots")]
+    #[test]
+    fn names_are_matched_to_members() {
+        let report = analyse(&apollo_poll(), Some(&members())).unwrap();
+        assert!(report.contains("  Alice → alice (1) (Username)\n"), "{report}");
+        assert!(report.contains("  Bob\\_the\\_builder → bob_the_builder (2) (Username)\n"), "{report}");
+        assert!(report.contains("  Женя → Женя (3) (Nick)\n"), "{report}");
+        assert!(report.contains("Matched 3 of 4 names to 5 members\n"), "{report}");
+        assert!(report.contains("Several members go by the name (not counted):\n  Carol (Declined): Carol (4), Carol (5)\n"), "{report}");
+
+        let mut not_a_poll = apollo_poll();
+        not_a_poll.author.id = UserId::new(1);
+        assert!(analyse(&not_a_poll, None).is_err());
+    }
+}
@@ -1,7 +1,131 @@
+use std::sync::Arc;
+
+use serenity::all::{ChannelId, ChannelType, CommandDataOptionValue, CommandInteraction, CommandOptionType, Context, CreateCommandOption, GuildId, Message};
+use serenity::builder::CreateCommand;
+
+use crate::i18n::{self, tr};
+use crate::poll_sources;
+use crate::storage::{self, PollStore};
+use crate::utils::{self, PollRef};
+
+pub mod context_menu;
+#[cfg(feature = "poll_creation")]
+pub mod close_poll;
+#[cfg(feature = "poll_creation")]
 pub mod new_poll;
+pub mod export;
 pub mod get_accepted;
+pub mod get_option;
 pub mod get_not_in_voice;
 pub mod get_no_vote;
 pub mod get_tentative;
 pub mod lineup;
+pub mod set_deadline;
+pub mod set_event_time;
+pub mod stats;
+#[cfg(feature = "scheduled_polls")]
+pub mod schedule;
 pub mod test;
+
+// names of all the commands that can be registered
+pub const NAMES: &[&str] = &[
+    "lineup",
+    "test",
+    "get_accepted",
+    "get_tentative",
+    "get_no_vote",
+    "get_not_in_voice",
+    "get_option",
+    "set_deadline",
+    "set_event_time",
+    "stats",
+    "export",
+    context_menu::NO_VOTE,
+    context_menu::NOT_IN_VOICE,
+    context_menu::LINEUP,
+    #[cfg(feature = "poll_creation")]
+    "new_poll",
+    #[cfg(feature = "poll_creation")]
+    "close_poll",
+    #[cfg(feature = "scheduled_polls")]
+    "schedule",
+];
+
+// returns the command builder by its name
+pub fn register(name: &str) -> Option<CreateCommand> {
+    Some(match name {
+        "lineup" => lineup::register(),
+        "test" => test::register(),
+        "get_accepted" => get_accepted::register(),
+        "get_tentative" => get_tentative::register(),
+        "get_no_vote" => get_no_vote::register(),
+        "get_not_in_voice" => get_not_in_voice::register(),
+        "get_option" => get_option::register(),
+        "set_deadline" => set_deadline::register(),
+        "set_event_time" => set_event_time::register(),
+        "stats" => stats::register(),
+        "export" => export::register(),
+        context_menu::NO_VOTE | context_menu::NOT_IN_VOICE | context_menu::LINEUP => context_menu::register(name),
+        #[cfg(feature = "poll_creation")]
+        "new_poll" => new_poll::register(),
+        #[cfg(feature = "poll_creation")]
+        "close_poll" => close_poll::register(),
+        #[cfg(feature = "scheduled_polls")]
+        "schedule" => schedule::register(),
+        _ => return None,
+    })
+}
+
+
+// "poll" option shared by the query commands: a message link or id of the poll to look at
+pub fn poll_option() -> CreateCommandOption {
+    i18n::option(CommandOptionType::String, "poll", "options.poll")
+        .required(false)
+}
+
+// "role" option of the commands that list members: only the members with the role are listed
+pub fn role_option() -> CreateCommandOption {
+    i18n::option(CommandOptionType::Role, "role", "options.role")
+        .required(false)
+}
+
+// "voice_channel" option of the commands that look at voice: only that channel (or the channels of that category) counts
+pub fn voice_channel_option() -> CreateCommandOption {
+    i18n::option(CommandOptionType::Channel, "voice_channel", "options.voice_channel")
+        .channel_types(vec![ChannelType::Voice, ChannelType::Stage, ChannelType::Category])
+        .required(false)
+}
+
+pub fn voice_channel(ci: &CommandInteraction) -> Option<ChannelId> {
+    match option_value(ci, "voice_channel") {
+        Some(CommandDataOptionValue::Channel(ch_id)) => Some(*ch_id),
+        _ => None,
+    }
+}
+
+// returns the value of the command's option by its name
+pub fn option_value<'a>(ci: &'a CommandInteraction, name: &str) -> Option<&'a CommandDataOptionValue> {
+    ci.data.options.iter().find(|o| o.name == name).map(|o| &o.value)
+}
+
+pub fn option_str<'a>(ci: &'a CommandInteraction, name: &str) -> Option<&'a str> {
+    option_value(ci, name)?.as_str()
+}
+
+
+// the poll from the "poll" option (the last one in the channel by default), recorded in the store
+// for the commands that attach something to the poll
+pub async fn poll_in_store(ctx: &Context, ci: &CommandInteraction, g_id: GuildId) -> Result<(Arc<PollStore>, Message), String> {
+    let loc = i18n::command_locale(ci);
+    let Some(store) = storage::get(ctx).await else {
+        return Err(tr!(loc, "store.unavailable"));
+    };
+    let msg = utils::find_poll(ctx, g_id, ci.channel_id, PollRef::from_option(option_str(ci, "poll")), loc).await?;
+    let own_id = ctx.cache.current_user().id;
+    let Some(source) = poll_sources::find_source(&msg, own_id) else {
+        return Err(tr!(loc, "poll_ref.unsupported"));
+    };
+    store.record_poll(&utils::stored_poll_from_message(&msg, g_id, source))
+        .map_err(|e| tr!(loc, "store.save_failed", error = e))?;
+    Ok((store, msg))
+}
@@ -0,0 +1,25 @@
+use serenity::{all::{CommandInteraction, Context, GuildId, Permissions}, builder::CreateCommand};
+
+use crate::commands;
+use crate::i18n;
+use crate::poll_closing;
+use crate::utils::{self, PollRef};
+
+pub async fn run(ctx: &Context, ci: &CommandInteraction, g_id: GuildId){
+    let loc = i18n::command_locale(ci);
+    let reply = match utils::find_poll(ctx, g_id, ci.channel_id, PollRef::from_option(commands::option_str(ci, "poll")), loc).await {
+        Ok(msg) => poll_closing::close(ctx, msg, g_id, &ci.user.name, loc).await,
+        Err(e) => Err(e),
+    };
+    let reply = match reply {
+        Ok(r) => r,
+        Err(e) => e,
+    };
+    utils::send_ephemeral_followup(ctx, &reply, ci).await;
+}
+
+pub fn register() -> CreateCommand {
+    i18n::command("close_poll")
+        .default_member_permissions(Permissions::MANAGE_MESSAGES)
+        .add_option(commands::poll_option())
+}
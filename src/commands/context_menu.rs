@@ -0,0 +1,55 @@
+// Message context menu commands (right click on a poll -> Apps), they work on exactly that message
+use serenity::{all::{CommandInteraction, CommandType, Context, GuildId, ResolvedTarget}, builder::CreateCommand};
+
+use crate::commands::lineup;
+use crate::i18n::{self, tr};
+use crate::poll_sources::OptionKind;
+use crate::utils::{self, OptionQuery, PollRef, UserComparison};
+
+pub const NO_VOTE: &str = "Who hasn't voted";
+pub const NOT_IN_VOICE: &str = "Accepted but not in voice";
+pub const LINEUP: &str = "Build lineup";
+
+pub async fn run(ctx: &Context, ci: &CommandInteraction, g_id: GuildId){
+    let Some(ResolvedTarget::Message(msg)) = ci.data.target() else {
+        utils::send_ephemeral_followup(ctx, &tr!(i18n::command_locale(ci), "context_menu.not_a_message"), ci).await;
+        return;
+    };
+    match ci.data.name.as_str() {
+        NO_VOTE => utils::compare_channel_members_to_poll_and_respond(
+            ctx,
+            ci,
+            g_id,
+            UserComparison::NotSelectedOption,
+            None,
+            PollRef::Message(msg),
+        ).await,
+        NOT_IN_VOICE => utils::compare_channel_members_to_poll_and_respond(
+            ctx,
+            ci,
+            g_id,
+            UserComparison::SelectedOptionNotInVoice(OptionQuery::Kind(OptionKind::Accepted), None),
+            None,
+            PollRef::Message(msg),
+        ).await,
+        LINEUP => lineup::post_lineup(ctx, ci, g_id, PollRef::Message(msg), &lineup::LineupOptions {
+            from_poll: true,
+            ..Default::default()
+        }).await,
+        _ => println!("Unknown context menu command {}", ci.data.name),
+    }
+}
+
+// context menu commands have no descriptions, only the names are translated
+pub fn register(name: &str) -> CreateCommand {
+    let key = match name {
+        NO_VOTE => "context_menu.no_vote",
+        NOT_IN_VOICE => "context_menu.not_in_voice",
+        _ => "context_menu.lineup",
+    };
+    let mut c = CreateCommand::new(name).kind(CommandType::Message);
+    for locale in i18n::locales().filter(|l| *l != i18n::DEFAULT_LOCALE) {
+        c = c.name_localized(locale, tr!(locale, key));
+    }
+    c
+}
@@ -0,0 +1,159 @@
+// /export - the poll results as a CSV or JSON file, one row per channel member (for spreadsheets)
+use serde::Serialize;
+use serenity::{all::{CommandDataOptionValue, CommandInteraction, CommandOptionType, Context, CreateAttachment,
+    CreateInteractionResponseFollowup, GuildId, Member, UserId}, builder::CreateCommand};
+
+use crate::commands;
+use crate::i18n::{self, tr};
+use crate::utils::{self, PollRef, PollResults};
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ExportRow {
+    pub user_id: String,        // a string, spreadsheets mangle 19-digit numbers
+    pub display_name: String,
+    pub nick: Option<String>,
+    pub options: Vec<String>,   // labels of the selected options, empty if none
+    pub roles: Vec<String>,
+    pub voice_channel: Option<String>,
+}
+
+pub async fn run(ctx: &Context, ci: &CommandInteraction, g_id: GuildId){
+    let loc = i18n::command_locale(ci);
+    let role = match commands::option_value(ci, "role") {
+        Some(CommandDataOptionValue::Role(roleid)) => Some(*roleid),
+        _ => None,
+    };
+    let json = commands::option_str(ci, "format") == Some("json");
+
+    let results = match utils::get_poll_results(ctx, g_id, ci.channel_id, PollRef::from_option(commands::option_str(ci, "poll")), loc).await {
+        Ok(r) => r,
+        Err(e) => {
+            utils::send_ephemeral_followup(ctx, &e, ci).await;
+            return;
+        },
+    };
+    let mut members = results.channel_members.clone();
+    if let Some(r) = role {
+        members.retain(|m| m.roles.contains(&r));
+    }
+    let Some(rows) = export_rows(ctx, g_id, &results, &members) else {
+        utils::send_ephemeral_followup(ctx, &tr!(loc, "export.no_cache"), ci).await;
+        return;
+    };
+
+    let (data, ext) = if json {
+        match serde_json::to_vec_pretty(&rows) {
+            Ok(d) => (d, "json"),
+            Err(e) => {
+                utils::send_ephemeral_followup(ctx, &tr!(loc, "export.failed", error = e), ci).await;
+                return;
+            },
+        }
+    } else {
+        (to_csv(&rows).into_bytes(), "csv")
+    };
+    let mut text = format!("{}\n{}", results.describe(loc), tr!(loc, "export.members", n = rows.len()));
+    if !results.warnings.is_empty() {
+        text += &format!("\n{}", results.warnings);
+    }
+    let followup = CreateInteractionResponseFollowup::new()
+        .content(text)
+        .add_file(CreateAttachment::bytes(data, format!("poll-{}.{ext}", results.msg.id)))
+        .ephemeral(true);
+    if let Err(why) = ci.create_followup(&ctx.http, followup).await {
+        println!("Cannot respond to slash command: {why}");
+    }
+}
+
+// everything is read from the cache at once, the guild can't be held across an await
+fn export_rows(ctx: &Context, g_id: GuildId, results: &PollResults, members: &[Member]) -> Option<Vec<ExportRow>> {
+    let guild = g_id.to_guild_cached(&ctx)?;
+    let rows = members.iter().map(|m| {
+        ExportRow {
+            user_id: m.user.id.to_string(),
+            display_name: m.user.display_name().to_string(),
+            nick: m.nick.clone(),
+            options: selected_options(results, m.user.id),
+            roles: m.roles.iter().filter_map(|r| guild.roles.get(r)).map(|r| r.name.clone()).collect(),
+            voice_channel: guild.voice_states.get(&m.user.id)
+                .and_then(|v| v.channel_id)
+                .map(|ch| guild.channels.get(&ch).map_or(ch.to_string(), |c| c.name.clone())),
+        }
+    }).collect();
+    Some(rows)
+}
+
+fn selected_options(results: &PollResults, u_id: UserId) -> Vec<String> {
+    results.options.iter().zip(&results.responses)
+        .filter(|(_, voters)| voters.contains(&u_id))
+        .map(|(o, _)| o.label.clone())
+        .collect()
+}
+
+
+// the lists are joined with "; ", fields are quoted when needed (RFC 4180)
+pub fn to_csv(rows: &[ExportRow]) -> String {
+    fn field(s: &str) -> String {
+        if s.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        } else {
+            s.to_string()
+        }
+    }
+    let mut csv = "user_id,display_name,nick,option,roles,voice_channel\r\n".to_string();
+    for r in rows {
+        let fields = [
+            field(&r.user_id),
+            field(&r.display_name),
+            field(r.nick.as_deref().unwrap_or_default()),
+            field(&r.options.join("; ")),
+            field(&r.roles.join("; ")),
+            field(r.voice_channel.as_deref().unwrap_or_default()),
+        ];
+        csv += &fields.join(",");
+        csv += "\r\n";
+    }
+    csv
+}
+
+
+pub fn register() -> CreateCommand {
+    i18n::command("export")
+        .add_option(i18n::option(CommandOptionType::String, "format", "commands.export.format")
+            .add_string_choice("CSV", "csv")
+            .add_string_choice("JSON", "json")
+            .required(false))
+        .add_option(commands::role_option())
+        .add_option(commands::poll_option())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_quoted() {
+        let rows = vec![
+            ExportRow {
+                user_id: "1".to_string(),
+                display_name: "Plain".to_string(),
+                nick: None,
+                options: Vec::new(),
+                roles: Vec::new(),
+                voice_channel: None,
+            },
+            ExportRow {
+                user_id: "2".to_string(),
+                display_name: "Smith, \"Agent\"".to_string(),
+                nick: Some("Neo".to_string()),
+                options: vec!["Tank".to_string(), "Healer".to_string()],
+                roles: vec!["Raider".to_string()],
+                voice_channel: Some("Raid".to_string()),
+            },
+        ];
+        assert_eq!(to_csv(&rows), "user_id,display_name,nick,option,roles,voice_channel\r\n\
+            1,Plain,,,,\r\n\
+            2,\"Smith, \"\"Agent\"\"\",Neo,Tank; Healer,Raider,Raid\r\n");
+    }
+}
@@ -1,39 +1,27 @@
-use serenity::{all::{CommandDataOptionValue, CommandInteraction, Context, CreateCommandOption, GuildId, Role}, builder::CreateCommand};
+use serenity::{all::{CommandDataOptionValue, CommandInteraction, Context, GuildId}, builder::CreateCommand};
 
-use crate::utils::{self, UserComparison};
+use crate::commands;
+use crate::i18n;
+use crate::utils::{self, PollRef, UserComparison};
 
 pub async fn run(ctx: &Context, ci: &CommandInteraction, g_id: GuildId){
-    //TODO parse option
-    let mut role = None;
-    if ci.data.options.len() > 0 
-        && ci.data.options[0].name == "role"{
-            match ci.data.options[0].value {
-                CommandDataOptionValue::Role(roleid) => {
-                    role = ci.data.resolved.roles.get(&roleid);
-                }
-                _ =>{},
-            }
-        }
+    let role = match commands::option_value(ci, "role") {
+        Some(CommandDataOptionValue::Role(roleid)) => ci.data.resolved.roles.get(roleid),
+        _ => None,
+    };
 
     utils::compare_channel_members_to_poll_and_respond(
         ctx, 
         ci, 
         g_id, 
-        UserComparison::MembersNotSelectedOption, 
-        None,
+        UserComparison::NotSelectedOption,
         role,
+        PollRef::from_option(commands::option_str(ci, "poll")),
     ).await;
 }
 
 pub fn register() -> CreateCommand {
-    let option = CreateCommandOption::new(
-        serenity::all::CommandOptionType::Role,
-        "role",
-        "Role to narrow the members down to (optional)")
-        .description_localized("ru", "Ограничить список пользователей конкретной ролью (необязательно)")
-        .required(false);
-    CreateCommand::new("get_no_vote")
-        .description("Get the list of all members (mentionable) who have access to the channel, but haven't voted 👀")
-        .description_localized("ru", "Получить список всех пользователей, кто видит опрос, но не выбрал никакой вариант 👀.")
-        .add_option(option)
-}
\ No newline at end of file
+    i18n::command("get_no_vote")
+        .add_option(commands::role_option())
+        .add_option(commands::poll_option())
+}
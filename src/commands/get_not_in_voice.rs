@@ -1,20 +1,23 @@
 use serenity::{all::{CommandInteraction, Context, GuildId}, builder::CreateCommand};
 
-use crate::utils::{self, UserComparison};
+use crate::commands;
+use crate::i18n;
+use crate::poll_sources::OptionKind;
+use crate::utils::{self, OptionQuery, PollRef, UserComparison};
 
 pub async fn run(ctx: &Context, ci: &CommandInteraction, g_id: GuildId){
         utils::compare_channel_members_to_poll_and_respond(
                 ctx, 
                 ci, 
                 g_id, 
-                UserComparison::MembersSelectedOptionNotInVoice, 
-                Some(0),
-                None)
+                UserComparison::SelectedOptionNotInVoice(OptionQuery::Kind(OptionKind::Accepted), commands::voice_channel(ci)),
+                None,
+                PollRef::from_option(commands::option_str(ci, "poll")))
         .await;
 }
 
 pub fn register() -> CreateCommand {
-    CreateCommand::new("get_not_in_voice")
-        .description("Get the list of users who selected \"✅\" but are not present in any of the voice channels right now 🔇.")
-        .description_localized("ru", "Получить список всех пользователей, кто выбрал \"✅\", но отсутствует в голосовых каналах 🔇.")
-}
\ No newline at end of file
+    i18n::command("get_not_in_voice")
+        .add_option(commands::poll_option())
+        .add_option(commands::voice_channel_option())
+}
@@ -0,0 +1,51 @@
+use serenity::{all::{CommandInteraction, CommandOptionType, Context, CreateAutocompleteResponse, CreateInteractionResponse, GuildId},
+    builder::CreateCommand};
+
+use crate::commands;
+use crate::i18n;
+use crate::poll_sources;
+use crate::utils::{self, OptionQuery, PollRef, UserComparison};
+
+pub async fn run(ctx: &Context, ci: &CommandInteraction, g_id: GuildId){
+    let option = commands::option_str(ci, "option").unwrap_or_default().to_string();
+    utils::compare_channel_members_to_poll_and_respond(
+        ctx,
+        ci,
+        g_id,
+        UserComparison::SelectedOption(OptionQuery::Name(option)),
+        None,
+        PollRef::from_option(commands::option_str(ci, "poll")),
+    ).await;
+}
+
+
+// suggests the options of the poll (the one from the "poll" option or the last one in the channel)
+pub async fn autocomplete(ctx: &Context, ci: &CommandInteraction){
+    let typed = ci.data.autocomplete().map(|a| a.value.to_lowercase()).unwrap_or_default();
+    let mut response = CreateAutocompleteResponse::new();
+    if let Some(g_id) = ci.guild_id {
+        match utils::find_poll(ctx, g_id, ci.channel_id, PollRef::from_option(commands::option_str(ci, "poll")), i18n::DEFAULT_LOCALE).await {
+            Ok(msg) => {
+                let own_id = ctx.cache.current_user().id;
+                if let Some(source) = poll_sources::find_source(&msg, own_id) {
+                    for o in source.options(&msg).iter().filter(|o| o.label.to_lowercase().contains(&typed)).take(25) {
+                        response = response.add_string_choice(&o.label, &o.label);
+                    }
+                }
+            },
+            Err(e) => println!("get_option autocomplete: {e}"),
+        }
+    }
+    if let Err(e) = ci.create_response(&ctx.http, CreateInteractionResponse::Autocomplete(response)).await {
+        println!("Cannot respond to autocomplete: {e}");
+    }
+}
+
+pub fn register() -> CreateCommand {
+    let option = i18n::option(CommandOptionType::String, "option", "commands.get_option.option")
+        .set_autocomplete(true)
+        .required(true);
+    i18n::command("get_option")
+        .add_option(option)
+        .add_option(commands::poll_option())
+}
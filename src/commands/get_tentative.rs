@@ -1,20 +1,22 @@
 use serenity::{all::{CommandInteraction, Context, GuildId}, builder::CreateCommand};
 
-use crate::utils::{self, UserComparison};
+use crate::commands;
+use crate::i18n;
+use crate::poll_sources::OptionKind;
+use crate::utils::{self, OptionQuery, PollRef, UserComparison};
 
 pub async fn run(ctx: &Context, ci: &CommandInteraction, g_id: GuildId){
         utils::compare_channel_members_to_poll_and_respond(
             ctx, 
             ci, 
             g_id, 
-            UserComparison::MembersSelectedOption, 
-            Some(2),
-            None)
+            UserComparison::SelectedOption(OptionQuery::Kind(OptionKind::Tentative)),
+            None,
+            PollRef::from_option(commands::option_str(ci, "poll")))
         .await;
 }
 
 pub fn register() -> CreateCommand {
-    CreateCommand::new("get_tentative")
-    .description("Get the list of all users (mentionable) who selected \"❔\".")
-    .description_localized("ru", "Получить список всех пользователей (для упоминания), кто выбрал \"❔\".")
-}
\ No newline at end of file
+    i18n::command("get_tentative")
+        .add_option(commands::poll_option())
+}
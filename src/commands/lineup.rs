@@ -1,59 +1,229 @@
 use serenity::builder::CreateCommand;
-use serenity::all::{CommandInteraction, Context, CreateCommandOption, GuildId, MessageBuilder,};
+use serenity::all::{ActionRowComponent, ButtonKind, ButtonStyle, ChannelId, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    ComponentInteraction, Context, CreateActionRow, CreateAllowedMentions, CreateButton, CreateInputText,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, CreateModal, GuildId, InputTextStyle,
+    Member, Message, ModalInteraction, Permissions, RoleId, User, UserId};
 
-use crate::utils;
+use crate::commands;
+use crate::i18n::{self, tr};
+use crate::poll_sources::OptionKind;
+use crate::squads::{self, BalanceOptions, Candidate, Lineup};
+use crate::utils::{self, OptionQuery, PollRef};
 
 const SQUAD_ICONS:[&str; 8] = ["❤️", "💚", "💙", "💛", "🤍", "🖤",
  "💜", "🧡",];
+const DEFAULT_SQUADS: usize = 4;
+// the edit button keeps the id of the member who asked for the lineup
+const EDIT_PREFIX: &str = "lineup_edit:";
+pub const EDIT_MODAL: &str = "lineup_edit_modal";
+const EDIT_INPUT: &str = "lineup_text";
+const LEN_LIMIT_MESSAGE: usize = 2000;
+
+#[derive(Debug, Clone, Default)]
+pub struct LineupOptions {
+    pub squads: Option<usize>,
+    pub max_size: Option<usize>,
+    pub role: Option<RoleId>,       // at least one member with it in every squad
+    pub keep_voice_together: bool,
+    pub from_poll: bool,            // the accepted members of the poll instead of everyone in voice
+    pub voice_channel: Option<ChannelId>, // a voice channel or category instead of the event channels
+}
 
 pub async fn run(ctx: &Context, ci: &CommandInteraction, g_id: GuildId){
-    let num_squads: Option<usize>;
-    if ci.data.options.len() > 0 && ci.data.options[0].name == "number" { //TODO find a better way to validate this
-        num_squads = ci.data.options[0].value.as_i64().map(|s| s as usize);
+    let poll_ref = PollRef::from_option(commands::option_str(ci, "poll"));
+    let opts = LineupOptions {
+        squads: commands::option_value(ci, "number").and_then(|v| v.as_i64()).map(|n| n as usize),
+        max_size: commands::option_value(ci, "size").and_then(|v| v.as_i64()).map(|n| n as usize),
+        role: match commands::option_value(ci, "required_role") {
+            Some(CommandDataOptionValue::Role(roleid)) => Some(*roleid),
+            _ => None,
+        },
+        keep_voice_together: matches!(commands::option_value(ci, "keep_together"), Some(CommandDataOptionValue::Boolean(true))),
+        from_poll: commands::option_str(ci, "from") == Some("poll") || !matches!(poll_ref, PollRef::LastInChannel),
+        voice_channel: commands::voice_channel(ci),
+    };
+    post_lineup(ctx, ci, g_id, poll_ref, &opts).await;
+}
+
+
+// splits everyone in voice (or everyone who accepted the poll) into squads and posts the result to the channel
+pub async fn post_lineup(ctx: &Context, ci: &CommandInteraction, g_id: GuildId, poll_ref: PollRef<'_>, opts: &LineupOptions)
+{
+    let loc = i18n::command_locale(ci);
+    let gloc = i18n::guild_locale(ctx, g_id);
+    let mut header = String::new();
+    let uids: Vec<UserId> = if opts.from_poll {
+        let results = match utils::get_poll_results(ctx, g_id, ci.channel_id, poll_ref, loc).await {
+            Ok(res) => res,
+            Err(e) => {
+                utils::send_ephemeral_followup(ctx, &e, ci).await;
+                return;
+            },
+        };
+        header = format!("{}\n", results.describe(gloc));
+        results.voters(&results.select(&OptionQuery::Kind(OptionKind::Accepted)))
     } else {
-        num_squads = None;
+        utils::get_members_in_voice(ctx, g_id, opts.voice_channel).unwrap_or_default().into_iter().collect()
+    };
+    if uids.is_empty() {
+        utils::send_ephemeral_followup(ctx, &tr!(loc, "lineup.nobody"), ci).await;
+        return;
+    }
+    let Some(candidates) = candidates(ctx, g_id, uids, opts.role) else {
+        utils::send_ephemeral_followup(ctx, &tr!(loc, "lineup.no_cache"), ci).await;
+        return;
+    };
+
+    let squads = match (opts.squads, opts.max_size) {
+        (Some(n), _) => n,
+        (None, Some(size)) => candidates.len().div_ceil(size.max(1)),
+        (None, None) => DEFAULT_SQUADS,
+    }.clamp(1, SQUAD_ICONS.len());
+    let lineup = squads::balance(&candidates, &BalanceOptions {
+        squads,
+        max_size: opts.max_size,
+        keep_voice_together: opts.keep_voice_together,
+    });
+    let text = format!("{header}{}", render(&lineup, opts.role, gloc));
+    if text.chars().count() > LEN_LIMIT_MESSAGE {
+        utils::send_ephemeral_followup(ctx, &tr!(loc, "lineup.too_long", n = candidates.len()), ci).await;
+        return;
     }
-    get_lineup_template(ctx, ci, g_id, num_squads).await;
+
+    // nobody gets pinged by the lineup, it's a draft until the squads are final
+    let builder = CreateMessage::new()
+        .content(text)
+        .allowed_mentions(CreateAllowedMentions::new())
+        .components(vec![edit_row(ci.user.id, gloc)]);
+    let reply = match ci.channel_id.send_message(&ctx.http, builder).await {
+        Ok(msg) => tr!(loc, "lineup.posted", link = msg.link()),
+        Err(e) => tr!(loc, "lineup.failed", error = e),
+    };
+    utils::send_ephemeral_followup(ctx, &reply, ci).await;
 }
 
+// the members sorted by id (the same input gives the same lineup), with their voice channels and the role
+fn candidates(ctx: &Context, g_id: GuildId, mut uids: Vec<UserId>, role: Option<RoleId>) -> Option<Vec<Candidate>> {
+    let guild = g_id.to_guild_cached(&ctx)?;
+    uids.sort();
+    Some(uids.into_iter().map(|u| Candidate {
+        user_id: u,
+        voice_channel: guild.voice_states.get(&u).and_then(|v| v.channel_id),
+        has_role: role.is_some_and(|r| guild.members.get(&u).is_some_and(|m| m.roles.contains(&r))),
+    }).collect())
+}
 
-async fn get_lineup_template(ctx: &Context, ci: &CommandInteraction, g_id: GuildId, num_squads: Option<usize>){
-    let possibly_in_voice = utils::get_all_members_in_voice_cached(ctx, &g_id);
-    let mut in_voice_uids = String::new();
-    if let Some(in_voice) = &possibly_in_voice {
-        for (uid, _state) in in_voice {
-            in_voice_uids += format!("<@{}>\n", uid.to_string()).as_str();
+// a line per squad, then the bench
+fn render(lineup: &Lineup, role: Option<RoleId>, loc: &str) -> String {
+    let mut text = format!("**{}**\n", tr!(loc, "lineup.title"));
+    for (i, squad) in lineup.squads.iter().enumerate() {
+        text += &format!("{} `{}` {}\n", SQUAD_ICONS[i], squad.len(), mentions(squad));
+    }
+    if !lineup.bench.is_empty() {
+        text += &format!("{}\n", tr!(loc, "lineup.bench", n = lineup.bench.len(), members = mentions(&lineup.bench)));
+    }
+    if let Some(r) = role {
+        let without = lineup.role_counts.iter().filter(|c| **c == 0).count();
+        if without > 0 {
+            text += &format!("{}\n", tr!(loc, "lineup.no_role", n = without, role = format!("<@&{r}>")));
         }
     }
-    let num_squads: usize = match num_squads {
-        Some(n) if (n >= 1 && n <= SQUAD_ICONS.len()) => n,
-        _ => 4,
+    text
+}
+
+fn mentions(uids: &[UserId]) -> String {
+    uids.iter().map(|u| format!("<@{u}>")).collect::<Vec<String>>().join(" ")
+}
+
+
+fn edit_row(owner: UserId, loc: &str) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![CreateButton::new(format!("{EDIT_PREFIX}{owner}"))
+        .label(tr!(loc, "lineup.edit"))
+        .emoji('✏')
+        .style(ButtonStyle::Secondary)])
+}
+
+pub fn is_edit_button(custom_id: &str) -> bool {
+    custom_id.starts_with(EDIT_PREFIX)
+}
+
+// whoever asked for the lineup and the members who can manage messages can change it
+fn may_edit(msg: &Message, user: &User, member: Option<&Member>) -> bool {
+    let owner = msg.components.iter()
+        .flat_map(|row| &row.components)
+        .find_map(|c| match c {
+            ActionRowComponent::Button(b) => match &b.data {
+                ButtonKind::NonLink { custom_id, .. } => custom_id.strip_prefix(EDIT_PREFIX)?.parse::<UserId>().ok(),
+                _ => None,
+            },
+            _ => None,
+        });
+    owner == Some(user.id) || member.and_then(|m| m.permissions).is_some_and(|p| p.contains(Permissions::MANAGE_MESSAGES))
+}
+
+// the edit button opens the text of the lineup in a form
+pub async fn handle_edit_click(ctx: &Context, ci: &ComponentInteraction) {
+    let loc = i18n::user_locale(&ci.locale, ci.guild_locale.as_deref());
+    let response = if may_edit(&ci.message, &ci.user, ci.member.as_ref()) {
+        let input = CreateInputText::new(InputTextStyle::Paragraph, tr!(loc, "lineup.edit"), EDIT_INPUT)
+            .value(ci.message.content.clone())
+            .max_length(LEN_LIMIT_MESSAGE as u16);
+        CreateInteractionResponse::Modal(CreateModal::new(EDIT_MODAL, tr!(loc, "lineup.edit_title"))
+            .components(vec![CreateActionRow::InputText(input)]))
+    } else {
+        CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+            .content(tr!(loc, "lineup.not_allowed"))
+            .ephemeral(true))
+    };
+    if let Err(why) = ci.create_response(&ctx.http, response).await {
+        println!("Cannot respond to the lineup edit: {why}");
+    }
+}
+
+// replaces the lineup with the text from the form
+pub async fn handle_edit_submit(ctx: &Context, mi: &ModalInteraction) {
+    let loc = i18n::user_locale(&mi.locale, mi.guild_locale.as_deref());
+    let text = mi.data.components.iter()
+        .flat_map(|row| &row.components)
+        .find_map(|c| match c {
+            ActionRowComponent::InputText(t) if t.custom_id == EDIT_INPUT => t.value.clone(),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let response = match &mi.message {
+        Some(msg) if may_edit(msg, &mi.user, mi.member.as_ref()) && !text.trim().is_empty() =>
+            CreateInteractionResponse::UpdateMessage(CreateInteractionResponseMessage::new()
+                .content(text)
+                .allowed_mentions(CreateAllowedMentions::new())),
+        _ => CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+            .content(tr!(loc, "lineup.not_allowed"))
+            .ephemeral(true)),
     };
-    let mut reply = MessageBuilder::new();
-    reply.push("```Lineup:\n");
-    for i in 0..num_squads {
-        reply.push(SQUAD_ICONS[i])
-            .push("\n\n");
+    if let Err(why) = mi.create_response(&ctx.http, response).await {
+        println!("Cannot update the lineup: {why}");
     }
-    reply.push("\n\n")
-        .push(in_voice_uids)
-        .push("```");
-    let t = reply.build();
-    utils::send_ephemeral_followup(ctx, &t, ci).await;
 }
 
+
 pub fn register() -> CreateCommand {
-    let option = CreateCommandOption::new(
-        serenity::all::CommandOptionType::Integer,
-        "number",
-        "Number of color-coded squads in the template (1-8, default 4)")
-        .min_int_value(1)
-        .max_int_value(SQUAD_ICONS.len() as u64)
-        .required(false)
-        .name_localized("ru", "количество")
-        .description_localized("ru", "Количество отрядов (1-8, по умолчанию 4)");
-    CreateCommand::new("lineup")
-        .description("Get lineup template with all members currently in voice channels 💙💚💛.")
-        .description_localized("ru","Получить шаблон для расписывания по отрядам тех, кто сейчас в голосовых каналах 💙💚💛.")
-        .add_option(option)
+    let from = i18n::option(CommandOptionType::String, "from", "commands.lineup.from")
+        .required(false);
+    let from = i18n::string_choice(from, "commands.lineup.from_voice", "voice");
+    let from = i18n::string_choice(from, "commands.lineup.from_poll", "poll");
+    i18n::command("lineup")
+        .add_option(i18n::option(CommandOptionType::Integer, "number", "commands.lineup.number")
+            .min_int_value(1)
+            .max_int_value(SQUAD_ICONS.len() as u64)
+            .required(false))
+        .add_option(from)
+        .add_option(i18n::option(CommandOptionType::Integer, "size", "commands.lineup.size")
+            .min_int_value(1)
+            .max_int_value(100)
+            .required(false))
+        .add_option(i18n::option(CommandOptionType::Role, "required_role", "commands.lineup.required_role")
+            .required(false))
+        .add_option(i18n::option(CommandOptionType::Boolean, "keep_together", "commands.lineup.keep_together")
+            .required(false))
+        .add_option(commands::poll_option())
+        .add_option(commands::voice_channel_option())
 }
@@ -1,9 +1,146 @@
 use serenity::builder::CreateCommand;
+use serenity::all::{ChannelType, CommandInteraction, CommandOptionType, Context, CreateEmbed, GuildId, Timestamp};
+
+use crate::commands;
+use crate::config::{self, OwnPollOption};
+use crate::i18n::{self, tr};
+use crate::poll_sources::OptionKind;
+use crate::utils;
+
+// everything the poll is created with
+pub struct NewPoll {
+    pub title: String,
+    pub description: Option<String>,
+    pub event_at: Option<Timestamp>,
+    pub deadline: Option<Timestamp>,    //members who haven't voted are reminded before it
+    pub options: Vec<OwnPollOption>,
+}
+
+impl NewPoll {
+    // the embed above the lists of voters
+    pub fn embed(&self, loc: &str) -> CreateEmbed {
+        let mut embed = CreateEmbed::new().title(&self.title);
+        if let Some(d) = &self.description {
+            embed = embed.description(d);
+        }
+        if let Some(t) = self.event_at {
+            let t = t.unix_timestamp();
+            embed = embed.field(tr!(loc, "poll.when"), format!("<t:{t}:F> (<t:{t}:R>)"), false);
+        }
+        if let Some(t) = self.deadline {
+            let t = t.unix_timestamp();
+            embed = embed.field(tr!(loc, "poll.vote_until"), format!("<t:{t}:F> (<t:{t}:R>)"), false);
+        }
+        embed
+    }
+}
+
+
+pub async fn run(ctx: &Context, ci: &CommandInteraction, g_id: GuildId){
+    let loc = i18n::command_locale(ci);
+    let title = commands::option_str(ci, "title").unwrap_or("Poll").to_string();
+    let description = commands::option_str(ci, "description").map(|d| d.to_string());
+    let event_at = match commands::option_str(ci, "when") {
+        Some(w) => match utils::parse_event_time(w, loc) {
+            Ok(t) => Some(t),
+            Err(e) => {
+                utils::send_ephemeral_followup(ctx, &e, ci).await;
+                return;
+            },
+        },
+        None => None,
+    };
+    let deadline = match commands::option_str(ci, "deadline") {
+        Some(d) => match utils::parse_event_time(d, loc) {
+            Ok(t) if t.unix_timestamp() > Timestamp::now().unix_timestamp() => Some(t),
+            Ok(_) => {
+                utils::send_ephemeral_followup(ctx, &tr!(loc, "deadline.in_the_past"), ci).await;
+                return;
+            },
+            Err(e) => {
+                utils::send_ephemeral_followup(ctx, &e, ci).await;
+                return;
+            },
+        },
+        None => None,
+    };
+    let options = match commands::option_str(ci, "options") {
+        Some(o) => match parse_options(o, loc) {
+            Ok(options) => options,
+            Err(e) => {
+                utils::send_ephemeral_followup(ctx, &e, ci).await;
+                return;
+            },
+        },
+        None => config::get().own_poll_options(),
+    };
+    let channel_id = match commands::option_value(ci, "channel").and_then(|v| v.as_channel_id()) {
+        Some(ch_id) => ch_id,
+        None => ci.channel_id,
+    };
+
+    let poll = NewPoll { title, description, event_at, deadline, options };
+    let reply = match crate::create_new_poll(ctx, channel_id, &g_id, &ci.user, &poll, loc).await {
+        Ok(r) => r,
+        Err(e) => tr!(loc, "new_poll.failed", error = e),
+    };
+    utils::send_ephemeral_followup(ctx, &reply, ci).await;
+}
+
+
+// "🛡️ Tank, 💚 Healer, ⚔️ DPS, ❌ Can't come", options without an emoji get a number
+// the kind of an option is taken from the configured option with the same emoji, the rest count as accepted
+pub fn parse_options(s: &str, loc: &str) -> Result<Vec<OwnPollOption>, String>
+{
+    const NUMBERS: [&str; 10] = ["1️⃣", "2️⃣", "3️⃣", "4️⃣", "5️⃣", "6️⃣", "7️⃣", "8️⃣", "9️⃣", "🔟"];
+    let configured = config::get().own_poll_options();
+    let mut options: Vec<OwnPollOption> = Vec::new();
+    for (i, entry) in s.split([',', ';', '\n']).map(|e| e.trim()).filter(|e| !e.is_empty()).enumerate() {
+        let (emoji, label) = match entry.split_once(char::is_whitespace) {
+            Some((e, l)) if !e.starts_with(char::is_alphanumeric) => (e.to_string(), l),
+            _ if !entry.starts_with(char::is_alphanumeric) => return Err(tr!(loc, "new_poll.option_without_name", option = entry)),
+            _ => (NUMBERS.get(i).unwrap_or(&"▫️").to_string(), entry),
+        };
+        if options.iter().any(|o| o.emoji == emoji) {
+            return Err(tr!(loc, "new_poll.emoji_used_twice", emoji = emoji));
+        }
+        let kind = match configured.iter().find(|o| o.emoji == emoji) {
+            Some(o) => o.kind,
+            None => OptionKind::Accepted,
+        };
+        options.push(OwnPollOption { emoji, label: sanitize_label(label), kind });
+    }
+    if options.is_empty() || options.len() > config::MAX_OPTIONS {
+        return Err(tr!(loc, "new_poll.option_count", max = config::MAX_OPTIONS, n = options.len()));
+    }
+    Ok(options)
+}
+
+
+// option labels end up between the underscores of the section headers, so they can't have any
+fn sanitize_label(l: &str) -> String
+{
+    let l: String = l.replace(['\n', '_'], " ").trim().to_string();
+    if l.is_empty() {"-".to_string()} else {l}
+}
 
-// pub fn run(ctx: &Context, _options: &[ResolvedOption]) -> String {
-//     "new_poll.run".to_string()
-// }
 
 pub fn register() -> CreateCommand {
-    CreateCommand::new("new_poll").description("Create new poll")
-}
\ No newline at end of file
+    i18n::command("new_poll")
+        .add_option(i18n::option(CommandOptionType::String, "title", "commands.new_poll.title")
+            .max_length(256)
+            .required(true))
+        .add_option(i18n::option(CommandOptionType::String, "description", "commands.new_poll.description_option")
+            .max_length(1000)
+            .required(false))
+        .add_option(i18n::option(CommandOptionType::String, "when", "commands.new_poll.when")
+            .required(false))
+        .add_option(i18n::option(CommandOptionType::String, "deadline", "commands.new_poll.deadline")
+            .required(false))
+        .add_option(i18n::option(CommandOptionType::String, "options", "options.poll_options")
+            .max_length(500)
+            .required(false))
+        .add_option(i18n::option(CommandOptionType::Channel, "channel", "commands.new_poll.channel")
+            .channel_types(vec![ChannelType::Text])
+            .required(false))
+}
@@ -0,0 +1,164 @@
+// /schedule add|list|pause|resume|delete - recurring polls, see scheduler.rs
+use chrono::Utc;
+use serenity::builder::CreateCommand;
+use serenity::all::{ChannelType, CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType, Context,
+    CreateCommandOption, GuildId, Permissions};
+
+use crate::config;
+use crate::i18n::{self, tr};
+use crate::scheduler;
+use crate::storage::{self, StoredSchedule};
+use crate::utils;
+
+pub async fn run(ctx: &Context, ci: &CommandInteraction, g_id: GuildId){
+    let loc = i18n::command_locale(ci);
+    let reply = match storage::get(ctx).await {
+        Some(store) => match subcommand(ci) {
+            Some(("add", opts)) => add(ci, g_id, opts, &store, loc),
+            Some(("list", _)) => list(g_id, &store, loc),
+            Some((name @ ("pause" | "resume"), opts)) => pause(g_id, opts, name == "pause", &store, loc),
+            Some(("delete", opts)) => delete(g_id, opts, &store, loc),
+            _ => Err(tr!(loc, "schedule.unknown_subcommand")),
+        },
+        None => Err(tr!(loc, "schedule.no_store")),
+    };
+    let reply = match reply {
+        Ok(r) => r,
+        Err(e) => e,
+    };
+    utils::send_ephemeral_followup(ctx, &reply, ci).await;
+}
+
+
+fn add(ci: &CommandInteraction, g_id: GuildId, opts: &[CommandDataOption], store: &storage::PollStore, loc: &str) -> Result<String, String> {
+    let now = Utc::now();
+    let mut s = StoredSchedule {
+        id: 0,
+        guild_id: g_id,
+        channel_id: match sub_value(opts, "channel").and_then(|v| v.as_channel_id()) {
+            Some(ch_id) => ch_id,
+            None => ci.channel_id,
+        },
+        created_by: ci.user.id,
+        spec: sub_str(opts, "when").unwrap_or_default().trim().to_string(),
+        timezone: sub_str(opts, "timezone").map(|t| t.trim().to_string()).unwrap_or(scheduler::default_timezone()),
+        title: sub_str(opts, "title").unwrap_or("Poll {date}").to_string(),
+        description: sub_str(opts, "description").map(|d| d.to_string()),
+        options: sub_str(opts, "options").map(|o| o.to_string()),
+        paused: false,
+        next_run: None,
+    };
+    let first = scheduler::validate(&s, now, loc)?
+        .ok_or(tr!(loc, "schedule.never_fires", spec = s.spec))?;
+    s.next_run = Some(first.timestamp());
+    s.id = store.add_schedule(&s).map_err(|e| tr!(loc, "schedule.save_failed", error = e))?;
+    println!("{} added schedule #{} in {}", ci.user.name, s.id, g_id);
+    Ok(tr!(loc, "schedule.added", schedule = scheduler::describe(&s, loc)))
+}
+
+fn list(g_id: GuildId, store: &storage::PollStore, loc: &str) -> Result<String, String> {
+    let schedules = store.guild_schedules(g_id).map_err(|e| tr!(loc, "schedule.read_failed", error = e))?;
+    if schedules.is_empty() {
+        return Ok(tr!(loc, "schedule.none"));
+    }
+    let limit = config::get().limits.message_length;
+    let mut text = String::new();
+    for (i, s) in schedules.iter().enumerate() {
+        let line = scheduler::describe(s, loc);
+        if text.len() + line.len() + 20 > limit {
+            text += &tr!(loc, "list.more", n = schedules.len() - i);
+            break;
+        }
+        text += &line;
+        text += "\n";
+    }
+    Ok(text)
+}
+
+fn pause(g_id: GuildId, opts: &[CommandDataOption], paused: bool, store: &storage::PollStore, loc: &str) -> Result<String, String> {
+    let s = find(g_id, opts, store, loc)?;
+    let next_run = if paused {s.next_run} else {scheduler::next_run(&s, Utc::now())};
+    store.set_schedule_paused(g_id, s.id, paused, next_run).map_err(|e| tr!(loc, "schedule.update_failed", error = e))?;
+    let s = StoredSchedule { paused, next_run, ..s };
+    let key = if paused {"schedule.paused_one"} else {"schedule.resumed"};
+    Ok(tr!(loc, key, schedule = scheduler::describe(&s, loc)))
+}
+
+fn delete(g_id: GuildId, opts: &[CommandDataOption], store: &storage::PollStore, loc: &str) -> Result<String, String> {
+    let s = find(g_id, opts, store, loc)?;
+    store.delete_schedule(g_id, s.id).map_err(|e| tr!(loc, "schedule.delete_failed", error = e))?;
+    Ok(tr!(loc, "schedule.deleted", schedule = scheduler::describe(&s, loc)))
+}
+
+fn find(g_id: GuildId, opts: &[CommandDataOption], store: &storage::PollStore, loc: &str) -> Result<StoredSchedule, String> {
+    let id = match sub_value(opts, "id") {
+        Some(CommandDataOptionValue::Integer(id)) => *id,
+        _ => return Err(tr!(loc, "schedule.no_id")),
+    };
+    match store.get_schedule(g_id, id) {
+        Ok(Some(s)) => Ok(s),
+        Ok(None) => Err(tr!(loc, "schedule.not_found", id = id)),
+        Err(e) => Err(tr!(loc, "schedule.read_failed", error = e)),
+    }
+}
+
+
+// the name and options of the subcommand that was used
+fn subcommand(ci: &CommandInteraction) -> Option<(&str, &[CommandDataOption])> {
+    let o = ci.data.options.first()?;
+    match &o.value {
+        CommandDataOptionValue::SubCommand(opts) => Some((o.name.as_str(), opts.as_slice())),
+        _ => None,
+    }
+}
+
+fn sub_value<'a>(opts: &'a [CommandDataOption], name: &str) -> Option<&'a CommandDataOptionValue> {
+    opts.iter().find(|o| o.name == name).map(|o| &o.value)
+}
+
+fn sub_str<'a>(opts: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
+    sub_value(opts, name)?.as_str()
+}
+
+
+fn id_option() -> CreateCommandOption {
+    i18n::option(CommandOptionType::Integer, "id", "commands.schedule.id")
+        .min_int_value(1)
+        .required(true)
+}
+
+fn subcommand_option(name: &str) -> CreateCommandOption {
+    i18n::option(CommandOptionType::SubCommand, name, &format!("commands.schedule.{name}"))
+}
+
+pub fn register() -> CreateCommand {
+    let add = subcommand_option("add")
+        .add_sub_option(i18n::option(CommandOptionType::String, "when", "commands.schedule.add.when")
+            .max_length(100)
+            .required(true))
+        .add_sub_option(i18n::option(CommandOptionType::String, "title", "commands.schedule.add.title")
+            .max_length(200)
+            .required(true))
+        .add_sub_option(i18n::option(CommandOptionType::String, "timezone", "commands.schedule.add.timezone")
+            .max_length(64)
+            .required(false))
+        .add_sub_option(i18n::option(CommandOptionType::String, "description", "commands.schedule.add.description_option")
+            .max_length(1000)
+            .required(false))
+        .add_sub_option(i18n::option(CommandOptionType::String, "options", "options.poll_options")
+            .max_length(500)
+            .required(false))
+        .add_sub_option(i18n::option(CommandOptionType::Channel, "channel", "commands.schedule.add.channel")
+            .channel_types(vec![ChannelType::Text])
+            .required(false));
+    i18n::command("schedule")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(add)
+        .add_option(subcommand_option("list"))
+        .add_option(subcommand_option("pause")
+            .add_sub_option(id_option()))
+        .add_option(subcommand_option("resume")
+            .add_sub_option(id_option()))
+        .add_option(subcommand_option("delete")
+            .add_sub_option(id_option()))
+}
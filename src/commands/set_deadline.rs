@@ -0,0 +1,49 @@
+use serenity::{all::{CommandInteraction, CommandOptionType, Context, GuildId, Permissions, Timestamp}, builder::CreateCommand};
+
+use crate::commands;
+use crate::i18n::{self, tr};
+use crate::reminders;
+use crate::utils;
+
+pub async fn run(ctx: &Context, ci: &CommandInteraction, g_id: GuildId){
+    let reply = match set_deadline(ctx, ci, g_id, i18n::command_locale(ci)).await {
+        Ok(r) => r,
+        Err(e) => e,
+    };
+    utils::send_ephemeral_followup(ctx, &reply, ci).await;
+}
+
+async fn set_deadline(ctx: &Context, ci: &CommandInteraction, g_id: GuildId, loc: &str) -> Result<String, String> {
+    let when = commands::option_str(ci, "when").unwrap_or_default().trim();
+    let deadline = match when {
+        "none" | "-" => None,
+        w => Some(utils::parse_event_time(w, loc)?.unix_timestamp()),
+    };
+    if deadline.is_some_and(|d| d <= Timestamp::now().unix_timestamp()) {
+        return Err(tr!(loc, "deadline.in_the_past"));
+    }
+    let (store, msg) = commands::poll_in_store(ctx, ci, g_id).await?;
+    store.set_deadline(msg.id, deadline).map_err(|e| tr!(loc, "deadline.save_failed", error = e))?;
+
+    let gloc = i18n::guild_locale(ctx, g_id);
+    let log_message = match deadline {
+        Some(d) => tr!(gloc, "log.deadline_set", user = ci.user.name, deadline = format!("<t:{d}:F>")),
+        None => tr!(gloc, "log.deadline_removed", user = ci.user.name),
+    };
+    if let Err(e) = utils::log_to_thread(ctx, &log_message, &g_id, &msg.channel_id, &msg.id.to_string()).await {
+        println!("{e}");
+    }
+    Ok(match deadline {
+        Some(d) => tr!(loc, "deadline.set", link = msg.link(), deadline = format!("<t:{d}:F> (<t:{d}:R>)"),
+            reminders = reminders::describe_offsets(loc)),
+        None => tr!(loc, "deadline.removed", link = msg.link()),
+    })
+}
+
+pub fn register() -> CreateCommand {
+    i18n::command("set_deadline")
+        .default_member_permissions(Permissions::MANAGE_MESSAGES)
+        .add_option(i18n::option(CommandOptionType::String, "when", "commands.set_deadline.when")
+            .required(true))
+        .add_option(commands::poll_option())
+}
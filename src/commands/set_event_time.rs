@@ -0,0 +1,48 @@
+use serenity::{all::{CommandInteraction, CommandOptionType, Context, GuildId, Permissions}, builder::CreateCommand};
+
+use crate::commands;
+use crate::config;
+use crate::i18n::{self, tr};
+use crate::utils;
+
+pub async fn run(ctx: &Context, ci: &CommandInteraction, g_id: GuildId){
+    let reply = match set_event_time(ctx, ci, g_id, i18n::command_locale(ci)).await {
+        Ok(r) => r,
+        Err(e) => e,
+    };
+    utils::send_ephemeral_followup(ctx, &reply, ci).await;
+}
+
+async fn set_event_time(ctx: &Context, ci: &CommandInteraction, g_id: GuildId, loc: &str) -> Result<String, String> {
+    let when = commands::option_str(ci, "when").unwrap_or_default().trim();
+    let event_at = match when {
+        "none" | "-" => None,
+        w => Some(utils::parse_event_time(w, loc)?.unix_timestamp()),
+    };
+    let (store, msg) = commands::poll_in_store(ctx, ci, g_id).await?;
+    store.set_event_at(msg.id, event_at).map_err(|e| tr!(loc, "event_time.save_failed", error = e))?;
+
+    let gloc = i18n::guild_locale(ctx, g_id);
+    let log_message = match event_at {
+        Some(t) => tr!(gloc, "log.event_time_set", user = ci.user.name, time = format!("<t:{t}:F>")),
+        None => tr!(gloc, "log.event_time_removed", user = ci.user.name),
+    };
+    if let Err(e) = utils::log_to_thread(ctx, &log_message, &g_id, &msg.channel_id, &msg.id.to_string()).await {
+        println!("{e}");
+    }
+    let cfg = &config::get().attendance;
+    Ok(match event_at {
+        Some(t) if cfg.enabled => tr!(loc, "event_time.set", link = msg.link(), time = format!("<t:{t}:F> (<t:{t}:R>)"),
+            minutes = cfg.delay_minutes),
+        Some(t) => tr!(loc, "event_time.set_no_attendance", link = msg.link(), time = format!("<t:{t}:F> (<t:{t}:R>)")),
+        None => tr!(loc, "event_time.removed", link = msg.link()),
+    })
+}
+
+pub fn register() -> CreateCommand {
+    i18n::command("set_event_time")
+        .default_member_permissions(Permissions::MANAGE_MESSAGES)
+        .add_option(i18n::option(CommandOptionType::String, "when", "commands.set_event_time.when")
+            .required(true))
+        .add_option(commands::poll_option())
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use serenity::{all::{CommandDataOptionValue, CommandInteraction, CommandOptionType, Context, GuildId, Member, Timestamp, UserId},
+    builder::CreateCommand};
+use serenity::futures::future;
+
+use crate::attendance;
+use crate::commands;
+use crate::config;
+use crate::i18n::{self, tr};
+use crate::storage;
+use crate::utils;
+
+const DEFAULT_DAYS: i64 = 30;
+
+pub async fn run(ctx: &Context, ci: &CommandInteraction, g_id: GuildId){
+    let reply = match stats(ctx, ci, g_id, i18n::command_locale(ci)).await {
+        Ok(r) => r,
+        Err(e) => e,
+    };
+    utils::send_ephemeral_followup(ctx, &reply, ci).await;
+}
+
+async fn stats(ctx: &Context, ci: &CommandInteraction, g_id: GuildId, loc: &str) -> Result<String, String> {
+    let Some(store) = storage::get(ctx).await else {
+        return Err(tr!(loc, "stats.no_store"));
+    };
+    let days = match commands::option_value(ci, "days") {
+        Some(CommandDataOptionValue::Integer(d)) => *d,
+        _ => DEFAULT_DAYS,
+    };
+    let role = match commands::option_value(ci, "role") {
+        Some(CommandDataOptionValue::Role(roleid)) => Some(*roleid),
+        _ => None,
+    };
+    let since = Timestamp::now().unix_timestamp() - days * 86400;
+    let mut stats = store.attendance_stats(g_id, since).map_err(|e| tr!(loc, "stats.read_failed", error = e))?;
+    // the least reliable first
+    stats.sort_by(|a, b| b.no_shows.cmp(&a.no_shows).then(b.no_votes.cmp(&a.no_votes)));
+
+    let members = members(ctx, g_id, stats.iter().map(|s| s.user_id).collect()).await;
+    let mut lines: Vec<String> = Vec::new();
+    for s in &stats {
+        let member = members.get(&s.user_id);
+        if let Some(r) = role {
+            if !member.is_some_and(|m| m.roles.contains(&r)) {
+                continue;
+            }
+        }
+        let name = match member {
+            Some(m) => m.display_name().to_string(),
+            None => format!("<@{}>", s.user_id),
+        };
+        lines.push(tr!(loc, "stats.line", name = name, polls = s.polls, rates = attendance::describe_stats(s, loc)));
+    }
+    if lines.is_empty() {
+        return Ok(tr!(loc, "stats.empty", days = days));
+    }
+
+    let limit = config::get().limits.message_length;
+    let mut text = format!("{}\n", tr!(loc, "stats.title", days = days));
+    for (i, line) in lines.iter().enumerate() {
+        if text.len() + line.len() + 20 > limit {
+            text += &tr!(loc, "list.more", n = lines.len() - i);
+            break;
+        }
+        text += line;
+        text += "\n";
+    }
+    Ok(text)
+}
+
+// the members from the cache, the ones missing there are fetched all at once
+// (the members who left the server are not in the result)
+async fn members(ctx: &Context, g_id: GuildId, uids: Vec<UserId>) -> HashMap<UserId, Member> {
+    let mut members: HashMap<UserId, Member> = match g_id.to_guild_cached(ctx) {
+        Some(g) => uids.iter().filter_map(|u| g.members.get(u).map(|m| (*u, m.clone()))).collect(),
+        None => HashMap::new(),
+    };
+    let misses: Vec<UserId> = uids.into_iter().filter(|u| !members.contains_key(u)).collect();
+    let fetched = future::join_all(misses.iter().map(|u| g_id.member(ctx, *u))).await;
+    members.extend(fetched.into_iter().filter_map(|m| m.ok()).map(|m| (m.user.id, m)));
+    members
+}
+
+pub fn register() -> CreateCommand {
+    i18n::command("stats")
+        .add_option(i18n::option(CommandOptionType::Integer, "days", "commands.stats.days")
+            .min_int_value(1)
+            .max_int_value(365)
+            .required(false))
+        .add_option(commands::role_option())
+}
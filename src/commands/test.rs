@@ -1,18 +1,14 @@
-use core::num;
-use std::collections::{HashMap, HashSet};
-use std::error::Error;
-use std::string;
-
 use serenity::builder::CreateCommand;
-use serenity::all::{CommandInteraction, Context, CreateCommandOption, CreateInteractionResponseFollowup, GuildId, Member, Message, MessageBuilder, User, UserId};
+use serenity::all::{CommandInteraction, Context, GuildId, MessageBuilder};
 
+use crate::i18n::{self, tr};
 use crate::utils;
 
 const SQUAD_ICONS:[&str; 8] = ["❤️", "💚", "💙", "💛", "🤍", "🖤",
  "💜", "🧡",];
 
 pub async fn run(ctx: &Context, ci: &CommandInteraction, g_id: GuildId){
-    // compare_channel_members_to_poll_and_respond(ctx, ci, g_id, UserComparison::MembersNotSelectedOption, Some(0)).await;
+    // compare_channel_members_to_poll_and_respond(ctx, ci, g_id, UserComparison::NotSelectedOption, Some(0)).await;
     get_4_squad_template(ctx, ci, g_id,Some(4)).await;
 }
 
@@ -21,8 +17,8 @@ async fn get_4_squad_template(ctx: &Context, ci: &CommandInteraction, g_id: Guil
     let possibly_in_voice = utils::get_all_members_in_voice_cached(ctx, &g_id);
     let mut in_voice_uids = String::new();
     if let Some(in_voice) = &possibly_in_voice {
-        for (uid, _state) in in_voice {
-            in_voice_uids += format!("<@{}>\n", uid.to_string()).as_str();
+        for uid in in_voice.keys() {
+            in_voice_uids += format!("<@{}>\n", uid).as_str();
         }
     }
     let num_squads: usize = match num_squads {
@@ -30,9 +26,9 @@ async fn get_4_squad_template(ctx: &Context, ci: &CommandInteraction, g_id: Guil
         _ => 4,
     };
     let mut reply = MessageBuilder::new();
-    reply.push("```Лайнап:");
-    for i in 0..num_squads {
-        reply.push(SQUAD_ICONS[i])
+    reply.push(format!("```{}", tr!(i18n::command_locale(ci), "lineup.title")));
+    for icon in SQUAD_ICONS.iter().take(num_squads) {
+        reply.push(*icon)
             .push("\n\n");
     }
     reply.push("\n\n")
@@ -43,12 +39,9 @@ async fn get_4_squad_template(ctx: &Context, ci: &CommandInteraction, g_id: Guil
 }
 
 pub fn register() -> CreateCommand {
-    let option = CreateCommandOption::new(
-        serenity::all::CommandOptionType::Integer,
-        "number",
-        "Number of color-coded squads in the template (1-8, default 4)")
+    let option = i18n::option(serenity::all::CommandOptionType::Integer, "number", "commands.lineup.number")
         .min_int_value(1)
         .max_int_value(8)
         .required(false);
-    CreateCommand::new("test").description("Get 4-squad template with all members currently in voice.").add_option(option)
+    i18n::command("test").add_option(option)
 }
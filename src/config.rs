@@ -0,0 +1,644 @@
+//Configuration loaded from a TOML file at startup (see pollbot.example.toml)
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+use crate::commands;
+use crate::poll_sources::{OptionKind, PollOption};
+
+pub const DEFAULT_CONFIG_PATH: &str = "pollbot.toml";
+// max number of options in a poll (own polls have a button for every option and one more, 5 rows of 5 at most)
+pub const MAX_OPTIONS: usize = 10;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    // guilds to register guild-specific commands in
+    #[serde(default)]
+    pub dev_guilds: Vec<u64>,
+    // a directory with <locale>.toml catalogs, their strings replace the built-in ones (see locales/)
+    #[serde(default)]
+    pub locales_dir: Option<String>,
+    #[serde(default)]
+    pub commands: CommandsConfig,
+    // supported 3rd party poll bots
+    #[serde(default = "default_bots")]
+    pub bots: Vec<BotConfig>,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub reactions: ReactionsConfig,
+    #[serde(default)]
+    pub own_polls: OwnPollsConfig,
+    #[serde(default)]
+    pub native_polls: NativePollsConfig,
+    // the sections of the features the bot was built without are still read, so one config works for every build
+    #[cfg_attr(not(feature = "scheduled_polls"), allow(dead_code))]
+    #[serde(default)]
+    pub scheduler: SchedulerConfig,
+    #[serde(default)]
+    pub reminders: RemindersConfig,
+    #[serde(default)]
+    pub attendance: AttendanceConfig,
+    #[serde(default)]
+    pub voice: VoiceConfig,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CommandsConfig {
+    // registered globally
+    #[serde(default = "default_global_commands")]
+    pub global: Vec<String>,
+    // registered in every guild from dev_guilds
+    #[serde(default)]
+    pub guild: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BotConfig {
+    pub name: String,
+    pub id: u64,
+    // how the voters' names are listed in the embed fields, the name of the bot's module in poll_sources
+    pub format: String,
+    // prefixes of the embed field names for "accepted", "declined" and "tentative" options
+    pub emojis: Vec<String>,
+    // fields whose names don't start with any of the emojis are counted towards this option (index in emojis) if set
+    // e.g. Raid-Helper lists the sign-ups in one field per class
+    #[serde(default)]
+    pub unmatched_fields_option: Option<usize>,
+    // names of the options, one per emoji ("Accepted", "Declined", "Tentative" and the emoji itself by default)
+    #[serde(default)]
+    pub labels: Vec<String>,
+    // kinds of the options, one per emoji ("accepted", "declined", "tentative" and "other" by default)
+    #[serde(default)]
+    pub kinds: Vec<OptionKind>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LimitsConfig {
+    // max length of a message we send
+    #[serde(default = "default_message_length")]
+    pub message_length: usize,
+    // max number of follow-up messages per command
+    #[serde(default = "default_max_followups")]
+    pub max_followups: usize,
+    // what to do with the mention lists that don't fit in max_followups messages
+    #[serde(default)]
+    pub overflow: OverflowMode,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OverflowMode {
+    // a text file with the whole list
+    #[default]
+    Attachment,
+    // an embed with next/prev buttons
+    Pages,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ReactionsConfig {
+    #[serde(default = "default_accepted")]
+    pub accepted: char,
+    #[serde(default = "default_declined")]
+    pub declined: char,
+    #[serde(default = "default_tentative")]
+    pub tentative: char,
+}
+
+// how the voters pick an option in own polls
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PollStyle {
+    // the reactions from [reactions], the bot removes the other ones when someone reacts
+    #[default]
+    Reactions,
+    // a button for every option and "clear my vote", the votes are kept in the store
+    Buttons,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct OwnPollsConfig {
+    #[cfg_attr(not(feature = "poll_creation"), allow(dead_code))]
+    #[serde(default)]
+    pub style: PollStyle,
+    // default options of new polls, "accepted", "declined" and "tentative" with the reactions from [reactions] if empty
+    #[serde(default)]
+    pub options: Vec<OwnPollOption>,
+    // close the polls when their deadline passes
+    #[cfg_attr(not(feature = "poll_creation"), allow(dead_code))]
+    #[serde(default = "default_true")]
+    pub close_at_deadline: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OwnPollOption {
+    pub emoji: String,
+    pub label: String,
+    pub kind: OptionKind,
+}
+
+impl OwnPollOption {
+    pub fn poll_option(&self) -> PollOption {
+        PollOption { label: self.label.clone(), kind: self.kind }
+    }
+}
+
+// Discord's own polls
+// answers are matched by their text or emoji (case-insensitive), answers that match nothing are mapped by position
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NativePollsConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_native_accepted")]
+    pub accepted: Vec<String>,
+    #[serde(default = "default_native_declined")]
+    pub declined: Vec<String>,
+    #[serde(default = "default_native_tentative")]
+    pub tentative: Vec<String>,
+    // map the answers that didn't match anything by their position (1st - accepted, 2nd - declined, 3rd - tentative)
+    #[serde(default = "default_true")]
+    pub map_by_index: bool,
+}
+
+// recurring polls (/schedule)
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "scheduled_polls"), allow(dead_code))]
+pub struct SchedulerConfig {
+    // time zone of the schedules added without one
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    // polls that are late by more than this (e.g. the bot was offline) are skipped
+    #[serde(default = "default_max_delay_minutes")]
+    pub max_delay_minutes: u64,
+}
+
+// reminders to the members who haven't voted before the poll's deadline
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RemindersConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    // how long before the deadline, e.g. "24h", "2h", "30m", "1d"
+    #[serde(default = "default_reminder_offsets")]
+    pub offsets: Vec<String>,
+    #[serde(default)]
+    pub mode: ReminderMode,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReminderMode {
+    // one message in the poll's channel mentioning everyone
+    #[default]
+    Channel,
+    // a direct message to every member
+    DM,
+}
+
+// who was in voice when the event started (/stats)
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AttendanceConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    // how long after the event start to look at the voice channels (people are often a bit late)
+    #[serde(default = "default_attendance_delay")]
+    pub delay_minutes: u64,
+}
+
+// who counts as "in voice" for /get_not_in_voice, /lineup and the attendance
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct VoiceConfig {
+    // the members in the server's AFK channel are not in voice
+    #[serde(default = "default_true")]
+    pub exclude_afk: bool,
+    // self-deafened and server-deafened members count as in voice too
+    #[serde(default)]
+    pub count_deafened: bool,
+    // servers where only some voice channels are for the events
+    #[serde(default)]
+    pub guilds: Vec<GuildVoiceConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GuildVoiceConfig {
+    pub id: u64,
+    // voice channels or categories (every voice channel in them), the rest of the server's voice channels don't count
+    pub event_channels: Vec<u64>,
+}
+
+
+fn default_global_commands() -> Vec<String> {
+    let mut v = vec!["lineup", "get_accepted", "get_tentative", "get_no_vote", "get_not_in_voice", "get_option", "set_deadline", "set_event_time", "stats", "export",
+        commands::context_menu::NO_VOTE, commands::context_menu::NOT_IN_VOICE, commands::context_menu::LINEUP];
+    if cfg!(feature = "poll_creation") {
+        v.push("new_poll");
+        v.push("close_poll");
+    }
+    if cfg!(feature = "scheduled_polls") {
+        v.push("schedule");
+    }
+    v.into_iter().map(|s| s.to_string()).collect()
+}
+
+fn default_bots() -> Vec<BotConfig> {
+    crate::poll_sources::default_bot_configs()
+}
+
+fn default_true() -> bool {true}
+fn default_native_accepted() -> Vec<String> {vec!["✅".to_string(), "yes".to_string(), "accepted".to_string()]}
+fn default_native_declined() -> Vec<String> {vec!["❌".to_string(), "no".to_string(), "declined".to_string()]}
+fn default_native_tentative() -> Vec<String> {vec!["❔".to_string(), "maybe".to_string(), "tentative".to_string()]}
+fn default_message_length() -> usize {1996}
+fn default_max_followups() -> usize {9}
+fn default_accepted() -> char {'✅'}
+fn default_declined() -> char {'❌'}
+fn default_tentative() -> char {'❔'}
+fn default_timezone() -> String {"UTC".to_string()}
+fn default_max_delay_minutes() -> u64 {60}
+fn default_attendance_delay() -> u64 {10}
+fn default_reminder_offsets() -> Vec<String> {vec!["24h".to_string(), "2h".to_string()]}
+
+impl Default for CommandsConfig {
+    fn default() -> Self {
+        CommandsConfig { global: default_global_commands(), guild: Vec::new() }
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig { message_length: default_message_length(), max_followups: default_max_followups(), overflow: OverflowMode::default() }
+    }
+}
+
+impl Default for ReactionsConfig {
+    fn default() -> Self {
+        ReactionsConfig { accepted: default_accepted(), declined: default_declined(), tentative: default_tentative() }
+    }
+}
+
+impl Default for NativePollsConfig {
+    fn default() -> Self {
+        NativePollsConfig {
+            enabled: default_true(),
+            accepted: default_native_accepted(),
+            declined: default_native_declined(),
+            tentative: default_native_tentative(),
+            map_by_index: default_true(),
+        }
+    }
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        SchedulerConfig { timezone: default_timezone(), max_delay_minutes: default_max_delay_minutes() }
+    }
+}
+
+impl Default for OwnPollsConfig {
+    fn default() -> Self {
+        OwnPollsConfig { style: PollStyle::default(), options: Vec::new(), close_at_deadline: default_true() }
+    }
+}
+
+impl Default for RemindersConfig {
+    fn default() -> Self {
+        RemindersConfig { enabled: default_true(), offsets: default_reminder_offsets(), mode: ReminderMode::default() }
+    }
+}
+
+impl Default for AttendanceConfig {
+    fn default() -> Self {
+        AttendanceConfig { enabled: default_true(), delay_minutes: default_attendance_delay() }
+    }
+}
+
+impl Default for VoiceConfig {
+    fn default() -> Self {
+        VoiceConfig { exclude_afk: default_true(), count_deafened: false, guilds: Vec::new() }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            dev_guilds: Vec::new(),
+            locales_dir: None,
+            commands: CommandsConfig::default(),
+            bots: default_bots(),
+            limits: LimitsConfig::default(),
+            reactions: ReactionsConfig::default(),
+            own_polls: OwnPollsConfig::default(),
+            native_polls: NativePollsConfig::default(),
+            scheduler: SchedulerConfig::default(),
+            reminders: RemindersConfig::default(),
+            attendance: AttendanceConfig::default(),
+            voice: VoiceConfig::default(),
+        }
+    }
+}
+
+
+impl BotConfig {
+    // options of the bot's polls, one per emoji
+    #[cfg(feature = "third_party_bots")]
+    pub fn options(&self) -> Vec<PollOption> {
+        (0..self.emojis.len()).map(|i| PollOption {
+            label: match (self.labels.get(i), crate::POLL_OPT_NAMES.get(i)) {
+                (Some(l), _) => l.clone(),
+                (None, Some(n)) => n.to_string(),
+                (None, None) => self.emojis[i].clone(),
+            },
+            kind: self.kinds.get(i).copied().unwrap_or(OptionKind::by_index(i)),
+        }).collect()
+    }
+}
+
+
+impl Config {
+    // reactions for "accepted", "declined" and "tentative" options of own polls
+    pub fn poll_opts(&self) -> [char; 3] {
+        [self.reactions.accepted, self.reactions.declined, self.reactions.tentative]
+    }
+
+    // default options of own polls
+    pub fn own_poll_options(&self) -> Vec<OwnPollOption> {
+        if !self.own_polls.options.is_empty() {
+            return self.own_polls.options.clone();
+        }
+        self.poll_opts().iter().enumerate()
+            .map(|(i, r)| OwnPollOption {
+                emoji: r.to_string(),
+                label: crate::POLL_OPT_NAMES[i].to_string(),
+                kind: OptionKind::by_index(i),
+            })
+            .collect()
+    }
+
+    // reminder offsets in seconds, the invalid ones are reported by validate()
+    pub fn reminder_offsets(&self) -> Vec<i64> {
+        self.reminders.offsets.iter().filter_map(|o| parse_duration(o).ok()).collect()
+    }
+
+    #[cfg(feature = "third_party_bots")]
+    pub fn bot(&self, id: u64) -> Option<&BotConfig> {
+        self.bots.iter().find(|b| b.id == id)
+    }
+
+    // the event voice channels (and categories) of the server, empty if every voice channel counts
+    pub fn event_channels(&self, guild_id: u64) -> &[u64] {
+        self.voice.guilds.iter().find(|g| g.id == guild_id).map_or(&[], |g| g.event_channels.as_slice())
+    }
+
+    pub fn from_toml(text: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(text).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // checks everything serde can't, lists all the problems at once
+    pub fn validate(&self) -> Result<(), String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        for g in &self.dev_guilds {
+            if *g == 0 {errors.push("dev_guilds: guild id can't be 0".to_string());}
+        }
+        if let Some(dir) = &self.locales_dir {
+            if !std::path::Path::new(dir).is_dir() {
+                errors.push(format!("locales_dir: {dir} is not a directory"));
+            }
+            for locale in crate::i18n::locales() {
+                if let Some(Err(e)) = crate::i18n::read_catalog(dir, locale) {
+                    errors.push(format!("locales_dir: {e}"));
+                }
+            }
+        }
+        for (list_name, list) in [("commands.global", &self.commands.global), ("commands.guild", &self.commands.guild)] {
+            for c in list {
+                if !commands::NAMES.contains(&c.as_str()) {
+                    errors.push(format!("{list_name}: unknown command \"{c}\" (known: {})", commands::NAMES.join(", ")));
+                }
+            }
+        }
+        if !self.commands.guild.is_empty() && self.dev_guilds.is_empty() {
+            errors.push("commands.guild: guild commands are listed, but dev_guilds is empty".to_string());
+        }
+
+        let mut bot_ids: HashSet<u64> = HashSet::new();
+        for (i, b) in self.bots.iter().enumerate() {
+            if b.id == 0 {errors.push(format!("bots[{i}] ({}): id can't be 0", b.name));}
+            if !bot_ids.insert(b.id) {errors.push(format!("bots[{i}] ({}): id {} is listed more than once", b.name, b.id));}
+            if b.emojis.is_empty() || b.emojis.len() > MAX_OPTIONS {
+                errors.push(format!("bots[{i}] ({}): emojis must have 1 to {MAX_OPTIONS} entries, got {}", b.name, b.emojis.len()));
+            }
+            // the bots are ignored without the feature, the example config still loads
+            if cfg!(feature = "third_party_bots") && !crate::poll_sources::BOT_FORMATS.iter().any(|(f, _)| *f == b.format) {
+                let known: Vec<&str> = crate::poll_sources::BOT_FORMATS.iter().map(|(f, _)| *f).collect();
+                errors.push(format!("bots[{i}] ({}): unknown format \"{}\" (known: {})", b.name, b.format, known.join(", ")));
+            }
+            if b.emojis.iter().any(|e| e.is_empty()) {errors.push(format!("bots[{i}] ({}): emojis can't be empty", b.name));}
+            if let Some(o) = b.unmatched_fields_option {
+                if o >= b.emojis.len() {
+                    errors.push(format!("bots[{i}] ({}): unmatched_fields_option must be less than the number of emojis, got {o}", b.name));
+                }
+            }
+            for (list_name, len) in [("labels", b.labels.len()), ("kinds", b.kinds.len())] {
+                if len > 0 && len != b.emojis.len() {
+                    errors.push(format!("bots[{i}] ({}): {list_name} must have one entry per emoji ({}), got {len}", b.name, b.emojis.len()));
+                }
+            }
+        }
+
+        if self.limits.message_length < 100 || self.limits.message_length > 2000 {
+            errors.push(format!("limits.message_length: must be between 100 and 2000, got {}", self.limits.message_length));
+        }
+        if self.limits.max_followups == 0 {
+            errors.push("limits.max_followups: must be at least 1".to_string());
+        }
+
+        let opts = self.poll_opts();
+        if opts[0] == opts[1] || opts[1] == opts[2] || opts[0] == opts[2] {
+            errors.push(format!("reactions: accepted, declined and tentative must differ, got {}{}{}", opts[0], opts[1], opts[2]));
+        }
+
+        let own = &self.own_polls.options;
+        if own.len() > MAX_OPTIONS {
+            errors.push(format!("own_polls.options: at most {MAX_OPTIONS} options, got {}", own.len()));
+        }
+        let mut emojis: HashSet<&str> = HashSet::new();
+        for (i, o) in own.iter().enumerate() {
+            if o.emoji.is_empty() || o.emoji.chars().next().is_some_and(|c| c.is_alphanumeric()) {
+                errors.push(format!("own_polls.options[{i}]: emoji must be an emoji, got \"{}\"", o.emoji));
+            }
+            if !emojis.insert(o.emoji.as_str()) {
+                errors.push(format!("own_polls.options[{i}]: emoji {} is used more than once", o.emoji));
+            }
+            if o.label.trim().is_empty() || o.label.contains('_') || o.label.contains('\n') {
+                errors.push(format!("own_polls.options[{i}]: label can't be empty or contain underscores or line breaks"));
+            }
+        }
+
+        let np = &self.native_polls;
+        let mut seen: HashSet<String> = HashSet::new();
+        for (list_name, list) in [("accepted", &np.accepted), ("declined", &np.declined), ("tentative", &np.tentative)] {
+            for a in list {
+                if !seen.insert(a.to_lowercase()) {
+                    errors.push(format!("native_polls.{list_name}: \"{a}\" is mapped to more than one option"));
+                }
+            }
+        }
+
+        for o in &self.reminders.offsets {
+            if let Err(e) = parse_duration(o) {
+                errors.push(format!("reminders.offsets: {e}"));
+            }
+        }
+
+        let mut voice_guilds: HashSet<u64> = HashSet::new();
+        for (i, g) in self.voice.guilds.iter().enumerate() {
+            if g.id == 0 {errors.push(format!("voice.guilds[{i}]: id can't be 0"));}
+            if !voice_guilds.insert(g.id) {errors.push(format!("voice.guilds[{i}]: guild {} is listed more than once", g.id));}
+            if g.event_channels.is_empty() || g.event_channels.contains(&0) {
+                errors.push(format!("voice.guilds[{i}]: event_channels must list channel ids (not 0)"));
+            }
+        }
+
+        #[cfg(feature = "scheduled_polls")]
+        if let Err(e) = crate::scheduler::parse_timezone(&self.scheduler.timezone, crate::i18n::DEFAULT_LOCALE) {
+            errors.push(format!("scheduler.timezone: {e}"));
+        }
+
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(errors.join("\n"))
+    }
+}
+
+
+// "90m", "2h", "1d" -> seconds
+pub fn parse_duration(s: &str) -> Result<i64, String> {
+    let s = s.trim();
+    let unit = match s.chars().last() {
+        Some('m') => 60,
+        Some('h') => 3600,
+        Some('d') => 86400,
+        _ => return Err(format!("\"{s}\" should be a number followed by m, h or d")),
+    };
+    match s[..s.len() - 1].parse::<i64>() {
+        Ok(n) if n > 0 => n.checked_mul(unit).ok_or(format!("\"{s}\" is too long")),
+        _ => Err(format!("\"{s}\" should be a positive number followed by m, h or d")),
+    }
+}
+
+
+// reads and validates the config file
+pub fn load(path: &str) -> Result<Config, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Can't read config file {path}: {e}"))?;
+    Config::from_toml(&text).map_err(|e| format!("Invalid config file {path}:\n{e}"))
+}
+
+// makes the config available through get(), can only be done once
+pub fn init(config: Config) {
+    if CONFIG.set(config).is_err() {
+        println!("Config is already initialized, ignoring.");
+    }
+}
+
+// returns the config loaded at startup (or the defaults if nothing was loaded)
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_are_read() {
+        assert_eq!(parse_duration("90m"), Ok(5400));
+        assert_eq!(parse_duration(" 2h "), Ok(7200));
+        assert_eq!(parse_duration("1d"), Ok(86400));
+        assert!(parse_duration("0h").is_err());
+        assert!(parse_duration("-1d").is_err());
+        assert!(parse_duration("2").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("").is_err());
+    }
+
+    #[test]
+    fn too_long_duration_is_an_error() {
+        assert_eq!(parse_duration(&format!("{}d", i64::MAX)), Err(format!("\"{}d\" is too long", i64::MAX)));
+        assert!(Config::from_toml("[reminders]\noffsets = [\"9999999999999999d\"]").is_err());
+    }
+
+    #[test]
+    fn empty_file_is_the_defaults() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.limits.message_length, Config::default().limits.message_length);
+        assert!(config.voice.exclude_afk);
+        assert_eq!(config.event_channels(1), &[] as &[u64]);
+    }
+
+    #[test]
+    fn example_file_is_valid() {
+        Config::from_toml(include_str!("../pollbot.example.toml")).unwrap();
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(Config::from_toml("no_such_key = 1").is_err());
+        assert!(Config::from_toml("[voice]\nexclude_akf = false").is_err());
+    }
+
+    #[test]
+    fn every_problem_is_listed() {
+        let text = "dev_guilds = [0]\n[commands]\nglobal = [\"no_such_command\"]\n[limits]\nmessage_length = 5000";
+        let e = Config::from_toml(text).unwrap_err();
+        assert_eq!(e.lines().count(), 3, "{e}");
+        assert!(e.contains("dev_guilds"), "{e}");
+        assert!(e.contains("no_such_command"), "{e}");
+        assert!(e.contains("limits.message_length"), "{e}");
+    }
+
+    #[test]
+    fn voice_guilds_are_checked() {
+        let config = Config::from_toml("[[voice.guilds]]\nid = 5\nevent_channels = [7, 8]").unwrap();
+        assert_eq!(config.event_channels(5), &[7, 8]);
+        assert_eq!(config.event_channels(6), &[] as &[u64]);
+        assert!(Config::from_toml("[[voice.guilds]]\nid = 5\nevent_channels = []").is_err());
+        let twice = "[[voice.guilds]]\nid = 5\nevent_channels = [7]\n[[voice.guilds]]\nid = 5\nevent_channels = [8]";
+        assert!(Config::from_toml(twice).unwrap_err().contains("more than once"));
+    }
+
+    #[test]
+    fn locales_dir_is_checked() {
+        let dir = std::env::temp_dir().join(format!("pollbot-locales-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = format!("locales_dir = {:?}", dir.display().to_string());
+        std::fs::write(dir.join("ru.toml"), "[stats]\nempty = \"Пусто\"").unwrap();
+        assert!(Config::from_toml(&config).is_ok());
+        std::fs::write(dir.join("ru.toml"), "[stats]\nempty = 5").unwrap();
+        assert!(Config::from_toml(&config).unwrap_err().contains("ru.toml"));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(Config::from_toml(&config).unwrap_err().contains("is not a directory"));
+    }
+}
@@ -0,0 +1,184 @@
+//Discord access: every request the poll logic makes goes through DiscordGateway, so the logic can run without Discord
+//the bot itself uses Context (HTTP and the cache), the tests use gateway::fake
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serenity::all::{AnswerId, ChannelId, ChannelType, Context, CreateThread, GetMessages, GuildChannel, GuildId,
+    Member, Message, MessageId, ReactionType, User, UserId, VoiceState};
+use serenity::async_trait;
+#[cfg(feature = "poll_creation")]
+use serenity::all::EditMessage;
+
+use crate::i18n;
+use crate::pagination;
+use crate::storage::{self, PollStore};
+
+#[cfg(test)]
+pub mod fake;
+
+// how many messages are asked for at once when looking through a channel
+pub const MESSAGES_PAGE: u8 = 100;
+
+
+#[async_trait]
+pub trait DiscordGateway: Send + Sync {
+    // the bot's own user id
+    fn current_user_id(&self) -> UserId;
+
+    // the store if it was opened at startup
+    async fn poll_store(&self) -> Option<Arc<PollStore>>;
+
+    // up to limit messages of the channel sent before the given one (or the last ones), the newest first
+    async fn messages(&self, ch_id: ChannelId, before: Option<MessageId>, limit: u8) -> Result<Vec<Message>, serenity::Error>;
+
+    async fn message(&self, ch_id: ChannelId, m_id: MessageId) -> Result<Message, serenity::Error>;
+
+    // all the users who reacted to the message with the emoji
+    async fn reaction_users(&self, msg: &Message, reaction: ReactionType) -> Result<Vec<User>, serenity::Error>;
+
+    // all the users who selected the answer of Discord's own poll
+    async fn poll_answer_voters(&self, msg: &Message, answer_id: AnswerId) -> Result<Vec<User>, serenity::Error>;
+
+    // None if it's not a guild channel (or there is no such channel)
+    async fn guild_channel(&self, ch_id: ChannelId) -> Option<GuildChannel>;
+
+    // the member from the cache, None if it's not there
+    #[cfg(feature = "poll_creation")]
+    fn cached_member(&self, g_id: GuildId, u_id: UserId) -> Option<Member>;
+
+    #[cfg(feature = "poll_creation")]
+    async fn user(&self, u_id: UserId) -> Result<User, serenity::Error>;
+
+    // the locale of everything that is not a reply to someone (see i18n::guild_locale)
+    fn guild_locale(&self, g_id: GuildId) -> &'static str;
+
+    // the members who can see the channel
+    fn channel_members(&self, ch: &GuildChannel) -> Result<Vec<Member>, String>;
+
+    // text channels and threads of the guild
+    fn guild_channels(&self, g_id: GuildId) -> Vec<ChannelId>;
+
+    // everyone in the guild's voice channels, None if the guild is unknown
+    fn voice_states(&self, g_id: GuildId) -> Option<HashMap<UserId, VoiceState>>;
+
+    // voice and stage channels of the guild
+    fn voice_channels(&self, g_id: GuildId) -> Vec<GuildChannel>;
+
+    fn afk_channel(&self, g_id: GuildId) -> Option<ChannelId>;
+
+    async fn active_threads(&self, g_id: GuildId) -> Result<Vec<GuildChannel>, serenity::Error>;
+
+    async fn create_thread(&self, ch_id: ChannelId, name: String, kind: ChannelType) -> Result<ChannelId, serenity::Error>;
+
+    async fn send_message(&self, ch_id: ChannelId, content: String) -> Result<Message, serenity::Error>;
+
+    #[cfg(feature = "poll_creation")]
+    async fn edit_message(&self, ch_id: ChannelId, m_id: MessageId, content: String) -> Result<Message, serenity::Error>;
+
+    // removes the user's reaction (or every reaction with the emoji if there is no user)
+    #[cfg(feature = "poll_creation")]
+    async fn delete_reaction(&self, msg: &Message, u_id: Option<UserId>, reaction: ReactionType) -> Result<(), serenity::Error>;
+}
+
+
+#[async_trait]
+impl DiscordGateway for Context {
+    fn current_user_id(&self) -> UserId {
+        self.cache.current_user().id
+    }
+
+    async fn poll_store(&self) -> Option<Arc<PollStore>> {
+        storage::get(self).await
+    }
+
+    async fn messages(&self, ch_id: ChannelId, before: Option<MessageId>, limit: u8) -> Result<Vec<Message>, serenity::Error> {
+        let builder = match before {
+            Some(m_id) => GetMessages::new().before(m_id).limit(limit),
+            None => GetMessages::new().limit(limit),
+        };
+        ch_id.messages(self, builder).await
+    }
+
+    async fn message(&self, ch_id: ChannelId, m_id: MessageId) -> Result<Message, serenity::Error> {
+        ch_id.message(self, m_id).await
+    }
+
+    async fn reaction_users(&self, msg: &Message, reaction: ReactionType) -> Result<Vec<User>, serenity::Error> {
+        pagination::all_reaction_users(&self.http, msg, reaction).await
+    }
+
+    async fn poll_answer_voters(&self, msg: &Message, answer_id: AnswerId) -> Result<Vec<User>, serenity::Error> {
+        pagination::all_poll_answer_voters(&self.http, msg, answer_id).await
+    }
+
+    async fn guild_channel(&self, ch_id: ChannelId) -> Option<GuildChannel> {
+        ch_id.to_channel(self).await.ok()?.guild()
+    }
+
+    #[cfg(feature = "poll_creation")]
+    fn cached_member(&self, g_id: GuildId, u_id: UserId) -> Option<Member> {
+        g_id.to_guild_cached(self)?.members.get(&u_id).cloned()
+    }
+
+    #[cfg(feature = "poll_creation")]
+    async fn user(&self, u_id: UserId) -> Result<User, serenity::Error> {
+        u_id.to_user(self).await
+    }
+
+    fn guild_locale(&self, g_id: GuildId) -> &'static str {
+        i18n::guild_locale(self, g_id)
+    }
+
+    fn channel_members(&self, ch: &GuildChannel) -> Result<Vec<Member>, String> {
+        ch.members(self).map_err(|e| e.to_string())
+    }
+
+    fn guild_channels(&self, g_id: GuildId) -> Vec<ChannelId> {
+        let Some(g) = g_id.to_guild_cached(self) else {return Vec::new();};
+        g.channels.values().filter(|c| c.kind == ChannelType::Text).map(|c| c.id)
+            .chain(g.threads.iter().map(|t| t.id))
+            .collect()
+    }
+
+    fn voice_states(&self, g_id: GuildId) -> Option<HashMap<UserId, VoiceState>> {
+        match g_id.to_guild_cached(self) {
+            Some(g) => Some(g.voice_states.clone()),
+            None => {
+                println!("Can't get guild from cache.");
+                None
+            },
+        }
+    }
+
+    fn voice_channels(&self, g_id: GuildId) -> Vec<GuildChannel> {
+        let Some(g) = g_id.to_guild_cached(self) else {return Vec::new();};
+        g.channels.values().filter(|c| matches!(c.kind, ChannelType::Voice | ChannelType::Stage)).cloned().collect()
+    }
+
+    fn afk_channel(&self, g_id: GuildId) -> Option<ChannelId> {
+        g_id.to_guild_cached(self)?.afk_metadata.as_ref().map(|a| a.afk_channel_id)
+    }
+
+    async fn active_threads(&self, g_id: GuildId) -> Result<Vec<GuildChannel>, serenity::Error> {
+        Ok(g_id.get_active_threads(self).await?.threads)
+    }
+
+    async fn create_thread(&self, ch_id: ChannelId, name: String, kind: ChannelType) -> Result<ChannelId, serenity::Error> {
+        Ok(ch_id.create_thread(self, CreateThread::new(name).kind(kind)).await?.id)
+    }
+
+    async fn send_message(&self, ch_id: ChannelId, content: String) -> Result<Message, serenity::Error> {
+        ch_id.say(&self.http, content).await
+    }
+
+    #[cfg(feature = "poll_creation")]
+    async fn edit_message(&self, ch_id: ChannelId, m_id: MessageId, content: String) -> Result<Message, serenity::Error> {
+        ch_id.edit_message(self, m_id, EditMessage::new().content(content)).await
+    }
+
+    #[cfg(feature = "poll_creation")]
+    async fn delete_reaction(&self, msg: &Message, u_id: Option<UserId>, reaction: ReactionType) -> Result<(), serenity::Error> {
+        msg.delete_reaction(self, u_id, reaction).await
+    }
+}
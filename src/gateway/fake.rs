@@ -0,0 +1,308 @@
+//In-memory Discord for the tests: a guild's channels, members, messages, reactions and voice channels
+//everything the bot sends is kept in sent, nothing is checked against the permissions
+//the channel members are the guild's members (the cache), the locale is always the default one
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serenity::all::{AnswerId, ChannelId, ChannelType, GuildChannel, GuildId, Member, Message, MessageId, MessageReaction,
+    ReactionType, User, UserId, VoiceState};
+use serenity::async_trait;
+#[cfg(feature = "poll_creation")]
+use serenity::all::Reaction;
+
+use super::DiscordGateway;
+use crate::i18n;
+use crate::storage::PollStore;
+
+
+#[derive(Default)]
+pub struct FakeGateway {
+    pub own_id: UserId,
+    pub store: Option<Arc<PollStore>>,
+    channels: HashMap<ChannelId, (GuildChannel, Vec<Member>)>,
+    messages: Mutex<Vec<Message>>,
+    reactions: Mutex<HashMap<(MessageId, String), Vec<User>>>,
+    answers: HashMap<(MessageId, AnswerId), Vec<User>>,
+    voice: HashMap<GuildId, HashMap<UserId, VoiceState>>,
+    voice_channels: Vec<GuildChannel>,
+    afk: HashMap<GuildId, ChannelId>,
+    pub threads: Mutex<Vec<GuildChannel>>,
+    next_id: Mutex<u64>,
+    pub sent: Mutex<Vec<(ChannelId, String)>>,
+}
+
+pub fn user(id: u64, name: &str) -> User {
+    let mut u = User::default();
+    u.id = UserId::new(id);
+    u.name = name.to_string();
+    u
+}
+
+pub fn member(g_id: GuildId, u: User) -> Member {
+    let mut m = Member::default();
+    m.guild_id = g_id;
+    m.user = u;
+    m
+}
+
+// the user's reaction as the gateway reports it
+#[cfg(feature = "poll_creation")]
+pub fn reaction(g_id: GuildId, ch_id: ChannelId, m_id: MessageId, u_id: UserId, emoji: ReactionType) -> Reaction {
+    let reaction_json = serde_json::json!({
+        "user_id": u_id,
+        "channel_id": ch_id,
+        "message_id": m_id,
+        "guild_id": g_id,
+        "emoji": emoji,
+        "burst": false,
+        "type": 0,
+    });
+    serde_json::from_value(reaction_json).unwrap()
+}
+
+impl FakeGateway {
+    pub fn new(own_id: u64) -> FakeGateway {
+        FakeGateway {
+            own_id: UserId::new(own_id),
+            // the ids of whatever the bot creates, far away from the ones the tests use
+            next_id: Mutex::new(1_000_000),
+            ..Default::default()
+        }
+    }
+
+    // a text channel that the members can see
+    pub fn add_channel(&mut self, g_id: GuildId, ch_id: ChannelId, members: Vec<Member>) {
+        let mut ch = GuildChannel::default();
+        ch.id = ch_id;
+        ch.guild_id = g_id;
+        ch.kind = ChannelType::Text;
+        ch.name = format!("channel-{ch_id}");
+        self.channels.insert(ch_id, (ch, members));
+    }
+
+    // the message as if it was posted to its channel
+    pub fn post(&mut self, msg: Message) {
+        self.messages.get_mut().unwrap().push(msg);
+    }
+
+    // the users react to the message with the emoji (the bot itself counts too, as "me")
+    pub fn react(&mut self, m_id: MessageId, reaction: ReactionType, users: Vec<User>) {
+        let own_id = self.own_id;
+        let reaction_json = serde_json::json!({
+            "count": users.len(),
+            "count_details": {"burst": 0, "normal": users.len()},
+            "me": users.iter().any(|u| u.id == own_id),
+            "me_burst": false,
+            "emoji": reaction,
+            "burst_colors": [],
+        });
+        let r: MessageReaction = serde_json::from_value(reaction_json).unwrap();
+        for msg in self.messages.get_mut().unwrap().iter_mut().filter(|m| m.id == m_id) {
+            msg.reactions.retain(|old| old.reaction_type != r.reaction_type);
+            msg.reactions.push(r.clone());
+        }
+        self.reactions.get_mut().unwrap().insert((m_id, reaction.to_string()), users);
+    }
+
+    // the users vote for the answer (by its answer_id) of Discord's own poll
+    pub fn vote_native(&mut self, m_id: MessageId, answer_id: u8, users: Vec<User>) {
+        let answer_id: AnswerId = serde_json::from_value(answer_id.into()).unwrap();
+        self.answers.insert((m_id, answer_id), users);
+    }
+
+    // the users who reacted to the message with the emoji, as they are now
+    #[cfg(feature = "poll_creation")]
+    pub fn reacted(&self, m_id: MessageId, reaction: &ReactionType) -> Vec<UserId> {
+        self.reactions.lock().unwrap().get(&(m_id, reaction.to_string())).map(|users| users.iter().map(|u| u.id).collect())
+            .unwrap_or_default()
+    }
+
+    // the message as it is now (after the bot has edited it)
+    #[cfg(feature = "poll_creation")]
+    pub fn current(&self, m_id: MessageId) -> Option<Message> {
+        self.messages.lock().unwrap().iter().find(|m| m.id == m_id).cloned()
+    }
+
+    // a voice channel, in the category if given
+    pub fn add_voice_channel(&mut self, g_id: GuildId, ch_id: ChannelId, category: Option<ChannelId>) {
+        let mut ch = GuildChannel::default();
+        ch.id = ch_id;
+        ch.guild_id = g_id;
+        ch.kind = ChannelType::Voice;
+        ch.parent_id = category;
+        self.voice_channels.push(ch);
+    }
+
+    pub fn set_afk_channel(&mut self, g_id: GuildId, ch_id: ChannelId) {
+        self.afk.insert(g_id, ch_id);
+    }
+
+    pub fn join_voice(&mut self, g_id: GuildId, u_id: UserId, ch_id: ChannelId) {
+        self.join_voice_deafened(g_id, u_id, ch_id, false);
+    }
+
+    pub fn join_voice_deafened(&mut self, g_id: GuildId, u_id: UserId, ch_id: ChannelId, self_deaf: bool) {
+        let state_json = serde_json::json!({
+            "channel_id": ch_id,
+            "guild_id": g_id,
+            "user_id": u_id,
+            "deaf": false,
+            "mute": false,
+            "self_deaf": self_deaf,
+            "self_mute": false,
+            "self_video": false,
+            "session_id": "",
+            "suppress": false,
+        });
+        self.voice.entry(g_id).or_default().insert(u_id, serde_json::from_value(state_json).unwrap());
+    }
+
+    fn new_id(&self) -> u64 {
+        let mut id = self.next_id.lock().unwrap();
+        *id += 1;
+        *id
+    }
+}
+
+
+#[async_trait]
+impl DiscordGateway for FakeGateway {
+    fn current_user_id(&self) -> UserId {
+        self.own_id
+    }
+
+    async fn poll_store(&self) -> Option<Arc<PollStore>> {
+        self.store.clone()
+    }
+
+    async fn messages(&self, ch_id: ChannelId, before: Option<MessageId>, limit: u8) -> Result<Vec<Message>, serenity::Error> {
+        let mut result: Vec<Message> = self.messages.lock().unwrap().iter()
+            .filter(|m| m.channel_id == ch_id && before.is_none_or(|b| m.id < b))
+            .cloned()
+            .collect();
+        result.sort_by_key(|m| std::cmp::Reverse(m.id));
+        result.truncate(limit as usize);
+        Ok(result)
+    }
+
+    async fn message(&self, ch_id: ChannelId, m_id: MessageId) -> Result<Message, serenity::Error> {
+        self.messages.lock().unwrap().iter()
+            .find(|m| m.channel_id == ch_id && m.id == m_id)
+            .cloned()
+            .ok_or(serenity::Error::Other("Unknown Message"))
+    }
+
+    async fn reaction_users(&self, msg: &Message, reaction: ReactionType) -> Result<Vec<User>, serenity::Error> {
+        Ok(self.reactions.lock().unwrap().get(&(msg.id, reaction.to_string())).cloned().unwrap_or_default())
+    }
+
+    async fn poll_answer_voters(&self, msg: &Message, answer_id: AnswerId) -> Result<Vec<User>, serenity::Error> {
+        Ok(self.answers.get(&(msg.id, answer_id)).cloned().unwrap_or_default())
+    }
+
+    async fn guild_channel(&self, ch_id: ChannelId) -> Option<GuildChannel> {
+        self.channels.get(&ch_id).map(|(ch, _)| ch.clone())
+    }
+
+    #[cfg(feature = "poll_creation")]
+    fn cached_member(&self, g_id: GuildId, u_id: UserId) -> Option<Member> {
+        self.channels.values()
+            .flat_map(|(_, members)| members)
+            .find(|m| m.guild_id == g_id && m.user.id == u_id)
+            .cloned()
+    }
+
+    #[cfg(feature = "poll_creation")]
+    async fn user(&self, u_id: UserId) -> Result<User, serenity::Error> {
+        self.channels.values()
+            .flat_map(|(_, members)| members)
+            .find(|m| m.user.id == u_id)
+            .map(|m| m.user.clone())
+            .ok_or(serenity::Error::Other("Unknown User"))
+    }
+
+    fn guild_locale(&self, _g_id: GuildId) -> &'static str {
+        i18n::DEFAULT_LOCALE
+    }
+
+    fn channel_members(&self, ch: &GuildChannel) -> Result<Vec<Member>, String> {
+        match self.channels.get(&ch.id) {
+            Some((_, members)) => Ok(members.clone()),
+            None => Err("Unknown Channel".to_string()),
+        }
+    }
+
+    fn guild_channels(&self, g_id: GuildId) -> Vec<ChannelId> {
+        let mut channels: Vec<ChannelId> = self.channels.values().filter(|(ch, _)| ch.guild_id == g_id).map(|(ch, _)| ch.id).collect();
+        channels.sort();
+        channels
+    }
+
+    fn voice_states(&self, g_id: GuildId) -> Option<HashMap<UserId, VoiceState>> {
+        if !self.channels.values().any(|(ch, _)| ch.guild_id == g_id) {
+            return None;
+        }
+        Some(self.voice.get(&g_id).cloned().unwrap_or_default())
+    }
+
+    fn voice_channels(&self, g_id: GuildId) -> Vec<GuildChannel> {
+        self.voice_channels.iter().filter(|ch| ch.guild_id == g_id).cloned().collect()
+    }
+
+    fn afk_channel(&self, g_id: GuildId) -> Option<ChannelId> {
+        self.afk.get(&g_id).copied()
+    }
+
+    async fn active_threads(&self, g_id: GuildId) -> Result<Vec<GuildChannel>, serenity::Error> {
+        Ok(self.threads.lock().unwrap().iter().filter(|t| t.guild_id == g_id).cloned().collect())
+    }
+
+    async fn create_thread(&self, ch_id: ChannelId, name: String, kind: ChannelType) -> Result<ChannelId, serenity::Error> {
+        let Some((parent, _)) = self.channels.get(&ch_id) else {
+            return Err(serenity::Error::Other("Unknown Channel"));
+        };
+        let mut thread = GuildChannel::default();
+        thread.id = ChannelId::new(self.new_id());
+        thread.guild_id = parent.guild_id;
+        thread.parent_id = Some(ch_id);
+        thread.kind = kind;
+        thread.name = name;
+        self.threads.lock().unwrap().push(thread.clone());
+        Ok(thread.id)
+    }
+
+    async fn send_message(&self, ch_id: ChannelId, content: String) -> Result<Message, serenity::Error> {
+        let mut msg = Message::default();
+        msg.id = MessageId::new(self.new_id());
+        msg.channel_id = ch_id;
+        msg.author.id = self.own_id;
+        msg.content = content.clone();
+        self.sent.lock().unwrap().push((ch_id, content));
+        self.messages.lock().unwrap().push(msg.clone());
+        Ok(msg)
+    }
+
+    #[cfg(feature = "poll_creation")]
+    async fn edit_message(&self, ch_id: ChannelId, m_id: MessageId, content: String) -> Result<Message, serenity::Error> {
+        let mut messages = self.messages.lock().unwrap();
+        let Some(msg) = messages.iter_mut().find(|m| m.channel_id == ch_id && m.id == m_id) else {
+            return Err(serenity::Error::Other("Unknown Message"));
+        };
+        msg.content = content;
+        Ok(msg.clone())
+    }
+
+    #[cfg(feature = "poll_creation")]
+    async fn delete_reaction(&self, msg: &Message, u_id: Option<UserId>, reaction: ReactionType) -> Result<(), serenity::Error> {
+        let mut reactions = self.reactions.lock().unwrap();
+        let key = (msg.id, reaction.to_string());
+        match u_id {
+            Some(u_id) => if let Some(users) = reactions.get_mut(&key) {
+                users.retain(|u| u.id != u_id);
+            },
+            None => {reactions.remove(&key);},
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+//Message catalog: every user-facing string by its key, one file per locale in locales/
+//a locale is named by its Discord locale code, en is the default and the fallback for missing keys
+//the catalogs are built in, locales_dir of the config can replace them without rebuilding the bot
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use serenity::all::{CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption, GuildId};
+
+use crate::config;
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+const CATALOG_FILES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.toml")),
+    ("ru", include_str!("../locales/ru.toml")),
+];
+
+static CATALOGS: OnceLock<HashMap<&'static str, HashMap<String, String>>> = OnceLock::new();
+
+// tr!(loc, "key") or tr!(loc, "key", name = value, ...) for the "{name}" placeholders
+// (the arguments are dropped right away, so tr! can be used next to an .await)
+macro_rules! tr {
+    ($loc:expr, $key:expr) => {
+        $crate::i18n::text($loc, $key, &[])
+    };
+    ($loc:expr, $key:expr, $($name:ident = $value:expr),+ $(,)?) => {{
+        let s = $crate::i18n::text($loc, $key, &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+]);
+        s
+    }};
+}
+pub(crate) use tr;
+
+
+fn catalogs() -> &'static HashMap<&'static str, HashMap<String, String>> {
+    CATALOGS.get_or_init(|| CATALOG_FILES.iter()
+        .map(|(locale, src)| {
+            let mut catalog = match parse_catalog(src) {
+                Ok(c) => c,
+                Err(e) => panic!("locales/{locale}.toml: {e}"),
+            };
+            // the strings from locales_dir replace the built-in ones
+            if let Some(dir) = &config::get().locales_dir {
+                match read_catalog(dir, locale) {
+                    Some(Ok(c)) => catalog.extend(c),
+                    Some(Err(e)) => println!("{e}, using the built-in catalog"),
+                    None => {},
+                }
+            }
+            (*locale, catalog)
+        })
+        .collect())
+}
+
+// <dir>/<locale>.toml, None if there is no such file
+pub fn read_catalog(dir: &str, locale: &str) -> Option<Result<HashMap<String, String>, String>> {
+    let path = Path::new(dir).join(format!("{locale}.toml"));
+    let src = match std::fs::read_to_string(&path) {
+        Ok(src) => src,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => return Some(Err(format!("Can't read {}: {e}", path.display()))),
+    };
+    Some(parse_catalog(&src).map_err(|e| format!("{}: {e}", path.display())))
+}
+
+// the tables are flattened into dotted keys: [stats] none = "..." -> "stats.none"
+pub fn parse_catalog(src: &str) -> Result<HashMap<String, String>, String> {
+    fn flatten(prefix: &str, table: &toml::Table, out: &mut HashMap<String, String>) -> Result<(), String> {
+        for (k, v) in table {
+            let key = if prefix.is_empty() {k.clone()} else {format!("{prefix}.{k}")};
+            match v {
+                toml::Value::String(s) => {out.insert(key, s.clone());},
+                toml::Value::Table(t) => flatten(&key, t, out)?,
+                _ => return Err(format!("{key}: only strings and tables are allowed")),
+            }
+        }
+        Ok(())
+    }
+    let table: toml::Table = toml::from_str(src).map_err(|e| e.to_string())?;
+    let mut out = HashMap::new();
+    flatten("", &table, &mut out)?;
+    Ok(out)
+}
+
+pub fn locales() -> impl Iterator<Item = &'static str> {
+    CATALOG_FILES.iter().map(|(locale, _)| *locale)
+}
+
+
+// the first of the candidates we have a catalog for, "en-US" and "en-GB" are "en"
+pub fn pick(candidates: &[&str]) -> &'static str {
+    for c in candidates {
+        if let Some(locale) = locales().find(|l| l == c || c.split('-').next() == Some(l)) {
+            return locale;
+        }
+    }
+    DEFAULT_LOCALE
+}
+
+// the user's locale, then the server's (both come with every interaction)
+pub fn user_locale(locale: &str, guild_locale: Option<&str>) -> &'static str {
+    pick(&[locale, guild_locale.unwrap_or_default()])
+}
+
+pub fn command_locale(ci: &CommandInteraction) -> &'static str {
+    user_locale(&ci.locale, ci.guild_locale.as_deref())
+}
+
+// for everything that is not a reply to someone: the log threads, the poll messages, the reminders
+pub fn guild_locale(ctx: &Context, g_id: GuildId) -> &'static str {
+    match g_id.to_guild_cached(&ctx) {
+        Some(g) => pick(&[g.preferred_locale.as_str()]),
+        None => DEFAULT_LOCALE,
+    }
+}
+
+
+// the string in the locale (or the default one) with the placeholders filled in, the key itself if there is none
+pub fn text(locale: &str, key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let catalogs = catalogs();
+    let template = catalogs.get(locale).and_then(|c| c.get(key))
+        .or_else(|| catalogs[DEFAULT_LOCALE].get(key));
+    let Some(template) = template else {
+        println!("i18n: no \"{key}\" in the catalog");
+        return key.to_string();
+    };
+    let mut s = template.clone();
+    for (name, value) in args {
+        s = s.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    s
+}
+
+// every translation of the string, for recognising our own messages
+pub fn all_texts(key: &str) -> Vec<String> {
+    locales().map(|l| text(l, key, &[])).collect()
+}
+
+
+// the command with its description (and translated names) from commands.<name> of every catalog
+pub fn command(name: &str) -> CreateCommand {
+    let key = format!("commands.{name}");
+    let mut c = CreateCommand::new(name).description(text(DEFAULT_LOCALE, &format!("{key}.description"), &[]));
+    for locale in locales().filter(|l| *l != DEFAULT_LOCALE) {
+        if let Some(n) = translated(locale, &format!("{key}.name"), name) {
+            c = c.name_localized(locale, n);
+        }
+        c = c.description_localized(locale, text(locale, &format!("{key}.description"), &[]));
+    }
+    c
+}
+
+// the option with the name and description from the catalog section key (e.g. "commands.stats.days" or "options.poll")
+pub fn option(kind: CommandOptionType, name: &str, key: &str) -> CreateCommandOption {
+    let mut o = CreateCommandOption::new(kind, name, text(DEFAULT_LOCALE, &format!("{key}.description"), &[]));
+    for locale in locales().filter(|l| *l != DEFAULT_LOCALE) {
+        if let Some(n) = translated(locale, &format!("{key}.name"), name) {
+            o = o.name_localized(locale, n);
+        }
+        o = o.description_localized(locale, text(locale, &format!("{key}.description"), &[]));
+    }
+    o
+}
+
+// a choice of a string option, named by the key
+pub fn string_choice(o: CreateCommandOption, key: &str, value: &str) -> CreateCommandOption {
+    let names: Vec<(&str, String)> = locales().filter(|l| *l != DEFAULT_LOCALE)
+        .map(|l| (l, text(l, key, &[])))
+        .collect();
+    o.add_string_choice_localized(text(DEFAULT_LOCALE, key, &[]), value, names)
+}
+
+// the translated name if it differs from the original one
+fn translated(locale: &str, key: &str, name: &str) -> Option<String> {
+    catalogs().get(locale)?.get(key).filter(|n| *n != name).cloned()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    #[test]
+    fn every_locale_has_every_key() {
+        let default: BTreeSet<&String> = catalogs()[DEFAULT_LOCALE].keys().collect();
+        for locale in locales() {
+            let keys: BTreeSet<&String> = catalogs()[locale].keys().collect();
+            let missing: Vec<&&String> = default.difference(&keys).collect();
+            let unknown: Vec<&&String> = keys.difference(&default).collect();
+            assert!(missing.is_empty(), "{locale} is missing {missing:?}");
+            assert!(unknown.is_empty(), "{locale} has keys that {DEFAULT_LOCALE} doesn't: {unknown:?}");
+        }
+    }
+
+    #[test]
+    fn placeholders_are_the_same_in_every_locale() {
+        fn placeholders(s: &str) -> BTreeSet<&str> {
+            s.split('{').skip(1).filter_map(|p| p.split_once('}')).map(|(name, _)| name).collect()
+        }
+        for locale in locales() {
+            for (key, default) in &catalogs()[DEFAULT_LOCALE] {
+                let names = placeholders(&catalogs()[locale][key]);
+                assert_eq!(names, placeholders(default), "{locale}: {key}");
+                // literal braces would be taken for placeholders, they are passed as values instead
+                assert!(names.iter().all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')),
+                    "{locale}: {key} has a brace that is not a placeholder");
+            }
+        }
+    }
+
+    #[test]
+    fn locale_is_picked_by_language() {
+        assert_eq!(pick(&["ru"]), "ru");
+        assert_eq!(pick(&["en-US", "ru"]), "en");
+        assert_eq!(pick(&["de", "ru"]), "ru");
+        assert_eq!(pick(&["de", ""]), DEFAULT_LOCALE);
+    }
+
+    #[test]
+    fn placeholders_are_filled() {
+        assert_eq!(text("en", "stats.empty", &[("days", &30)]), "No attendance was recorded in the last 30 days.");
+        assert_eq!(text("en", "no.such.key", &[]), "no.such.key");
+    }
+}
@@ -1,49 +1,62 @@
+mod attendance;
+mod cli;
 mod commands;
+mod config;
+mod gateway;
+mod i18n;
+mod mention_pages;
+mod pagination;
+mod poll_sources;
+mod reminders;
+mod squads;
+mod storage;
 mod utils;
 
-use std::collections::HashMap;
 use std::env;
-use std::vec;
 use serenity::all::ActivityData;
-use serenity::all::ChannelId;
-use serenity::all::ChannelType;
-use serenity::all::PartialChannel;
-use serenity::all::User;
-use serenity::all::UserId;
 use serenity::all::GuildId;
+use serenity::all::Message;
+use std::sync::Arc;
 use serenity::async_trait;
 use serenity::model::gateway::Ready;
 use serenity::prelude::*;
 #[cfg(feature = "poll_creation")]
 use serenity::model::channel::Reaction;
-use serenity::utils::MessageBuilder;
 use serenity::builder::{CreateInteractionResponse, CreateInteractionResponseMessage};
-use serenity::model::application::{Command, Interaction};
+use serenity::model::application::{Command, CommandType, Interaction};
 
 
 #[cfg(feature = "third_party_bots")]
 mod tpbot_utils;
+#[cfg(feature = "poll_creation")]
+mod poll_buttons;
+#[cfg(feature = "poll_creation")]
+mod poll_closing;
+#[cfg(feature = "scheduled_polls")]
+mod scheduler;
+#[cfg(feature = "poll_creation")]
+use {commands::new_poll::NewPoll,
+    config::PollStyle,
+    serenity::all::ChannelId,
+    serenity::all::ChannelType,
+    serenity::all::CreateMessage,
+    serenity::all::User,
+    serenity::utils::MessageBuilder,
+    std::vec,
+};
 
 struct Handler;
 
-static REACTION_A: char = '✅';
-static REACTION_D: char = '❌';
-static REACTION_T: char = '❔';
-const POLL_OPTS: [char; 3] = [REACTION_A, REACTION_D, REACTION_T];
+const POLL_OPT_NAMES: [&str; 3] = ["Accepted", "Declined", "Tentative"];
 
-const BOT_ID_APOLLO:   u64 = 475744554910351370u64;  //Apollo bot
-// const BOT_ID_CARL:     u64 = 235148962103951360u64;  //Carl bot
-// const BOT_ID_JUNIPER:  u64 = 310848622642069504u64;  //Juniper bot
-const BOT_ID_PANCAKE:  u64 = 627525335423909909u64;  //Pancake bot
-const BOT_ID_POLLBOT:  u64 = 1470761370944405695u64; //Pollbot //TODO get from cache //TODO cache somewhere closer
 
 
 
 #[cfg(feature = "poll_creation")]
 enum ReactionChangeType {
-    ADD,
-    REMOVE,
-    REMOVEEMOJI,
+    Add,
+    Remove,
+    RemoveEmoji,
 }
 
 
@@ -53,11 +66,17 @@ impl EventHandler for Handler {
     // Event handlers are dispatched through a threadpool, and so multiple events can be
     // dispatched simultaneously.
 
+    // new message handler, records Discord's and 3rd party bots' polls as they are posted
+    async fn message(&self, ctx: Context, msg: Message)
+    {
+        utils::record_poll_if_supported(&ctx, &msg).await;
+    }
+
     // reaction add handler
     #[cfg(feature = "poll_creation")]
     async fn reaction_add(&self, ctx: Context, reaction: Reaction)
     {
-        match utils::handle_reaction_change(&ctx, reaction, ReactionChangeType::ADD).await {
+        match utils::handle_reaction_change(&ctx, reaction, ReactionChangeType::Add).await {
             Ok(s) => println!("reaction_add: {}", s),
             Err(e) => println!("reaction_add error: {}", e),
         }
@@ -68,7 +87,7 @@ impl EventHandler for Handler {
     #[cfg(feature = "poll_creation")]
     async fn reaction_remove(&self, ctx: Context, reaction: Reaction)
     {
-        match utils::handle_reaction_change(&ctx, reaction, ReactionChangeType::REMOVE).await {
+        match utils::handle_reaction_change(&ctx, reaction, ReactionChangeType::Remove).await {
             Ok(s) => println!("reaction_remove: {}", s),
             Err(e) => println!("reaction_remove error: {}", e),
         }
@@ -82,7 +101,7 @@ impl EventHandler for Handler {
     #[cfg(feature = "poll_creation")]
     async fn reaction_remove_emoji(&self, ctx: Context, reaction: Reaction)
     {
-        match utils::handle_reaction_change(&ctx, reaction, ReactionChangeType::REMOVEEMOJI).await {
+        match utils::handle_reaction_change(&ctx, reaction, ReactionChangeType::RemoveEmoji).await {
             Ok(s) => println!("reaction_remove_emoji: {}", s),
             Err(e) => println!("reaction_remove_emoji error: {}", e),
         }
@@ -96,38 +115,82 @@ impl EventHandler for Handler {
     async fn ready(&self, ctx: Context, ready: Ready) {
         println!("{} is connected!", ready.user.name);
 
-        //registering commands (guild-specific for now)
-        let guild_id = GuildId::from(330410844854943745); //own
-
-
-        let g_commands = guild_id
-            .set_commands(&ctx, vec![
-                //commands::test::register(),
-            ])
-            .await;
-        
-        println!("I now have the following guild slash commands: {g_commands:#?}");
-        let mut gcv: Vec<serenity::builder::CreateCommand> = vec![
-            commands::lineup::register(),
-            //commands::test::register(),
-            commands::get_accepted::register(),
-            commands::get_tentative::register(),
-            commands::get_no_vote::register(),
-            commands::get_not_in_voice::register(),
-        ];
-        if cfg!(feature="poll_creation") {
-            gcv.extend_from_slice(&[
-                commands::new_poll::register(),
-            ]);
+        let config = config::get();
+
+        //registering guild-specific commands in every dev guild
+        let gcv: Vec<serenity::builder::CreateCommand> = config.commands.guild.iter()
+            .filter_map(|name| commands::register(name))
+            .collect();
+        for g in &config.dev_guilds {
+            let guild_id = GuildId::from(*g);
+            let g_commands = guild_id
+                .set_commands(&ctx, gcv.clone())
+                .await;
+            println!("I now have the following guild slash commands in {guild_id}: {g_commands:#?}");
         }
+
+        //registering global commands
+        let gcv: Vec<serenity::builder::CreateCommand> = config.commands.global.iter()
+            .filter_map(|name| commands::register(name))
+            .collect();
         let g_commands = Command::set_global_commands(&ctx, gcv).await;
         println!("I now have the following global slash commands: {g_commands:#?}");
+
+        reminders::start(ctx.clone());
+        attendance::start(ctx.clone());
+        #[cfg(feature = "poll_creation")]
+        poll_closing::start(ctx.clone());
+        #[cfg(feature = "scheduled_polls")]
+        scheduler::start(ctx.clone());
     }
 
     async fn interaction_create(&self, ctx: Context, inter: Interaction) {
+        if let Interaction::Component(ci) = &inter {
+            // next/prev of the long mention lists
+            if mention_pages::is_page_button(&ci.data.custom_id) {
+                mention_pages::handle_click(&ctx, ci).await;
+                return;
+            }
+            // the edit button of a lineup
+            if commands::lineup::is_edit_button(&ci.data.custom_id) {
+                commands::lineup::handle_edit_click(&ctx, ci).await;
+                return;
+            }
+            // vote buttons of own polls
+            #[cfg(feature = "poll_creation")]
+            match poll_buttons::handle_click(&ctx, ci).await {
+                Ok(s) => {
+                    println!("button click: {}", s);
+                    if let Some(g_id) = ci.guild_id {
+                        if let Err(e) = utils::log_to_thread(&ctx, &s, &g_id, &ci.channel_id, &ci.message.id.to_string()).await {
+                            println!("{e}");
+                        }
+                    }
+                },
+                Err(e) => println!("button click error: {}", e),
+            }
+            return;
+        }
+
+        // the lineup edit form was submitted
+        if let Interaction::Modal(mi) = &inter {
+            if mi.data.custom_id == commands::lineup::EDIT_MODAL {
+                commands::lineup::handle_edit_submit(&ctx, mi).await;
+            }
+            return;
+        }
+
+        // suggestions for the options being typed
+        if let Interaction::Autocomplete(ac) = &inter {
+            if ac.data.name.as_str() == "get_option" {
+                commands::get_option::autocomplete(&ctx, ac).await;
+            }
+            return;
+        }
+
         if let Interaction::Command(cmd) = inter {
             let d_msg = CreateInteractionResponseMessage::new()
-            .content("Running command...")
+            .content(i18n::tr!(i18n::command_locale(&cmd), "common.running"))
             .ephemeral(true);
             let builder = CreateInteractionResponse::Defer(d_msg);
             if let Err(why) = cmd.create_response(&ctx.http, builder).await {
@@ -152,9 +215,25 @@ impl EventHandler for Handler {
                     "get_tentative" => {commands::get_tentative::run(&ctx, &cmd, g_id).await; return;},
                     "get_accepted" => {commands::get_accepted::run(&ctx, &cmd, g_id).await; return;},
                     "get_not_in_voice" => {commands::get_not_in_voice::run(&ctx, &cmd, g_id).await; return;},
+                    "get_option" => {commands::get_option::run(&ctx, &cmd, g_id).await; return;},
+                    "set_deadline" => {commands::set_deadline::run(&ctx, &cmd, g_id).await; return;},
+                    "set_event_time" => {commands::set_event_time::run(&ctx, &cmd, g_id).await; return;},
+                    "stats" => {commands::stats::run(&ctx, &cmd, g_id).await; return;},
+                    "export" => {commands::export::run(&ctx, &cmd, g_id).await; return;},
                     "lineup" => {commands::lineup::run(&ctx, &cmd, g_id).await; return;},
+                    #[cfg(feature = "poll_creation")]
+                    "new_poll" => {commands::new_poll::run(&ctx, &cmd, g_id).await; return;},
+                    #[cfg(feature = "poll_creation")]
+                    "close_poll" => {commands::close_poll::run(&ctx, &cmd, g_id).await; return;},
+                    #[cfg(feature = "scheduled_polls")]
+                    "schedule" => {commands::schedule::run(&ctx, &cmd, g_id).await; return;},
                     _ => {},
                 }
+                // message context menu commands
+                if cmd.data.kind == CommandType::Message {
+                    commands::context_menu::run(&ctx, &cmd, g_id).await;
+                    return;
+                }
             } else {
                     println!("No guild info");
             }
@@ -164,7 +243,9 @@ impl EventHandler for Handler {
 
 
 // creates new poll, returns a message that can be presented to the user requesting new poll
-pub async fn create_new_poll(ctx: &Context, channel_id: ChannelId, g_id: &GuildId, u: &User) -> Result<String, serenity::Error>
+#[cfg(feature = "poll_creation")]
+pub async fn create_new_poll(ctx: &Context, channel_id: ChannelId, g_id: &GuildId, u: &User, poll: &NewPoll,
+    loc: &str) -> Result<String, serenity::Error>
 {
     let g_ch = match g_id.to_guild_cached(&ctx).and_then(| g|g.channels.get(&channel_id).cloned()){
         Some(guild_channel) => guild_channel.to_owned(),
@@ -172,223 +253,127 @@ pub async fn create_new_poll(ctx: &Context, channel_id: ChannelId, g_id: &GuildI
             let ch = channel_id.to_channel(&ctx).await?;
             match ch.guild() {
                 Some(guild_channel) => guild_channel,
-                None => return Ok("Tried to create a poll not in a guild channel. Aborted.".to_string()),
+                None => return Ok(i18n::tr!(loc, "new_poll.not_in_guild")),
             }
         },
     }; 
 
     if g_ch.kind != ChannelType::Text {
-        return Ok("Tried to create a poll in a channel which is not a text channel. Aborted.".to_string());
+        return Ok(i18n::tr!(loc, "new_poll.not_text_channel"));
     }
     
-    //creating message
-    let msg = channel_id.say(&ctx.http, "Creating new poll...").await?;
+    let style = config::get().own_polls.style;
+    let store = storage::get(ctx).await;
+    if style == PollStyle::Buttons && store.is_none() {
+        return Ok(i18n::tr!(loc, "new_poll.no_store"));
+    }
+
+    //the poll itself is for everyone in the server
+    let gloc = i18n::guild_locale(ctx, *g_id);
+    let mut builder = CreateMessage::new()
+        .content(utils::render_own_poll(ctx, g_id, &vec![Vec::new(); poll.options.len()], &poll.options, None).await)
+        .embed(poll.embed(gloc));
+    if style == PollStyle::Buttons {
+        builder = builder.components(poll_buttons::buttons(&poll.options, gloc));
+    }
+    let msg = channel_id.send_message(&ctx.http, builder).await?;
 
     let log_message = MessageBuilder::new()
     .mention(u)
-    .push_safe(format!(" created a poll {}", msg.link()))
+    .push_safe(format!(" {}", i18n::tr!(gloc, "log.poll_created", title = poll.title, link = msg.link())))
     .build();
-    match utils::log_to_thread(&ctx, &log_message, g_id, &channel_id, &msg.id.to_string()).await
-    {
-        Err(e) => println!("{e}"),
-        _ => {},
+    if let Err(e) = utils::log_to_thread(ctx, &log_message, g_id, &channel_id, &msg.id.to_string()).await {
+        println!("{e}");
     }
     
-    //adding initial reactions sequentially
-    msg.react(&ctx, crate::REACTION_A).await?;
-    msg.react(&ctx, crate::REACTION_D).await?;
-    msg.react(&ctx, crate::REACTION_T).await?;
-    Ok("Successfully created a poll.".to_string())
-}
-
-
-// returns a string with non-menton mentions of all the people who left the selected emoji under the last vote post in the current
-// or parent text channel
-async fn mention_all_who_voted_emoji(ctx: &Context, pch: &PartialChannel, g_id: &GuildId, react: char, u: &User) 
--> Result<String, serenity::Error>
-{
-    if let Some (ch_id) = utils::find_suitable_channel( &pch){
-        if let Some(msg) = utils::find_last_own_message(ctx, ch_id).await
+    if let Some(store) = store {
+        let stored = utils::stored_poll_from_message(&msg, *g_id, &poll_sources::own::OwnPoll);
+        if let Err(e) = store.record_poll(&stored)
+            .and_then(|_| store.replace_votes(msg.id, &vec![Vec::new(); stored.options.len()]))
+            .and_then(|_| store.set_deadline(msg.id, poll.deadline.map(|d| d.unix_timestamp())))
+            .and_then(|_| store.set_event_at(msg.id, poll.event_at.map(|t| t.unix_timestamp())))
         {
-            let own_id = ctx.cache.current_user().id;
-            let users_p = msg.reaction_users(&ctx, react, Some(100u8), None).await?;
-            let mut names= String::from("");
-            let mut mentions= String::from("");
-            let mut cnt = 0;
-            for u in &users_p {
-                if u.id == own_id {continue;} //skipping own reactions
-                names += MessageBuilder::new()
-                .push_line_safe( match u.nick_in(&ctx, g_id).await
-                {
-                    Some(n) => n.to_string(),
-                    None => u.display_name().to_string(),
-                })
-                .build().as_str();
-                mentions += MessageBuilder::new()
-                .mention(u)
-                .build().as_str();
-                cnt+=1;
-            }
-            let log_message = MessageBuilder::new()
-                .mention(u)
-                .push_line_safe(format!(" requested the list of all members who voted \"{react}\" ({cnt}):"))
-                .push_line(match mentions.len() {0 => "".to_string(), _ => format!("{names}```{mentions}```"),})
-                .build();
-            utils::log_to_thread(&ctx, &log_message, g_id, &ch_id, &msg.id.to_string()).await?;
-            if cnt > 0 {
-                return Ok(format!("The following members selected \"{react}\" ({cnt}):\n{names}```{mentions}```").to_string());
-            } else {
-                return Ok(format!("Nobody selected \"{react}\".").to_string());
-            }
+            println!("create_new_poll: store error {e}");
         }
-        return Ok("Unable to find any members.".to_string());
-    } else {
-        return Ok("Unable to find the poll.".to_string());
     }
-}
 
-async fn mention_all_who_not_voted(ctx: &Context, pch: &PartialChannel, g_id: &GuildId, u: &User) -> Result<String, serenity::Error>
-{
-    if let Some (ch_id) = utils::find_suitable_channel( &pch)
-    {
-        if let Some(msg) = utils::find_last_own_message(ctx, ch_id).await
-        {
-            if let Ok(ch_members) = utils::get_members_from_channelid_cached(&ctx, &ch_id, g_id)
-            {
-                let own_id = ctx.cache.current_user().id;
-                let (reacted_p, reacted_n, reacted_t) = tokio::join!(
-                msg.reaction_users(&ctx, REACTION_A, Some(100u8), None),
-                msg.reaction_users(&ctx, REACTION_D, Some(100u8), None),
-                msg.reaction_users(&ctx, REACTION_T, Some(100u8), None),
-                );
-                let mut reacted: Vec<User>= vec![];
-                reacted.extend(reacted_p?);
-                reacted.extend(reacted_n?);
-                reacted.extend(reacted_t?);
-                let reacted_map: HashMap<UserId, User> = reacted
-                .into_iter()
-                .map(|user|(user.id, user))
-                .collect();
-
-                let mut names= String::from("");
-                let mut mentions= String::from("");
-                let mut cnt_not_v = 0;
-                let mut cnt = 0;
-
-                for m in ch_members{
-                    if m.user.id == own_id { continue; }
-                    if !reacted_map.contains_key(&m.user.id) {
-                        names += MessageBuilder::new()
-                        .push_line_safe( match &m.nick
-                        {
-                            Some(n) => n.to_string(),
-                            None => m.display_name().to_string(),
-                        })
-                        .build().as_str();
-                        mentions += MessageBuilder::new()
-                        .mention(&m)
-                        .build().as_str();
-                        cnt_not_v+=1;
-                    }
-                    cnt+=1;
-                }
-                let log_message = MessageBuilder::new()
-                .mention(u)
-                .push_line_safe(format!(" requested the list of all members who have not voted yet ({cnt_not_v}/{cnt}):"))
-                .push_line(match mentions.len() {0 => "".to_string(), _ => format!("{names}```{mentions}```"),})
-                .build();
-                utils::log_to_thread(&ctx, &log_message, g_id, &ch_id, &msg.id.to_string()).await?;
-                if cnt_not_v > 0 
-                {
-                    return Ok(format!("The following members have not voted yet ({cnt_not_v}/{cnt}):\n{names}```{mentions}```").to_string());
-                } else if cnt_not_v == 0 && cnt > 0 {
-                    return Ok(format!("Everyone's voted ({cnt_not_v}/{cnt}) 👌"));
-                }
-                return Ok("Unable to find any members.".to_string());
-            } else {
-                return Ok("No cached members found in the channel.".to_string());
-            }
+    //adding initial reactions sequentially
+    if style == PollStyle::Reactions {
+        for o in &poll.options {
+            msg.react(&ctx, poll_sources::own::reaction_of(&o.emoji)).await?;
         }
     }
-    Ok("mention_all_who_not_voted()".to_string())
+    Ok(i18n::tr!(loc, "new_poll.created", link = msg.link()))
 }
 
 
-// returns a string with non-menton mentions of all the people who left the selected emoji under the last vote post in the current
-// or parent text channel
-async fn mention_all_who_voted_emoji_not_in_voice(ctx: &Context, pch: &PartialChannel, g_id: &GuildId, react: char, u: &User) 
-    -> Result<String, serenity::Error>
+// test command
+
+
+
+// returns the positional arguments, the path given with --config and the token given with --token, if any
+fn parse_args() -> (Vec<String>, Option<String>, Option<String>)
 {
-    if let Some (ch_id) = utils::find_suitable_channel(&pch){
-        if let Some(msg) = utils::find_last_own_message(ctx, ch_id).await
-        {
-            let own_id = ctx.cache.current_user().id;
-            let users_p = msg.reaction_users(&ctx, react, Some(100u8), None).await?;
-            let mut names= String::from("");
-            let mut mentions= String::from("");
-            let mut cnt = 0;
-            let mut cnt_not_in_v = 0;
-            let mut cnt_in_v = 0;
-            let mut names_in_v= String::from("");
-
-
-            //get users in voice
-            let possibly_in_voice = utils::get_all_members_in_voice_cached(ctx, g_id);            
-
-            for u in &users_p {
-                if u.id == own_id {continue;} //skipping own reactions
-                cnt+=1;
-                let u_name = MessageBuilder::new()
-                    .push_line_safe( match utils::nick_in_from_cache(&ctx, &u.id, g_id)
-                    {
-                        Some(n) => n,
-                        None => u.display_name().to_string(),
-                    })
-                    .build();
-                if let Some(in_voice) = &possibly_in_voice {
-                    if in_voice.contains_key(&u.id) {
-                        println!("Found {} in voice channel!", u.name);
-                        names_in_v += u_name.as_str();
-                        cnt_in_v += 1;
-                        continue;
-                    }
-                }
-                names += u_name.as_str();
-                mentions += MessageBuilder::new()
-                .mention(u)
-                .build().as_str();
-                cnt_not_in_v+=1;
-            }
-            let log_message = MessageBuilder::new()
-            .mention(u)
-            .push_line_safe(format!(" requested the list of all members who voted \"{react}\" but are not in voice ({cnt_not_in_v}/{cnt}):"))
-            .push_line(match mentions.len() {0 => "".to_string(), _ => format!("{names}```{mentions}```"),})
-            .push_line(format!("Present in the voice channels right now ({cnt_in_v}/{cnt}):"))
-            .push_line(names_in_v)
-            .build();
-            utils::log_to_thread(&ctx, &log_message, g_id, &ch_id, &msg.id.to_string()).await?;
-
-            if cnt_not_in_v > 0 {
-                return Ok(format!("The following members selected \"{react}\" and are not present in any of the voice channels right now ({cnt_not_in_v}/{cnt}):\n{names}```{mentions}```").to_string());
-            } else if cnt > 0 && cnt_not_in_v == 0 {
-                return Ok(format!("Everyone's in voice ({cnt_in_v}/{cnt}) 👌"));
-            } else if cnt == 0 {
-                return Ok(format!("Nobody selected \"{react}\".").to_string());
-            }
+    let mut positional = Vec::new();
+    let mut config_path = None;
+    let mut token = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(a) = args.next() {
+        if a == "--config" {
+            config_path = args.next();
+        } else if let Some(p) = a.strip_prefix("--config=") {
+            config_path = Some(p.to_string());
+        } else if a == "--token" {
+            token = args.next();
+        } else if let Some(t) = a.strip_prefix("--token=") {
+            token = Some(t.to_string());
+        } else {
+            positional.push(a);
         }
-        return Ok("Unable to find any members.".to_string());
-    } else {
-        return Ok("Unable to find the poll.".to_string());
     }
+    (positional, config_path, token)
 }
 
 
-// test command
-
-
-
 #[tokio::main]
 async fn main() {
+    let (args, config_arg, token_option) = parse_args();
+    // pollbot analyse/dump instead of the bot
+    let command = cli::parse(&args);
+
+    // Load the config from --config or POLLBOT_CONFIG, falling back to pollbot.toml (if present) and built-in defaults
+    let config_path = config_arg.or(env::var("POLLBOT_CONFIG").ok());
+    let cfg = match config_path {
+        Some(path) => config::load(&path),
+        None if std::path::Path::new(config::DEFAULT_CONFIG_PATH).exists() => config::load(config::DEFAULT_CONFIG_PATH),
+        None => {
+            println!("No config file given, using the defaults.");
+            Ok(config::Config::default())
+        },
+    };
+    // A broken config won't fix itself, so exit with 1 (the service doesn't restart on it) instead of panicking
+    let cfg = match cfg {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    config::init(cfg);
+
+    if let Some(command) = command {
+        let token = env::var("DISCORD_TOKEN").ok().or(token_option);
+        let result = match command {
+            Ok(c) => cli::run(c, token).await,
+            Err(usage) => Err(usage),
+        };
+        if let Err(e) = result {
+            println!("{e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // Configure the client with your Discord bot token in the environment.
     let token = match env::var("DISCORD_TOKEN")
     {
@@ -396,12 +381,13 @@ async fn main() {
         Err(e) => {
             println!("{e}");
             println!("Not found a DISCORD_TOKEN in the environment variables, trying the first argument...");
-            match std::env::args().nth(1){
+            match token_option.or(args.into_iter().next()) {
                 Some(a) => a,
                 None => panic!("Not found anything in the first argument, exiting. Have you forgotten to supply the token?"),
             }
         },
     };
+
     // Set gateway intents, which decides what events the bot will be notified about
     let intents = GatewayIntents::GUILD_MESSAGES
         | GatewayIntents::DIRECT_MESSAGES
@@ -413,12 +399,26 @@ async fn main() {
         | GatewayIntents::GUILD_VOICE_STATES        // needed for voice channel presence
         | GatewayIntents::GUILDS;                   // needed for voice channel presence
 
+    // Open the poll store, the path can be set with POLLBOT_DB
+    let db_path = env::var("POLLBOT_DB").unwrap_or(storage::DEFAULT_DB_PATH.to_string());
+    let store = match storage::PollStore::open(&db_path) {
+        Ok(store) => Some(Arc::new(store)),
+        Err(e) => {
+            println!("Can't open the poll store at {db_path}: {e}. Running without it.");
+            None
+        },
+    };
+
     // Create a new instance of the Client, logging in as a bot. This will automatically prepend
     // your bot token with "Bot ", which is a requirement by Discord for bot users.
     let ad= ActivityData::custom("v".to_owned() + env!("CARGO_PKG_VERSION"));
-    let mut client = Client::builder(&token, intents)
+    let mut client_builder = Client::builder(&token, intents)
             .activity(ad)
-            .event_handler(Handler).await.expect("Err creating client");
+            .event_handler(Handler);
+    if let Some(store) = store {
+        client_builder = client_builder.type_map_insert::<storage::PollStore>(store);
+    }
+    let mut client = client_builder.await.expect("Err creating client");
 
     // Finally, start a single shard, and start listening to events.
     //
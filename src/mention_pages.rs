@@ -0,0 +1,122 @@
+//Long mention lists as an embed with next/prev buttons, the lists are kept in memory while the buttons can be used
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serenity::all::{ButtonStyle, CommandInteraction, ComponentInteraction, Context, CreateActionRow, CreateButton, CreateEmbed,
+    CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseFollowup, CreateInteractionResponseMessage, UserId};
+
+use crate::i18n::{self, tr};
+use crate::utils::{join_mentions, LEN_LIMIT_UID_MENTION};
+
+const PAGE_PREFIX: &str = "mentions:";
+// interaction tokens expire after 15 minutes, the ephemeral message can't be updated after that anyway
+const KEEP_FOR: Duration = Duration::from_secs(15 * 60);
+// Discord's limit for the embed description
+const LEN_LIMIT_EMBED_DESCRIPTION: usize = 4096;
+pub const PER_PAGE: usize = LEN_LIMIT_EMBED_DESCRIPTION / LEN_LIMIT_UID_MENTION;
+
+// the lists by the id of the command interaction that produced them, with the time they were sent
+type Lists = HashMap<u64, (Instant, Vec<UserId>)>;
+static LISTS: Mutex<Option<Lists>> = Mutex::new(None);
+
+
+// sends the first page, the buttons are handled by handle_click
+pub async fn send(ctx: &Context, text: &String, uids: &[UserId], ci: &CommandInteraction) {
+    let key = ci.id.get();
+    {
+        let mut lists = LISTS.lock().unwrap();
+        let lists = lists.get_or_insert_with(HashMap::new);
+        lists.retain(|_, (t, _)| t.elapsed() < KEEP_FOR);
+        lists.insert(key, (Instant::now(), uids.to_vec()));
+    }
+    let followup_msg = CreateInteractionResponseFollowup::new()
+        .content(text)
+        .embed(page_embed(uids, 0, i18n::command_locale(ci)))
+        .components(page_buttons(key, 0, page_count(uids.len())))
+        .ephemeral(true);
+    if let Err(why) = ci.create_followup(&ctx.http, followup_msg).await {
+        println!("Cannot respond to slash command: {why}");
+    }
+}
+
+pub fn is_page_button(custom_id: &str) -> bool {
+    custom_id.starts_with(PAGE_PREFIX)
+}
+
+// shows the page the button points to
+pub async fn handle_click(ctx: &Context, ci: &ComponentInteraction) {
+    let Some((key, page)) = parse_custom_id(&ci.data.custom_id) else {
+        println!("Unknown button {}", ci.data.custom_id);
+        return;
+    };
+    let loc = i18n::user_locale(&ci.locale, ci.guild_locale.as_deref());
+    let uids = LISTS.lock().unwrap().as_ref()
+        .and_then(|l| l.get(&key))
+        .map(|(_, uids)| uids.clone());
+    let msg = match uids {
+        Some(uids) => {
+            let page = page.min(page_count(uids.len()) - 1);
+            CreateInteractionResponseMessage::new()
+                .embed(page_embed(&uids, page, loc))
+                .components(page_buttons(key, page, page_count(uids.len())))
+        },
+        None => CreateInteractionResponseMessage::new()
+            .content(tr!(loc, "mentions.expired"))
+            .embeds(Vec::new())
+            .components(Vec::new()),
+    };
+    if let Err(why) = ci.create_response(&ctx.http, CreateInteractionResponse::UpdateMessage(msg)).await {
+        println!("Cannot update the mention list: {why}");
+    }
+}
+
+
+pub fn page_count(n_uids: usize) -> usize {
+    n_uids.div_ceil(PER_PAGE).max(1)
+}
+
+fn page_embed(uids: &[UserId], page: usize, loc: &str) -> CreateEmbed {
+    let start = (page * PER_PAGE).min(uids.len());
+    let end = (start + PER_PAGE).min(uids.len());
+    CreateEmbed::new()
+        .description(join_mentions(&uids[start..end]))
+        .footer(CreateEmbedFooter::new(tr!(loc, "mentions.page", page = page + 1, pages = page_count(uids.len()),
+            from = start + 1, to = end, n = uids.len())))
+}
+
+fn page_buttons(key: u64, page: usize, pages: usize) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{PAGE_PREFIX}{key}:{}", page.saturating_sub(1)))
+            .label("◀")
+            .style(ButtonStyle::Secondary)
+            .disabled(page == 0),
+        CreateButton::new(format!("{PAGE_PREFIX}{key}:{}", page + 1))
+            .label("▶")
+            .style(ButtonStyle::Secondary)
+            .disabled(page + 1 >= pages),
+    ])]
+}
+
+// "mentions:<key>:<page>"
+fn parse_custom_id(custom_id: &str) -> Option<(u64, usize)> {
+    let (key, page) = custom_id.strip_prefix(PAGE_PREFIX)?.split_once(':')?;
+    Some((key.parse().ok()?, page.parse().ok()?))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages() {
+        assert_eq!(PER_PAGE, 178);
+        assert_eq!(page_count(0), 1);
+        assert_eq!(page_count(178), 1);
+        assert_eq!(page_count(179), 2);
+        assert_eq!(parse_custom_id("mentions:123:4"), Some((123, 4)));
+        assert_eq!(parse_custom_id("vote:1"), None);
+    }
+}
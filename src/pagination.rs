@@ -0,0 +1,158 @@
+//Paginated fetching of voters, Discord returns at most 100 users per request
+
+use serenity::all::{AnswerId, Http, Message, ReactionType, User, UserId};
+use serenity::async_trait;
+
+pub const PAGE_SIZE: u8 = 100;
+
+// one kind of "list of users" endpoint, users are returned sorted by id starting after the given one
+#[async_trait]
+pub trait UserPages: Send + Sync {
+    async fn page(&self, after: Option<UserId>, limit: u8) -> Result<Vec<User>, serenity::Error>;
+}
+
+// users who reacted to the message with the emoji
+pub struct ReactionPages<'a> {
+    pub http: &'a Http,
+    pub msg: &'a Message,
+    pub reaction: ReactionType,
+}
+
+#[async_trait]
+impl UserPages for ReactionPages<'_> {
+    async fn page(&self, after: Option<UserId>, limit: u8) -> Result<Vec<User>, serenity::Error> {
+        self.msg.reaction_users(self.http, self.reaction.clone(), Some(limit), after).await
+    }
+}
+
+// users who selected the answer of Discord's own poll
+pub struct PollAnswerPages<'a> {
+    pub http: &'a Http,
+    pub msg: &'a Message,
+    pub answer_id: AnswerId,
+}
+
+#[async_trait]
+impl UserPages for PollAnswerPages<'_> {
+    async fn page(&self, after: Option<UserId>, limit: u8) -> Result<Vec<User>, serenity::Error> {
+        self.http.get_poll_answer_voters(self.msg.channel_id, self.msg.id, self.answer_id, after, Some(limit)).await
+    }
+}
+
+
+// keeps requesting pages until a short one comes back
+pub async fn fetch_all(pages: &dyn UserPages) -> Result<Vec<User>, serenity::Error>
+{
+    let mut users: Vec<User> = Vec::new();
+    let mut after: Option<UserId> = None;
+    loop {
+        let page = pages.page(after, PAGE_SIZE).await?;
+        let n = page.len();
+        after = page.last().map(|u| u.id);
+        users.extend(page);
+        if n < PAGE_SIZE as usize || after.is_none() {break;}
+    }
+    Ok(users)
+}
+
+
+// all the users who reacted to the message with the emoji
+pub async fn all_reaction_users(http: &Http, msg: &Message, reaction: impl Into<ReactionType>)
+    -> Result<Vec<User>, serenity::Error>
+{
+    fetch_all(&ReactionPages { http, msg, reaction: reaction.into() }).await
+}
+
+// all the users who selected the answer of Discord's own poll
+pub async fn all_poll_answer_voters(http: &Http, msg: &Message, answer_id: AnswerId)
+    -> Result<Vec<User>, serenity::Error>
+{
+    fetch_all(&PollAnswerPages { http, msg, answer_id }).await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    // answers like the API would, remembers every request
+    struct StubPages {
+        users: Vec<u64>,
+        fail_on_request: Option<usize>,
+        requests: Mutex<Vec<(Option<UserId>, u8)>>,
+    }
+
+    impl StubPages {
+        fn with_users(n: u64) -> StubPages {
+            StubPages { users: (1..=n).collect(), fail_on_request: None, requests: Mutex::new(Vec::new()) }
+        }
+    }
+
+    #[async_trait]
+    impl UserPages for StubPages {
+        async fn page(&self, after: Option<UserId>, limit: u8) -> Result<Vec<User>, serenity::Error> {
+            let mut requests = self.requests.lock().unwrap();
+            requests.push((after, limit));
+            if self.fail_on_request == Some(requests.len()) {
+                return Err(serenity::Error::Other("stubbed failure"));
+            }
+            let after = after.map(|u| u.get()).unwrap_or(0);
+            Ok(self.users.iter()
+                .filter(|id| **id > after)
+                .take(limit as usize)
+                .map(|id| {
+                    let mut u = User::default();
+                    u.id = UserId::new(*id);
+                    u
+                })
+                .collect())
+        }
+    }
+
+    fn ids(users: &[User]) -> Vec<u64> {
+        users.iter().map(|u| u.id.get()).collect()
+    }
+
+    #[tokio::test]
+    async fn no_users_is_one_request() {
+        let stub = StubPages::with_users(0);
+        let users = fetch_all(&stub).await.unwrap();
+        assert!(users.is_empty());
+        assert_eq!(*stub.requests.lock().unwrap(), vec![(None, PAGE_SIZE)]);
+    }
+
+    #[tokio::test]
+    async fn fewer_than_a_page() {
+        let stub = StubPages::with_users(42);
+        let users = fetch_all(&stub).await.unwrap();
+        assert_eq!(ids(&users), (1..=42).collect::<Vec<u64>>());
+        assert_eq!(stub.requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn exactly_one_page_asks_for_the_next_one() {
+        let stub = StubPages::with_users(100);
+        let users = fetch_all(&stub).await.unwrap();
+        assert_eq!(users.len(), 100);
+        assert_eq!(*stub.requests.lock().unwrap(), vec![(None, PAGE_SIZE), (Some(UserId::new(100)), PAGE_SIZE)]);
+    }
+
+    #[tokio::test]
+    async fn many_pages_follow_the_cursor() {
+        let stub = StubPages::with_users(250);
+        let users = fetch_all(&stub).await.unwrap();
+        assert_eq!(ids(&users), (1..=250).collect::<Vec<u64>>());
+        let afters: Vec<Option<UserId>> = stub.requests.lock().unwrap().iter().map(|(a, _)| *a).collect();
+        assert_eq!(afters, vec![None, Some(UserId::new(100)), Some(UserId::new(200))]);
+    }
+
+    #[tokio::test]
+    async fn error_in_the_middle_is_returned() {
+        let mut stub = StubPages::with_users(250);
+        stub.fail_on_request = Some(2);
+        assert!(fetch_all(&stub).await.is_err());
+        assert_eq!(stub.requests.lock().unwrap().len(), 2);
+    }
+}
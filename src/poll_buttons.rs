@@ -0,0 +1,156 @@
+//Own polls with buttons instead of reactions, the votes are kept in the store (one option per user)
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use serenity::all::{ActionRowComponent, ButtonKind, ButtonStyle, ComponentInteraction, Context, CreateActionRow,
+    CreateButton, CreateInteractionResponse, CreateInteractionResponseFollowup, EditInteractionResponse, Mentionable, Message,
+    MessageId, Timestamp};
+
+use crate::config::OwnPollOption;
+use crate::i18n::{self, tr};
+use crate::poll_sources::{own, OptionKind};
+use crate::storage;
+use crate::utils;
+
+pub const VOTE_PREFIX: &str = "pollbot_vote:";
+pub const CLEAR: &str = "pollbot_clear";
+
+// clicks on the same poll are handled one at a time, from recording the vote to editing the message,
+// so the message never shows older votes than the store has
+static POLL_LOCKS: OnceLock<Mutex<HashMap<MessageId, Arc<tokio::sync::Mutex<()>>>>> = OnceLock::new();
+
+
+// a button for every option and "clear my vote", 5 per row
+pub fn buttons(options: &[OwnPollOption], loc: &str) -> Vec<CreateActionRow>
+{
+    let mut buttons: Vec<CreateButton> = Vec::new();
+    for (i, o) in options.iter().enumerate() {
+        let style = match o.kind {
+            OptionKind::Accepted => ButtonStyle::Success,
+            OptionKind::Declined => ButtonStyle::Danger,
+            OptionKind::Tentative => ButtonStyle::Primary,
+            OptionKind::Other => ButtonStyle::Secondary,
+        };
+        buttons.push(CreateButton::new(format!("{VOTE_PREFIX}{i}"))
+            .label(&o.label)
+            .emoji(own::reaction_of(&o.emoji))
+            .style(style));
+    }
+    buttons.push(CreateButton::new(CLEAR)
+        .label(tr!(loc, "poll.clear_vote"))
+        .style(ButtonStyle::Secondary));
+    buttons.chunks(5).map(|row| CreateActionRow::Buttons(row.to_vec())).collect()
+}
+
+
+// returns true if the message has our vote buttons
+pub fn is_button_poll(msg: &Message) -> bool
+{
+    msg.components.iter()
+        .flat_map(|row| &row.components)
+        .any(|c| match c {
+            ActionRowComponent::Button(b) => matches!(&b.data, ButtonKind::NonLink { custom_id, .. } if custom_id.starts_with(VOTE_PREFIX)),
+            _ => false,
+        })
+}
+
+
+// handles a click on one of the vote buttons, records the vote and updates the poll message
+// returns a line for the log thread
+pub async fn handle_click(ctx: &Context, ci: &ComponentInteraction) -> Result<String, serenity::Error>
+{
+    let msg = &ci.message;
+    let Some(g_id) = ci.guild_id else {
+        return Ok(tr!(i18n::DEFAULT_LOCALE, "log.button_outside_guild"));
+    };
+    let log_loc = i18n::guild_locale(ctx, g_id);
+    let options = own::own_poll_options(msg);
+    let option_idx: Option<usize> = if ci.data.custom_id == CLEAR {
+        None
+    } else {
+        match ci.data.custom_id.strip_prefix(VOTE_PREFIX).and_then(|i| i.parse::<usize>().ok()) {
+            Some(i) if i < options.len() => Some(i),
+            _ => return Ok(tr!(log_loc, "log.unknown_button", id = ci.data.custom_id)),
+        }
+    };
+    // rendering the names can take a while on a large poll, the click is acknowledged first
+    // so it doesn't fail, the message is updated when it's ready
+    ci.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await?;
+    // the replies are for the voter, the returned line is for the log thread
+    let loc = i18n::user_locale(&ci.locale, ci.guild_locale.as_deref());
+    if own::is_closed(ctx, msg).await {
+        followup_ephemeral(ctx, ci, &tr!(loc, "buttons.closed")).await?;
+        return Ok(tr!(log_loc, "log.clicked_closed", user = ci.user.name));
+    }
+    let Some(store) = storage::get(ctx).await else {
+        followup_ephemeral(ctx, ci, &tr!(loc, "buttons.unavailable")).await?;
+        return Ok(tr!(log_loc, "log.vote_not_recorded"));
+    };
+
+    let lock = poll_lock(msg.id);
+    let _guard = lock.lock().await;
+    let votes = match store.record_poll(&utils::stored_poll_from_message(msg, g_id, &own::OwnPoll))
+        // the store is the only place the votes of a button poll are kept, starting from scratch if it doesn't know them
+        .and_then(|_| match store.votes(msg.id, options.len())? {
+            Some(_) => Ok(()),
+            None => store.replace_votes(msg.id, &vec![Vec::new(); options.len()]),
+        })
+        .and_then(|_| store.set_vote(msg.id, ci.user.id, option_idx, Timestamp::now().unix_timestamp()))
+        .and_then(|_| store.votes(msg.id, options.len()))
+    {
+        Ok(Some(v)) => v,
+        Ok(None) => vec![Vec::new(); options.len()],
+        Err(e) => {
+            println!("handle_click: store error {e}");
+            followup_ephemeral(ctx, ci, &tr!(loc, "buttons.failed")).await?;
+            return Ok(tr!(log_loc, "log.vote_store_error", error = e));
+        },
+    };
+
+    let text = utils::render_own_poll(ctx, &g_id, &votes, &options, None).await;
+    ci.edit_response(&ctx.http, EditInteractionResponse::new().content(text)).await?;
+
+    let user_string = format!("{} `{}`", ci.user.display_name(), ci.user.id.mention());
+    Ok(match option_idx {
+        Some(i) => tr!(log_loc, "log.voted", user = user_string, option = options[i].emoji),
+        None => tr!(log_loc, "log.cleared_vote", user = user_string),
+    })
+}
+
+
+// the lock of the poll's clicks, the locks nobody holds or waits for are dropped
+fn poll_lock(m_id: MessageId) -> Arc<tokio::sync::Mutex<()>>
+{
+    let mut locks = POLL_LOCKS.get_or_init(Default::default).lock().unwrap();
+    locks.retain(|_, l| Arc::strong_count(l) > 1);
+    locks.entry(m_id).or_default().clone()
+}
+
+
+// the click is already acknowledged, the replies to the voter are followups
+async fn followup_ephemeral(ctx: &Context, ci: &ComponentInteraction, text: &str) -> Result<(), serenity::Error>
+{
+    let builder = CreateInteractionResponseFollowup::new()
+        .content(text)
+        .ephemeral(true);
+    ci.create_followup(&ctx.http, builder).await?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn clicks_on_a_poll_wait_for_each_other() {
+        let m_id = MessageId::new(1);
+        let lock = poll_lock(m_id);
+        let guard = lock.lock().await;
+        assert!(poll_lock(m_id).try_lock().is_err());
+        assert!(poll_lock(MessageId::new(2)).try_lock().is_ok());
+        drop(guard);
+        assert!(poll_lock(m_id).try_lock().is_ok());
+    }
+}
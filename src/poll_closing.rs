@@ -0,0 +1,116 @@
+//Closing own polls: the votes are frozen in the store and in the message, new votes are ignored
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serenity::all::{Context, EditMessage, GuildId, Message, Timestamp};
+
+use crate::config;
+use crate::i18n::{self, tr};
+use crate::poll_buttons;
+use crate::poll_sources::own;
+use crate::storage;
+use crate::utils;
+
+// how often the deadlines are checked, in seconds
+const CHECK_INTERVAL: u64 = 60;
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+
+// closes own poll, by is who or what closed it (for the log thread)
+// returns a message that can be presented to the user (in loc)
+pub async fn close(ctx: &Context, msg: Message, g_id: GuildId, by: &str, loc: &str) -> Result<String, String>
+{
+    if !own::is_own_poll(&msg, &ctx.cache.current_user().id) {
+        return Err(tr!(loc, "close.not_own"));
+    }
+    if own::is_closed(ctx, &msg).await {
+        return Err(tr!(loc, "close.already_closed", link = msg.link()));
+    }
+    let now = Timestamp::now().unix_timestamp();
+    let link = msg.link();
+    let options = own::own_poll_options(&msg);
+
+    // closing in the store first, the votes coming in from now on are ignored
+    if let Some(store) = storage::get(ctx).await {
+        if let Err(e) = store.record_poll(&utils::stored_poll_from_message(&msg, g_id, &own::OwnPoll))
+            .and_then(|_| store.close_poll(msg.id, now))
+        {
+            return Err(tr!(loc, "close.failed", error = e));
+        }
+    }
+
+    let (channel_id, message_id) = (msg.channel_id, msg.id);
+    if poll_buttons::is_button_poll(&msg) {
+        let votes = own::get_own_poll_votes(ctx, &msg).await.map_err(|e| e.to_string())?;
+        let text = utils::render_own_poll(ctx, &g_id, &votes, &options, Some(now)).await;
+        let mut msg = msg;
+        msg.edit(&ctx, EditMessage::new().content(text).components(Vec::new())).await
+            .map_err(|e| tr!(loc, "close.update_failed", error = e))?;
+    } else {
+        // the final read of the reactions, it also brings the store up to date
+        utils::edit_msg_with_reactions(ctx, msg, &g_id, None, None, Some(now)).await
+            .map_err(|e| tr!(loc, "close.update_failed", error = e))?;
+    }
+
+    // the final lists go to the log thread too
+    let log_message = tr!(i18n::guild_locale(ctx, g_id), "log.closed", by = by);
+    if let Err(e) = utils::log_to_thread(ctx, &log_message, &g_id, &channel_id, &message_id.to_string()).await {
+        println!("{e}");
+    }
+    match channel_id.message(&ctx.http, message_id).await {
+        Ok(m) => if let Err(e) = utils::log_to_thread(ctx, &m.content, &g_id, &channel_id, &message_id.to_string()).await {
+            println!("{e}");
+        },
+        Err(e) => println!("close: can't read the closed poll {message_id}: {e}"),
+    }
+    Ok(tr!(loc, "close.closed", link = link))
+}
+
+
+// starts closing the polls whose deadline has passed, only the first call does anything
+pub fn start(ctx: Context) {
+    if !config::get().own_polls.close_at_deadline || STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(CHECK_INTERVAL));
+        loop {
+            interval.tick().await;
+            close_due(&ctx).await;
+        }
+    });
+}
+
+async fn close_due(ctx: &Context) {
+    let Some(store) = storage::get(ctx).await else {return;};
+    let now = Timestamp::now().unix_timestamp();
+    let due = match store.polls_to_close(ctx.cache.current_user().id, now) {
+        Ok(d) => d,
+        Err(e) => {
+            println!("close_due: store error {e}");
+            return;
+        },
+    };
+    for poll in due {
+        let result = match poll.channel_id.message(&ctx.http, poll.message_id).await {
+            Ok(msg) => {
+                let loc = i18n::guild_locale(ctx, poll.guild_id);
+                close(ctx, msg, poll.guild_id, &tr!(loc, "close.by_deadline"), loc).await
+            },
+            Err(e) => Err(format!("can't get the poll message: {e}")),
+        };
+        let result = match result {
+            Ok(r) => r,
+            Err(e) => {
+                // deleted, inaccessible or broken, not trying again every CHECK_INTERVAL
+                if let Err(e) = store.close_poll(poll.message_id, now) {
+                    println!("close_due: store error {e}");
+                }
+                e
+            },
+        };
+        println!("close_due: poll {}: {result}", poll.message_id);
+    }
+}
@@ -0,0 +1,219 @@
+//Poll sources: everything that can post a poll we know how to read
+//Adding a poll bot that lists the names in the embed fields means adding a module here and listing it in BOT_FORMATS
+//and default_bot_configs()
+
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+use serenity::all::{Message, UserId};
+use serenity::async_trait;
+
+use crate::gateway::DiscordGateway;
+
+#[cfg(feature = "third_party_bots")]
+use {crate::config::{self, BotConfig},
+    crate::i18n::tr,
+};
+
+pub mod own;
+pub mod native;
+#[cfg(feature = "third_party_bots")]
+pub mod apollo;
+#[cfg(feature = "third_party_bots")]
+pub mod pancake;
+#[cfg(feature = "third_party_bots")]
+pub mod carl;
+#[cfg(feature = "third_party_bots")]
+pub mod juniper;
+#[cfg(feature = "third_party_bots")]
+pub mod sesh;
+#[cfg(feature = "third_party_bots")]
+pub mod raid_helper;
+
+// takes the value of an embed field, returns the names listed there (or None if there are none)
+pub type SplitNamesFn = fn(&str) -> Option<Vec<String>>;
+
+static REGISTRY: OnceLock<Vec<Box<dyn PollSource>>> = OnceLock::new();
+
+
+// what an option means for the queries about "accepted", "declined" or "tentative" voters
+// several options can have the same kind (e.g. "Tank", "Healer" and "DPS" are all "accepted")
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OptionKind {
+    Accepted,
+    Declined,
+    Tentative,
+    Other,
+}
+
+impl OptionKind {
+    // kind of the option by its position in the classic "accepted", "declined", "tentative" poll
+    pub fn by_index(i: usize) -> OptionKind {
+        match i {
+            0 => OptionKind::Accepted,
+            1 => OptionKind::Declined,
+            2 => OptionKind::Tentative,
+            _ => OptionKind::Other,
+        }
+    }
+
+    // as written in the config
+    pub fn name(&self) -> &'static str {
+        match self {
+            OptionKind::Accepted => "accepted",
+            OptionKind::Declined => "declined",
+            OptionKind::Tentative => "tentative",
+            OptionKind::Other => "other",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PollOption {
+    pub label: String,
+    pub kind: OptionKind,
+}
+
+// "Accepted", "Declined" and "Tentative"
+pub fn default_options() -> Vec<PollOption>
+{
+    crate::POLL_OPT_NAMES.iter().enumerate()
+        .map(|(i, n)| PollOption { label: n.to_string(), kind: OptionKind::by_index(i) })
+        .collect()
+}
+
+// voters for every option of the poll, in the same order as the options
+pub enum PollVoters {
+    #[cfg(feature = "third_party_bots")]
+    Names(Vec<Vec<String>>),
+    Ids(Vec<Vec<UserId>>),
+}
+
+
+#[async_trait]
+pub trait PollSource: Send + Sync {
+    // human-readable name for the replies
+    fn name(&self) -> &str;
+
+    // returns true if the message is a poll from this source
+    fn recognises(&self, msg: &Message, own_id: UserId) -> bool;
+
+    // extracts the voters for every option (see options()) and a message with user-presentable warnings (might be empty)
+    // the warnings and the errors are in loc
+    async fn extract(&self, gw: &dyn DiscordGateway, msg: &Message, loc: &str) -> Result<(PollVoters, String), String>;
+
+    // options of the poll
+    fn options(&self, _msg: &Message) -> Vec<PollOption> {
+        default_options()
+    }
+}
+
+
+// 3rd party bot that lists the voters' names in the embed fields
+// the modules for every bot only provide the name-splitting function
+#[cfg(feature = "third_party_bots")]
+pub struct EmbedBotSource {
+    pub bot: BotConfig,
+}
+
+#[cfg(feature = "third_party_bots")]
+#[async_trait]
+impl PollSource for EmbedBotSource {
+    fn name(&self) -> &str {
+        &self.bot.name
+    }
+
+    fn recognises(&self, msg: &Message, _own_id: UserId) -> bool {
+        msg.author.id.get() == self.bot.id && !msg.embeds.is_empty()
+    }
+
+    async fn extract(&self, _gw: &dyn DiscordGateway, msg: &Message, loc: &str) -> Result<(PollVoters, String), String> {
+        match msg.embeds.first() {
+            Some(e) => Ok((PollVoters::Names(crate::tpbot_utils::parse_tp_bot_embed(&self.bot, e, loc)?), String::new())),
+            None => Err(tr!(loc, "parse.no_embeds", bot = self.bot.name)),
+        }
+    }
+
+    fn options(&self, _msg: &Message) -> Vec<PollOption> {
+        self.bot.options()
+    }
+}
+
+
+// name-splitting functions of the 3rd party bot modules, by the format name used in the config
+#[cfg(feature = "third_party_bots")]
+pub const BOT_FORMATS: &[(&str, SplitNamesFn)] = &[
+    (apollo::FORMAT, apollo::split_names),
+    (pancake::FORMAT, pancake::split_names),
+    (carl::FORMAT, carl::split_names),
+    (juniper::FORMAT, juniper::split_names),
+    (sesh::FORMAT, sesh::split_names),
+    (raid_helper::FORMAT, raid_helper::split_names),
+];
+#[cfg(not(feature = "third_party_bots"))]
+pub const BOT_FORMATS: &[(&str, SplitNamesFn)] = &[];
+
+// configs of the bots supported out of the box
+#[cfg(feature = "third_party_bots")]
+pub fn default_bot_configs() -> Vec<BotConfig>
+{
+    vec![
+        apollo::default_config(),
+        pancake::default_config(),
+        carl::default_config(),
+        juniper::default_config(),
+        sesh::default_config(),
+        raid_helper::default_config(),
+    ]
+}
+#[cfg(not(feature = "third_party_bots"))]
+pub fn default_bot_configs() -> Vec<crate::config::BotConfig>
+{
+    Vec::new()
+}
+
+// returns the name-splitting function for the format
+#[cfg(feature = "third_party_bots")]
+pub fn split_names_fn(format: &str) -> Option<SplitNamesFn>
+{
+    BOT_FORMATS.iter().find(|(f, _)| *f == format).map(|(_, split)| *split)
+}
+
+
+#[cfg(feature = "third_party_bots")]
+fn third_party_sources() -> Vec<Box<dyn PollSource>>
+{
+    config::get().bots.iter()
+        .map(|bot| Box::new(EmbedBotSource { bot: bot.clone() }) as Box<dyn PollSource>)
+        .collect()
+}
+#[cfg(not(feature = "third_party_bots"))]
+fn third_party_sources() -> Vec<Box<dyn PollSource>>
+{
+    Vec::new()
+}
+
+fn build_registry() -> Vec<Box<dyn PollSource>>
+{
+    let mut sources: Vec<Box<dyn PollSource>> = vec![
+        Box::new(native::NativePoll),
+        Box::new(own::OwnPoll),
+    ];
+    sources.extend(third_party_sources());
+    sources
+}
+
+// all the poll sources, in the order they are tried
+pub fn registry() -> &'static [Box<dyn PollSource>]
+{
+    REGISTRY.get_or_init(build_registry)
+}
+
+// returns the source that recognises the message as its poll
+pub fn find_source(msg: &Message, own_id: UserId) -> Option<&'static dyn PollSource>
+{
+    registry().iter()
+        .find(|s| s.recognises(msg, own_id))
+        .map(|s| s.as_ref())
+}
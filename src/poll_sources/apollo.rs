@@ -0,0 +1,32 @@
+//Apollo polls, the voters' names are listed in the embed fields
+
+use crate::config::BotConfig;
+
+// the value of "format" in the bot's config
+pub const FORMAT: &str = "apollo";
+
+pub fn default_config() -> BotConfig {
+    BotConfig {
+        name: "Apollo".to_string(),
+        id: 475744554910351370,
+        format: FORMAT.to_string(),
+        emojis: vec!["<:accepted:713124484436983971>".to_string(), "<:declined:713124484688642068>".to_string(), "<:tentative:713214962641666109>".to_string()],
+        unmatched_fields_option: None,
+        labels: Vec::new(),
+        kinds: Vec::new(),
+    }
+}
+
+
+// ">>> " followed by one name per line
+pub fn split_names(s: &str) -> Option<Vec<String>>{
+    if let Some(s) = s.strip_prefix(">>> "){
+        let mut v: Vec<String>= Vec::new();
+        let s = s.replace("\\\\", "\\"); //the string received from the JSON embed appears to be double-serialized for some reason
+        for l in s.lines() {
+            v.push(l.to_string());
+        }
+        if !v.is_empty() {return Some(v)};
+    }
+    None
+}
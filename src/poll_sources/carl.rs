@@ -0,0 +1,31 @@
+//Carl-bot polls, the voters' names are listed in the embed fields
+
+use crate::config::BotConfig;
+
+// the value of "format" in the bot's config
+pub const FORMAT: &str = "carl";
+
+pub fn default_config() -> BotConfig {
+    BotConfig {
+        name: "Carl-bot".to_string(),
+        id: 235148962103951360,
+        format: FORMAT.to_string(),
+        emojis: vec!["✅".to_string(), "❌".to_string(), "❔".to_string()],
+        unmatched_fields_option: None,
+        labels: Vec::new(),
+        kinds: Vec::new(),
+    }
+}
+
+
+// one name per line, nothing else
+pub fn split_names(s: &str) -> Option<Vec<String>>{
+    let s = s.replace("\\\\", "\\");
+    let v: Vec<String> = s.lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| l.to_string())
+        .collect();
+    if !v.is_empty() {return Some(v)};
+    None
+}
@@ -0,0 +1,33 @@
+//Juniper polls, the voters' names are listed in the embed fields
+
+use crate::config::BotConfig;
+
+// the value of "format" in the bot's config
+pub const FORMAT: &str = "juniper";
+
+pub fn default_config() -> BotConfig {
+    BotConfig {
+        name: "Juniper".to_string(),
+        id: 310848622642069504,
+        format: FORMAT.to_string(),
+        emojis: vec!["👍".to_string(), "👎".to_string(), "🤷".to_string()],
+        unmatched_fields_option: None,
+        labels: Vec::new(),
+        kinds: Vec::new(),
+    }
+}
+
+
+// one "• name" (or "- name") per line
+pub fn split_names(s: &str) -> Option<Vec<String>>{
+    let s = s.replace("\\\\", "\\");
+    let mut v: Vec<String>= Vec::new();
+    for l in s.lines() {
+        let l = l.trim();
+        if let Some(n) = l.strip_prefix("• ").or(l.strip_prefix("- ")) {
+            v.push(n.trim().to_string());
+        }
+    }
+    if !v.is_empty() {return Some(v)};
+    None
+}
@@ -0,0 +1,150 @@
+//Discord's own (native) polls, the voters are fetched for every answer
+
+use serenity::all::{Message, Poll, PollAnswer, PollMediaEmoji, UserId};
+use serenity::async_trait;
+
+use crate::config;
+use crate::gateway::DiscordGateway;
+use crate::i18n::tr;
+use crate::poll_sources::{OptionKind, PollOption, PollSource, PollVoters};
+
+
+pub struct NativePoll;
+
+#[async_trait]
+impl PollSource for NativePoll {
+    fn name(&self) -> &str {
+        "Discord poll"
+    }
+
+    fn recognises(&self, msg: &Message, _own_id: UserId) -> bool {
+        is_native_poll(msg)
+    }
+
+    async fn extract(&self, gw: &dyn DiscordGateway, msg: &Message, loc: &str) -> Result<(PollVoters, String), String> {
+        match get_native_poll_votes(gw, msg).await {
+            Ok(voters) => Ok((PollVoters::Ids(voters), String::new())),
+            Err(e) => Err(tr!(loc, "parse.voters_failed", error = e)),
+        }
+    }
+
+    fn options(&self, msg: &Message) -> Vec<PollOption> {
+        let Some(poll) = &msg.poll else {return Vec::new();};
+        poll.answers.iter().zip(map_answers(poll))
+            .map(|(a, kind)| PollOption { label: answer_label(a), kind })
+            .collect()
+    }
+}
+
+
+// returns true if the message carries a native poll and native polls are enabled
+pub fn is_native_poll(msg: &Message) -> bool
+{
+    config::get().native_polls.enabled && msg.poll.is_some()
+}
+
+
+fn answer_label(a: &PollAnswer) -> String
+{
+    if let Some(t) = &a.poll_media.text {
+        return t.clone();
+    }
+    match &a.poll_media.emoji {
+        Some(PollMediaEmoji::Name(n)) => n.clone(),
+        Some(PollMediaEmoji::Id(id)) => id.to_string(),
+        None => String::new(),
+    }
+}
+
+
+// Maps every answer of the poll to "accepted", "declined", "tentative" or "other"
+// Answers are matched by text or emoji against native_polls config first, the rest get the kinds nothing matched,
+// in the "accepted", "declined", "tentative" order, if allowed (e.g. "Can't", "Yes" are "declined", "accepted")
+pub fn map_answers(poll: &Poll) -> Vec<OptionKind>
+{
+    let np = &config::get().native_polls;
+    let lists = [&np.accepted, &np.declined, &np.tentative];
+    let matches = |s: &str, i: usize| lists[i].iter().any(|l| l.to_lowercase() == s.trim().to_lowercase());
+
+    let mut result: Vec<Option<OptionKind>> = Vec::new();
+    for a in &poll.answers {
+        let mut mapped = None;
+        for i in 0..3 {
+            let by_text = a.poll_media.text.as_deref().is_some_and(|t| matches(t, i));
+            let by_emoji = match &a.poll_media.emoji {
+                Some(PollMediaEmoji::Name(n)) => matches(n, i),
+                _ => false,
+            };
+            if by_text || by_emoji {
+                mapped = Some(OptionKind::by_index(i));
+                break;
+            }
+        }
+        result.push(mapped);
+    }
+
+    let free: Vec<OptionKind> = (0..3).map(OptionKind::by_index).filter(|k| !result.contains(&Some(*k))).collect();
+    let mut free = free.into_iter();
+    result.into_iter()
+        .map(|m| match m {
+            Some(k) => k,
+            None if np.map_by_index => free.next().unwrap_or(OptionKind::Other),
+            None => OptionKind::Other,
+        })
+        .collect()
+}
+
+
+// Fetches the voters for every answer of the native poll
+// Returns the voters' IDs, one list per answer
+pub async fn get_native_poll_votes(gw: &dyn DiscordGateway, msg: &Message) -> Result<Vec<Vec<UserId>>, serenity::Error>
+{
+    let Some(poll) = &msg.poll else {
+        return Ok(Vec::new());
+    };
+
+    let mut result: Vec<Vec<UserId>> = Vec::new();
+    for a in &poll.answers {
+        let mut voters: Vec<UserId> = Vec::new();
+        for u in gw.poll_answer_voters(msg, a.answer_id).await? {
+            if !u.bot && !voters.contains(&u.id) {
+                voters.push(u.id);
+            }
+        }
+        result.push(voters);
+    }
+    Ok(result)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll(answers: &[&str]) -> Poll {
+        let answers: Vec<serde_json::Value> = answers.iter().enumerate()
+            .map(|(i, t)| serde_json::json!({"answer_id": i + 1, "poll_media": {"text": t}}))
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "question": {"text": "Raid?"},
+            "answers": answers,
+            "expiry": null,
+            "allow_multiselect": false,
+            "layout_type": 1,
+            "results": null,
+        })).unwrap()
+    }
+
+    #[test]
+    fn answers_are_matched_by_text() {
+        assert_eq!(map_answers(&poll(&["Maybe", "No", "YES"])),
+            vec![OptionKind::Tentative, OptionKind::Declined, OptionKind::Accepted]);
+    }
+
+    #[test]
+    fn unmatched_answers_get_the_kinds_left() {
+        assert_eq!(map_answers(&poll(&["Can't", "Yes"])), vec![OptionKind::Declined, OptionKind::Accepted]);
+        assert_eq!(map_answers(&poll(&["Soon", "No", "Later", "Never"])),
+            vec![OptionKind::Accepted, OptionKind::Declined, OptionKind::Tentative, OptionKind::Other]);
+    }
+}
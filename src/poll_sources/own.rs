@@ -0,0 +1,150 @@
+//Own polls, the voters are read from the reactions (or from the store)
+
+use serenity::all::{Message, ReactionType, UserId};
+use serenity::async_trait;
+use serenity::futures::future;
+
+use crate::config::{self, OwnPollOption};
+use crate::gateway::DiscordGateway;
+use crate::i18n::{self, tr};
+use crate::poll_sources::{OptionKind, PollOption, PollSource, PollVoters};
+use crate::utils::stored_poll_from_message;
+
+
+pub struct OwnPoll;
+
+
+#[async_trait]
+impl PollSource for OwnPoll {
+    fn name(&self) -> &str {
+        "pollbot"
+    }
+
+    fn recognises(&self, msg: &Message, own_id: UserId) -> bool {
+        is_own_poll(msg, &own_id)
+    }
+
+    async fn extract(&self, gw: &dyn DiscordGateway, msg: &Message, loc: &str) -> Result<(PollVoters, String), String> {
+        match get_own_poll_votes(gw, msg).await {
+            Ok(voters) => Ok((PollVoters::Ids(voters), String::new())),
+            Err(e) => Err(tr!(loc, "parse.reactions_failed", error = e)),
+        }
+    }
+
+    fn options(&self, msg: &Message) -> Vec<PollOption> {
+        own_poll_options(msg).iter().map(|o| o.poll_option()).collect()
+    }
+}
+
+
+// options of the poll, read from the section headers of the poll message ("✅** __Accepted__ (14):**")
+// the kind of an option is taken from the configured option with the same emoji, the rest count as accepted
+// falls back to the configured options if there are no headers (e.g. the poll is being created)
+pub fn own_poll_options(msg: &Message) -> Vec<OwnPollOption>
+{
+    let configured = config::get().own_poll_options();
+    let mut options: Vec<OwnPollOption> = Vec::new();
+    for line in msg.content.lines() {
+        let Some(line) = line.strip_suffix(":**") else {continue;};
+        let Some((emoji, rest)) = line.split_once("** __") else {continue;};
+        let Some(end) = rest.rfind("__") else {continue;};
+        if emoji.is_empty() || options.iter().any(|o| o.emoji == emoji) {continue;}
+        let kind = match configured.iter().find(|o| o.emoji == emoji) {
+            Some(o) => o.kind,
+            None => OptionKind::Accepted,
+        };
+        options.push(OwnPollOption { emoji: emoji.to_string(), label: rest[..end].to_string(), kind });
+    }
+    if options.is_empty() {
+        return configured;
+    }
+    options
+}
+
+
+// the reaction for the option's emoji (custom emojis are written as <:name:id>)
+pub fn reaction_of(emoji: &str) -> ReactionType
+{
+    ReactionType::try_from(emoji).unwrap_or_else(|_| ReactionType::Unicode(emoji.to_string()))
+}
+
+
+// returns true if the message looks like own poll (we left the initial reactions under it or it has our vote buttons)
+pub fn is_own_poll(msg: &Message, own_id: &UserId) -> bool
+{
+    if msg.author.id != *own_id {return false;}
+    if starts_with_closed_mark(msg) {return true;}     //the buttons are gone, the reactions might be too
+    #[cfg(feature = "poll_creation")]
+    if crate::poll_buttons::is_button_poll(msg) {return true;}
+    let Some(first) = own_poll_options(msg).into_iter().next() else {return false;};
+    let first = reaction_of(&first.emoji);
+    for r in &msg.reactions {
+        if r.me && r.reaction_type == first {return true;}
+    }
+    false
+}
+
+
+// closed polls keep their final votes, the message says so too (in case the store is not available)
+#[cfg(feature = "poll_creation")]
+pub async fn is_closed(gw: &dyn DiscordGateway, msg: &Message) -> bool
+{
+    if starts_with_closed_mark(msg) {return true;}
+    match gw.poll_store().await.map(|store| store.closed_at(msg.id)) {
+        Some(Ok(closed_at)) => closed_at.is_some(),
+        Some(Err(e)) => {
+            println!("is_closed: store error {e}");
+            false
+        },
+        None => false,
+    }
+}
+
+
+// the first line of a closed poll ("poll.closed"), in whatever locale it was closed in
+fn starts_with_closed_mark(msg: &Message) -> bool
+{
+    i18n::all_texts("poll.closed").iter().any(|mark| msg.content.starts_with(mark.as_str()))
+}
+
+
+// Current votes for own poll: from the store if it has them, otherwise from the reactions (the store gets a snapshot then)
+pub async fn get_own_poll_votes(gw: &dyn DiscordGateway, msg: &Message) -> Result<Vec<Vec<UserId>>, serenity::Error>
+{
+    let options = own_poll_options(msg);
+    let store = gw.poll_store().await;
+    if let Some(store) = &store {
+        match store.votes(msg.id, options.len()) {
+            Ok(Some(votes)) => return Ok(votes),
+            Ok(None) => {},
+            Err(e) => println!("get_own_poll_votes: store error {e}"),
+        }
+    }
+
+    // the votes of a button poll are only in the store
+    #[cfg(feature = "poll_creation")]
+    if crate::poll_buttons::is_button_poll(msg) {
+        println!("get_own_poll_votes: no votes in the store for button poll {}", msg.id);
+        return Ok(vec![Vec::new(); options.len()]);
+    }
+
+    let own_id = gw.current_user_id();
+    let reacted = future::join_all(options.iter()
+        .map(|o| gw.reaction_users(msg, reaction_of(&o.emoji)))).await;
+    let mut votes: Vec<Vec<UserId>> = Vec::new();
+    for r in reacted {
+        votes.push(r?.into_iter()
+            .filter(|u| u.id != own_id && !u.bot)
+            .map(|u| u.id)
+            .collect());
+    }
+    if let Some(store) = &store {
+        if let Some(g_id) = msg.guild_id {
+            if let Err(e) = store.record_poll(&stored_poll_from_message(msg, g_id, &OwnPoll))
+                .and_then(|_| store.replace_votes(msg.id, &votes)) {
+                println!("get_own_poll_votes: store error {e}");
+            }
+        }
+    }
+    Ok(votes)
+}
@@ -0,0 +1,32 @@
+//Pancake polls, the voters' names are listed in the embed fields
+
+use crate::config::BotConfig;
+
+// the value of "format" in the bot's config
+pub const FORMAT: &str = "pancake";
+
+pub fn default_config() -> BotConfig {
+    BotConfig {
+        name: "Pancake".to_string(),
+        id: 627525335423909909,
+        format: FORMAT.to_string(),
+        emojis: vec!["✅".to_string(), "❌".to_string(), "❔".to_string()], //pancake's emojis look default enough
+        unmatched_fields_option: None,
+        labels: Vec::new(),
+        kinds: Vec::new(),
+    }
+}
+
+
+// one "> name" per line
+pub fn split_names(s: &str) -> Option<Vec<String>>{
+    let mut v: Vec<String>= Vec::new();
+    let s = s.replace("\\\\", "\\"); //the string received from the JSON embed appears to be double-serialized for some reason
+    for l in s.lines() {
+        if let Some(l) = l.strip_prefix("> "){
+            v.push(l.to_string());
+        }
+    }
+    if !v.is_empty() {return Some(v)};
+    None
+}
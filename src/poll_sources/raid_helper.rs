@@ -0,0 +1,46 @@
+//Raid-Helper polls, the voters' names are listed in the embed fields
+
+use crate::config::BotConfig;
+
+// the value of "format" in the bot's config
+pub const FORMAT: &str = "raid_helper";
+
+pub fn default_config() -> BotConfig {
+    BotConfig {
+        name: "Raid-Helper".to_string(),
+        id: 579155972115660803,
+        format: FORMAT.to_string(),
+        emojis: vec!["<:Accepted:".to_string(), "<:Absence:".to_string(), "<:Tentative:".to_string()],
+        // the sign-ups are listed in one field per class, all of them count as accepted
+        unmatched_fields_option: Some(0),
+        labels: Vec::new(),
+        kinds: Vec::new(),
+    }
+}
+
+
+// "<:Class:123> `12` **name**" per line, the emojis and the sign-up number are optional
+pub fn split_names(s: &str) -> Option<Vec<String>>{
+    let s = s.replace("\\\\", "\\");
+    let mut v: Vec<String>= Vec::new();
+    for l in s.lines() {
+        // skipping custom emojis
+        let mut l = l.trim();
+        while l.starts_with("<:") || l.starts_with("<a:") {
+            match l.find('>') {
+                Some(end) => l = l[end + 1..].trim_start(),
+                None => break,
+            }
+        }
+        // the sign-up number, lines without it are not sign-ups
+        let Some(rest) = l.strip_prefix('`') else {continue;};
+        let Some(end) = rest.find('`') else {continue;};
+        if !rest[..end].chars().all(|c| c.is_ascii_digit()) {continue;}
+        let n = rest[end + 1..].trim().trim_start_matches("**").trim_end_matches("**").trim();
+        if !n.is_empty() {
+            v.push(n.to_string());
+        }
+    }
+    if !v.is_empty() {return Some(v)};
+    None
+}
@@ -0,0 +1,31 @@
+//Sesh polls, the voters' names are listed in the embed fields
+
+use crate::config::BotConfig;
+
+// the value of "format" in the bot's config
+pub const FORMAT: &str = "sesh";
+
+pub fn default_config() -> BotConfig {
+    BotConfig {
+        name: "Sesh".to_string(),
+        id: 616754792965865495,
+        format: FORMAT.to_string(),
+        emojis: vec!["✅".to_string(), "❌".to_string(), "🤔".to_string()],
+        unmatched_fields_option: None,
+        labels: Vec::new(),
+        kinds: Vec::new(),
+    }
+}
+
+
+// names or mentions separated by commas and/or new lines
+pub fn split_names(s: &str) -> Option<Vec<String>>{
+    let s = s.replace("\\\\", "\\");
+    let v: Vec<String> = s.split([',', '\n'])
+        .map(|n| n.trim())
+        .filter(|n| !n.is_empty())
+        .map(|n| n.to_string())
+        .collect();
+    if !v.is_empty() {return Some(v)};
+    None
+}
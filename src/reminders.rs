@@ -0,0 +1,178 @@
+//Reminders to the members who haven't voted yet, sent at the configured offsets before the poll's deadline
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serenity::all::{Context, CreateMessage, Timestamp, UserId};
+
+use crate::config::{self, ReminderMode};
+use crate::i18n::{self, tr};
+use crate::storage::{self, PollStore, StoredPoll};
+use crate::utils::{self, PollRef, LEN_LIMIT_UID_MENTION};
+
+// how often the deadlines are checked, in seconds
+const CHECK_INTERVAL: u64 = 60;
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+
+// starts checking the deadlines in the background, only the first call does anything (ready fires on every reconnect)
+pub fn start(ctx: Context) {
+    let cfg = &config::get().reminders;
+    if !cfg.enabled || cfg.offsets.is_empty() || STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(CHECK_INTERVAL));
+        loop {
+            interval.tick().await;
+            run_due(&ctx).await;
+        }
+    });
+}
+
+
+// the smallest offset that has been reached, None if it's too early for any
+// the bigger ones are not sent separately when several are reached at once (e.g. the deadline was set late)
+pub fn due_offset(offsets: &[i64], deadline: i64, now: i64) -> Option<i64> {
+    offsets.iter().filter(|o| deadline - **o <= now).min().copied()
+}
+
+async fn run_due(ctx: &Context) {
+    let Some(store) = storage::get(ctx).await else {return;};
+    let offsets = config::get().reminder_offsets();
+    let Some(horizon) = offsets.iter().max() else {return;};
+    let now = Timestamp::now().unix_timestamp();
+    let polls = match store.polls_with_deadline_between(now, now + horizon) {
+        Ok(p) => p,
+        Err(e) => {
+            println!("reminders: store error {e}");
+            return;
+        },
+    };
+    for (poll, deadline) in polls {
+        let Some(offset) = due_offset(&offsets, deadline, now) else {continue;};
+        match store.reminder_ran(poll.message_id, offset) {
+            Ok(false) => {},
+            Ok(true) => continue,
+            Err(e) => {
+                println!("reminders: store error {e}");
+                continue;
+            },
+        }
+        // the offset is marked as run only if the poll could be checked, it's tried again on the next tick otherwise
+        let reminded = match remind(ctx, &store, &poll, deadline, offset).await {
+            Ok(r) => r,
+            Err(e) => {
+                println!("reminders: {e}");
+                continue;
+            },
+        };
+        if let Err(e) = store.record_reminders(poll.message_id, offset, &reminded, now) {
+            println!("reminders: store error {e}");
+        }
+    }
+}
+
+// pings everyone who hasn't voted and wasn't reminded at this offset yet, returns who was reminded
+// or an error if the poll can't be checked
+async fn remind(ctx: &Context, store: &PollStore, poll: &StoredPoll, deadline: i64, offset: i64) -> Result<Vec<UserId>, String> {
+    let link = poll.message_id.link(poll.channel_id, Some(poll.guild_id));
+    let loc = i18n::guild_locale(ctx, poll.guild_id);
+    let results = utils::get_poll_results(ctx, poll.guild_id, poll.channel_id, PollRef::Given(&link), loc).await
+        .map_err(|e| format!("can't check {link}: {e}"))?;
+    let already = store.reminded(poll.message_id, offset).unwrap_or_default();
+    let targets: Vec<UserId> = results.did_not_vote(&results.channel_members).into_iter()
+        .filter(|u| !already.contains(u))
+        .collect();
+    if targets.is_empty() {
+        return Ok(targets);
+    }
+
+    let text = tr!(loc, "reminders.text", link = link, deadline = deadline);
+    let reminded = match config::get().reminders.mode {
+        ReminderMode::Channel => send_to_channel(ctx, poll, &text, &targets).await,
+        ReminderMode::DM => send_dms(ctx, &text, &targets).await,
+    };
+    let log_message = tr!(loc, "log.reminded", n = reminded.len(), of = targets.len(), offset = format_offset(offset));
+    if let Err(e) = utils::log_to_thread(ctx, &log_message, &poll.guild_id, &poll.channel_id, &poll.message_id.to_string()).await {
+        println!("{e}");
+    }
+    Ok(reminded)
+}
+
+// one or more messages in the poll's channel, as many mentions as fit in each
+async fn send_to_channel(ctx: &Context, poll: &StoredPoll, text: &str, targets: &[UserId]) -> Vec<UserId> {
+    let per_message = config::get().limits.message_length.saturating_sub(text.len() + 1) / LEN_LIMIT_UID_MENTION;
+    let mut reminded: Vec<UserId> = Vec::new();
+    for chunk in targets.chunks(per_message.max(1)) {
+        let mentions: String = chunk.iter().map(|u| format!("<@{u}>")).collect();
+        let builder = CreateMessage::new()
+            .content(format!("{text}\n{mentions}"))
+            .reference_message((poll.channel_id, poll.message_id));
+        match poll.channel_id.send_message(&ctx.http, builder).await {
+            Ok(_) => reminded.extend_from_slice(chunk),
+            Err(e) => {
+                println!("reminders: can't post in {}: {e}", poll.channel_id);
+                break;
+            },
+        }
+    }
+    reminded
+}
+
+// members with closed DMs are skipped
+async fn send_dms(ctx: &Context, text: &str, targets: &[UserId]) -> Vec<UserId> {
+    let mut reminded: Vec<UserId> = Vec::new();
+    for u in targets {
+        match u.direct_message(&ctx, CreateMessage::new().content(text)).await {
+            Ok(_) => reminded.push(*u),
+            Err(e) => println!("reminders: can't DM {u}: {e}"),
+        }
+    }
+    reminded
+}
+
+
+// 86400 -> "1d", 7200 -> "2h", 5400 -> "90m"
+pub fn format_offset(secs: i64) -> String {
+    match secs {
+        s if s % 86400 == 0 => format!("{}d", s / 86400),
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s => format!("{}m", s / 60),
+    }
+}
+
+// "Members who haven't voted will be reminded 24h, 2h before it." for replies about the deadline
+pub fn describe_offsets(loc: &str) -> String {
+    let cfg = &config::get().reminders;
+    if !cfg.enabled || cfg.offsets.is_empty() {
+        return tr!(loc, "reminders.off");
+    }
+    tr!(loc, "reminders.on", offsets = cfg.offsets.join(", "))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smallest_reached_offset_is_due() {
+        let offsets = [86400, 7200];
+        let deadline = 100_000;
+        assert_eq!(due_offset(&offsets, deadline, deadline - 86401), None);
+        assert_eq!(due_offset(&offsets, deadline, deadline - 86400), Some(86400));
+        assert_eq!(due_offset(&offsets, deadline, deadline - 7201), Some(86400));
+        assert_eq!(due_offset(&offsets, deadline, deadline - 7200), Some(7200));
+        // the deadline was set an hour before it, only the last reminder goes out
+        assert_eq!(due_offset(&offsets, deadline, deadline - 3600), Some(7200));
+    }
+
+    #[test]
+    fn offsets_are_formatted_back() {
+        assert_eq!(format_offset(86400), "1d");
+        assert_eq!(format_offset(7200), "2h");
+        assert_eq!(format_offset(5400), "90m");
+    }
+}
@@ -0,0 +1,343 @@
+//Recurring polls, the schedules are kept in the poll store and posted through create_new_poll
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use serenity::all::{Context, User};
+
+use crate::commands::new_poll::{self, NewPoll};
+use crate::config;
+use crate::i18n::{self, tr};
+use crate::storage::{self, StoredSchedule};
+
+// how often the due schedules are checked, in seconds
+const CHECK_INTERVAL: u64 = 30;
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+
+// when a schedule fires, local time of its time zone
+// None in a day field means "any", like * in cron
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    minutes: Vec<u32>,
+    hours: Vec<u32>,
+    days: Option<Vec<u32>>,
+    months: Option<Vec<u32>>,
+    weekdays: Option<Vec<u32>>,     //0 - Sunday
+}
+
+impl Schedule {
+    // "mon,thu 18:00", "mon-fri 9:30", "daily 20:00" or cron "minute hour day month weekday" ("0 18 * * 1,4")
+    pub fn parse(spec: &str, loc: &str) -> Result<Schedule, String> {
+        let fields: Vec<&str> = spec.split_whitespace().collect();
+        if fields.len() == 5 && !fields[4].contains(':') {
+            return Ok(Schedule {
+                minutes: parse_field(fields[0], 0, 59, &[], 0, loc)?.unwrap_or((0..60).collect()),
+                hours: parse_field(fields[1], 0, 23, &[], 0, loc)?.unwrap_or((0..24).collect()),
+                days: parse_field(fields[2], 1, 31, &[], 1, loc)?,
+                months: parse_field(fields[3], 1, 12, &MONTHS, 1, loc)?,
+                weekdays: parse_field(fields[4], 0, 7, &WEEKDAYS, 0, loc)?
+                    .map(|w| {
+                        let mut w: Vec<u32> = w.into_iter().map(|d| d % 7).collect();  //7 is Sunday too
+                        w.sort();
+                        w.dedup();
+                        w
+                    }),
+            });
+        }
+
+        let Some((time, days)) = fields.split_last() else {
+            return Err(tr!(loc, "schedule.empty"));
+        };
+        let (hour, minute) = time.split_once(':')
+            .and_then(|(h, m)| Some((h.parse::<u32>().ok()?, m.parse::<u32>().ok()?)))
+            .filter(|(h, m)| *h < 24 && *m < 60)
+            .ok_or(tr!(loc, "schedule.bad_time", time = time))?;
+        let weekdays = match days.join("").to_lowercase().as_str() {
+            "" | "daily" | "everyday" => None,
+            d => parse_field(d, 0, 6, &WEEKDAYS, 0, loc)?,
+        };
+        Ok(Schedule { minutes: vec![minute], hours: vec![hour], days: None, months: None, weekdays })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if self.months.as_ref().is_some_and(|m| !m.contains(&date.month())) {
+            return false;
+        }
+        let day = self.days.as_ref().map(|d| d.contains(&date.day()));
+        let weekday = self.weekdays.as_ref().map(|w| w.contains(&date.weekday().num_days_from_sunday()));
+        // like in cron, a day matches if either of them matches when both are given
+        match (day, weekday) {
+            (Some(d), Some(w)) => d || w,
+            (Some(d), None) => d,
+            (None, Some(w)) => w,
+            (None, None) => true,
+        }
+    }
+
+    // the first time after the given one, None if there is no such time in the next few years (e.g. "0 0 31 2 *")
+    // local times skipped by a DST change are skipped by the schedule too
+    pub fn next_after(&self, after: DateTime<Utc>, tz: Tz) -> Option<DateTime<Utc>> {
+        let mut date = after.with_timezone(&tz).date_naive();
+        for _ in 0..(366 * 5) {
+            if self.matches_day(date) {
+                for h in &self.hours {
+                    for m in &self.minutes {
+                        let Some(local) = date.and_hms_opt(*h, *m, 0) else {continue;};
+                        match tz.from_local_datetime(&local).earliest() {
+                            Some(t) if t.with_timezone(&Utc) > after => return Some(t.with_timezone(&Utc)),
+                            _ => {},
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+}
+
+
+// "*", "5", "1-5", "*/15", "mon,wed-fri", values are checked against min..=max
+// names are matched by their first 3 letters, names[0] has the value offset
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], offset: u32, loc: &str) -> Result<Option<Vec<u32>>, String> {
+    if field == "*" {
+        return Ok(None);
+    }
+    let value = |s: &str| -> Result<u32, String> {
+        let lower = s.to_lowercase();
+        if lower.chars().all(char::is_alphabetic) {
+            if let Some(i) = names.iter().position(|n| lower.starts_with(n)) {
+                return Ok(i as u32 + offset);
+            }
+        }
+        match s.parse::<u32>() {
+            Ok(v) if v >= min && v <= max => Ok(v),
+            _ => Err(tr!(loc, "schedule.bad_value", value = s, min = min, max = max)),
+        }
+    };
+    let mut values: Vec<u32> = Vec::new();
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((r, s)) => (r, s.parse::<u32>().ok().filter(|s| *s > 0).ok_or(tr!(loc, "schedule.bad_step", part = part))?),
+            None => (part, 1),
+        };
+        let (from, to) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((a, b)) => (value(a)?, value(b)?),
+            None if step > 1 => (value(range)?, max),
+            None => {let v = value(range)?; (v, v)},
+        };
+        if from > to {
+            return Err(tr!(loc, "schedule.bad_range", range = range));
+        }
+        values.extend((from..=to).step_by(step as usize));
+    }
+    values.sort();
+    values.dedup();
+    Ok(Some(values))
+}
+
+
+pub fn parse_timezone(tz: &str, loc: &str) -> Result<Tz, String> {
+    tz.trim().parse::<Tz>().map_err(|_| tr!(loc, "schedule.unknown_timezone", timezone = tz))
+}
+
+// the time zone of schedules added without one
+pub fn default_timezone() -> String {
+    config::get().scheduler.timezone.clone()
+}
+
+// "{date}" becomes 2025-06-05, "{date:%d.%m}" uses the given strftime format, "{weekday}" becomes Thursday
+pub fn render_title(template: &str, date: NaiveDate, loc: &str) -> Result<String, String> {
+    let mut title = template.replace("{date}", &date.format("%Y-%m-%d").to_string())
+        .replace("{weekday}", &date.format("%A").to_string());
+    let mut from = 0;
+    while let Some(start) = title[from..].find("{date:").map(|i| from + i) {
+        let Some(len) = title[start..].find('}') else {
+            return Err(tr!(loc, "schedule.unclosed_date", start = "{date:", end = "}"));
+        };
+        let format = &title[start + 6..start + len];
+        if StrftimeItems::new(format).any(|i| i == Item::Error) {
+            return Err(tr!(loc, "schedule.bad_date_format", format = format));
+        }
+        let formatted = date.format(format).to_string();
+        title.replace_range(start..=start + len, &formatted);
+        from = start + formatted.len();
+    }
+    Ok(title)
+}
+
+// checks everything a schedule is made of, returns its first run
+pub fn validate(s: &StoredSchedule, now: DateTime<Utc>, loc: &str) -> Result<Option<DateTime<Utc>>, String> {
+    let schedule = Schedule::parse(&s.spec, loc)?;
+    let tz = parse_timezone(&s.timezone, loc)?;
+    render_title(&s.title, now.date_naive(), loc)?;
+    if let Some(o) = &s.options {
+        new_poll::parse_options(o, loc)?;
+    }
+    Ok(schedule.next_after(now, tz))
+}
+
+// the run after the given time, None if the schedule is broken or never fires again
+pub fn next_run(s: &StoredSchedule, after: DateTime<Utc>) -> Option<i64> {
+    let schedule = Schedule::parse(&s.spec, i18n::DEFAULT_LOCALE).ok()?;
+    let tz = parse_timezone(&s.timezone, i18n::DEFAULT_LOCALE).ok()?;
+    schedule.next_after(after, tz).map(|t| t.timestamp())
+}
+
+
+// starts checking the schedules in the background, only the first call does anything (ready fires on every reconnect)
+pub fn start(ctx: Context) {
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(CHECK_INTERVAL));
+        loop {
+            interval.tick().await;
+            run_due(&ctx).await;
+        }
+    });
+}
+
+async fn run_due(ctx: &Context) {
+    let Some(store) = storage::get(ctx).await else {return;};
+    let now = Utc::now();
+    let due = match store.due_schedules(now.timestamp()) {
+        Ok(d) => d,
+        Err(e) => {
+            println!("scheduler: store error {e}");
+            return;
+        },
+    };
+    for s in due {
+        // moving on first, a failed post is not retried every CHECK_INTERVAL
+        if let Err(e) = store.set_schedule_run(s.id, next_run(&s, now), now.timestamp()) {
+            println!("scheduler: store error {e}");
+            continue;
+        }
+        let planned = s.next_run.unwrap_or(now.timestamp());
+        let late_by = now.timestamp() - planned;
+        if late_by > config::get().scheduler.max_delay_minutes as i64 * 60 {
+            println!("scheduler: skipped schedule #{} planned at {planned}, {late_by}s late (was the bot offline?)", s.id);
+            continue;
+        }
+        match post(ctx, &s, planned).await {
+            Ok(r) => println!("scheduler: schedule #{}: {r}", s.id),
+            Err(e) => println!("scheduler: schedule #{} failed: {e}", s.id),
+        }
+    }
+}
+
+async fn post(ctx: &Context, s: &StoredSchedule, planned: i64) -> Result<String, String> {
+    let loc = i18n::guild_locale(ctx, s.guild_id);
+    let tz = parse_timezone(&s.timezone, loc)?;
+    let date = DateTime::from_timestamp(planned, 0).unwrap_or(Utc::now()).with_timezone(&tz).date_naive();
+    let options = match &s.options {
+        Some(o) => new_poll::parse_options(o, loc)?,
+        None => config::get().own_poll_options(),
+    };
+    let poll = NewPoll {
+        title: render_title(&s.title, date, loc)?,
+        description: s.description.clone(),
+        event_at: None,
+        deadline: None,
+        options,
+    };
+    let me = User::from(ctx.cache.current_user().clone());
+    crate::create_new_poll(ctx, s.channel_id, &s.guild_id, &me, &poll, loc).await.map_err(|e| e.to_string())
+}
+
+
+// "#3 in #raids: thu 18:00 (Europe/Berlin) "Raid {date}", next <t:..:F>"
+pub fn describe(s: &StoredSchedule, loc: &str) -> String {
+    let state = match (s.paused, s.next_run) {
+        (true, _) => tr!(loc, "schedule.paused"),
+        (false, Some(t)) => tr!(loc, "schedule.next", time = format!("<t:{t}:F>")),
+        (false, None) => tr!(loc, "schedule.never"),
+    };
+    format!("**#{}** in <#{}>: `{}` ({}) \"{}\", {state}", s.id, s.channel_id, s.spec, s.timezone, s.title)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn weekday_form_matches_cron() {
+        assert_eq!(Schedule::parse("mon,thu 18:00", "en").unwrap(), Schedule::parse("0 18 * * 1,4", "en").unwrap());
+        assert_eq!(Schedule::parse("Mon-Fri 9:30", "en").unwrap(), Schedule::parse("30 9 * * mon-fri", "en").unwrap());
+        assert_eq!(Schedule::parse("daily 20:00", "en").unwrap(), Schedule::parse("0 20 * * *", "en").unwrap());
+        assert_eq!(Schedule::parse("0 20 * * 7", "en").unwrap(), Schedule::parse("sun 20:00", "en").unwrap());
+    }
+
+    #[test]
+    fn bad_specs() {
+        assert_eq!(Schedule::parse("", "en").unwrap_err(), tr!("en", "schedule.empty"));
+        assert_eq!(Schedule::parse("mon 25:00", "en").unwrap_err(), "Can't read the time \"25:00\", use HH:MM.");
+        assert_eq!(Schedule::parse("someday 18:00", "ru").unwrap_err(), "\"someday\" здесь не подходит (0-6).");
+        assert!(Schedule::parse("0 18 * * 8", "en").is_err());
+        assert_eq!(Schedule::parse("0 18 5-1 * *", "en").unwrap_err(), "Invalid range \"5-1\".");
+        assert_eq!(Schedule::parse("*/0 * * * *", "en").unwrap_err(), "Invalid step in \"*/0\".");
+        assert_eq!(parse_timezone("Mars/Base", "en").unwrap_err(),
+            "Unknown time zone \"Mars/Base\", use names like Europe/Berlin or UTC.");
+    }
+
+    #[test]
+    fn next_weekly_run() {
+        let s = Schedule::parse("mon,thu 18:00", "en").unwrap();
+        // 2025-06-05 is a Thursday
+        assert_eq!(s.next_after(utc("2025-06-05T12:00:00Z"), Tz::UTC), Some(utc("2025-06-05T18:00:00Z")));
+        assert_eq!(s.next_after(utc("2025-06-05T18:00:00Z"), Tz::UTC), Some(utc("2025-06-09T18:00:00Z")));
+    }
+
+    #[test]
+    fn next_run_follows_the_time_zone() {
+        let s = Schedule::parse("thu 18:00", "en").unwrap();
+        let tz = parse_timezone("Europe/Berlin", "en").unwrap();
+        // summer time, UTC+2
+        assert_eq!(s.next_after(utc("2025-06-01T00:00:00Z"), tz), Some(utc("2025-06-05T16:00:00Z")));
+        // winter time, UTC+1
+        assert_eq!(s.next_after(utc("2025-12-01T00:00:00Z"), tz), Some(utc("2025-12-04T17:00:00Z")));
+    }
+
+    #[test]
+    fn time_skipped_by_dst_is_skipped() {
+        // clocks in Berlin jump from 2:00 to 3:00 on 2025-03-30
+        let s = Schedule::parse("daily 2:30", "en").unwrap();
+        let tz = parse_timezone("Europe/Berlin", "en").unwrap();
+        assert_eq!(s.next_after(utc("2025-03-29T12:00:00Z"), tz), Some(utc("2025-03-31T00:30:00Z")));
+    }
+
+    #[test]
+    fn cron_steps_and_day_or_weekday() {
+        let s = Schedule::parse("*/20 9-10 * * *", "en").unwrap();
+        assert_eq!(s.next_after(utc("2025-06-05T09:45:00Z"), Tz::UTC), Some(utc("2025-06-05T10:00:00Z")));
+        // the 1st of the month or a Friday
+        let s = Schedule::parse("0 12 1 * fri", "en").unwrap();
+        assert_eq!(s.next_after(utc("2025-06-02T00:00:00Z"), Tz::UTC), Some(utc("2025-06-06T12:00:00Z")));
+        assert_eq!(s.next_after(utc("2025-06-28T00:00:00Z"), Tz::UTC), Some(utc("2025-07-01T12:00:00Z")));
+        assert_eq!(Schedule::parse("0 0 31 2 *", "en").unwrap().next_after(utc("2025-06-01T00:00:00Z"), Tz::UTC), None);
+    }
+
+    #[test]
+    fn title_template() {
+        let d = NaiveDate::from_ymd_opt(2025, 6, 5).unwrap();
+        assert_eq!(render_title("Raid {date}", d, "en").unwrap(), "Raid 2025-06-05");
+        assert_eq!(render_title("{weekday} {date:%d.%m}!", d, "en").unwrap(), "Thursday 05.06!");
+        assert_eq!(render_title("Raid {date:%Q}", d, "en").unwrap_err(), "Invalid date format \"%Q\".");
+        assert_eq!(render_title("Raid {date:%d", d, "en").unwrap_err(), "\"{date:\" is not closed with \"}\".");
+    }
+}
@@ -0,0 +1,135 @@
+//Splitting members into squads for /lineup: the role holders are spread first, then everyone else goes to the smallest squad
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use serenity::all::{ChannelId, UserId};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub user_id: UserId,
+    pub voice_channel: Option<ChannelId>,
+    pub has_role: bool,     // has the role every squad needs one of
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BalanceOptions {
+    pub squads: usize,
+    pub max_size: Option<usize>,
+    pub keep_voice_together: bool,  // members of one voice channel go to one squad (as long as it fits)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lineup {
+    pub squads: Vec<Vec<UserId>>,
+    pub bench: Vec<UserId>,         // didn't fit because of max_size
+    pub role_counts: Vec<usize>,    // role holders in every squad
+}
+
+
+pub fn balance(candidates: &[Candidate], opts: &BalanceOptions) -> Lineup {
+    let n = opts.squads.max(1);
+    let cap = opts.max_size.unwrap_or(usize::MAX).max(1);
+    let mut lineup = Lineup {
+        squads: vec![Vec::new(); n],
+        bench: Vec::new(),
+        role_counts: vec![0; n],
+    };
+
+    // the groups that should stay together, split if they can't fit in one squad anyway
+    let mut groups: Vec<Vec<&Candidate>> = Vec::new();
+    let mut by_channel: BTreeMap<ChannelId, Vec<&Candidate>> = BTreeMap::new();
+    for c in candidates {
+        match c.voice_channel {
+            Some(ch) if opts.keep_voice_together => by_channel.entry(ch).or_default().push(c),
+            _ => groups.push(vec![c]),
+        }
+    }
+    for (_, g) in by_channel {
+        groups.extend(g.chunks(cap).map(|chunk| chunk.to_vec()));
+    }
+    // the groups with role holders first, then the biggest first (the sort is stable, the order of equal ones stays)
+    groups.sort_by_key(|g| (Reverse(g.iter().filter(|c| c.has_role).count().min(1)), Reverse(g.len())));
+
+    for g in groups {
+        let roles = g.iter().filter(|c| c.has_role).count();
+        let fits = (0..n).filter(|i| lineup.squads[*i].len() + g.len() <= cap);
+        let target = if roles > 0 {
+            fits.min_by_key(|i| (lineup.role_counts[*i], lineup.squads[*i].len()))
+        } else {
+            fits.min_by_key(|i| lineup.squads[*i].len())
+        };
+        match target {
+            Some(i) => {
+                lineup.squads[i].extend(g.iter().map(|c| c.user_id));
+                lineup.role_counts[i] += roles;
+            },
+            // no room for the whole group, the members go one by one
+            None => for c in g {
+                match (0..n).filter(|i| lineup.squads[*i].len() < cap).min_by_key(|i| lineup.squads[*i].len()) {
+                    Some(i) => {
+                        lineup.squads[i].push(c.user_id);
+                        lineup.role_counts[i] += c.has_role as usize;
+                    },
+                    None => lineup.bench.push(c.user_id),
+                }
+            },
+        }
+    }
+    lineup
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: u64, voice: Option<u64>, has_role: bool) -> Candidate {
+        Candidate { user_id: UserId::new(id), voice_channel: voice.map(ChannelId::new), has_role }
+    }
+
+    fn sizes(l: &Lineup) -> Vec<usize> {
+        l.squads.iter().map(|s| s.len()).collect()
+    }
+
+    #[test]
+    fn squads_are_even() {
+        let candidates: Vec<Candidate> = (1..=10).map(|i| candidate(i, None, false)).collect();
+        let l = balance(&candidates, &BalanceOptions { squads: 3, ..Default::default() });
+        assert_eq!(sizes(&l), vec![4, 3, 3]);
+        assert!(l.bench.is_empty());
+    }
+
+    #[test]
+    fn role_holders_are_spread() {
+        let mut candidates: Vec<Candidate> = (1..=6).map(|i| candidate(i, None, false)).collect();
+        candidates.push(candidate(7, None, true));
+        candidates.push(candidate(8, None, true));
+        let l = balance(&candidates, &BalanceOptions { squads: 2, ..Default::default() });
+        assert_eq!(l.role_counts, vec![1, 1]);
+        assert_eq!(sizes(&l), vec![4, 4]);
+    }
+
+    #[test]
+    fn voice_channels_stay_together() {
+        let candidates = vec![
+            candidate(1, Some(100), false),
+            candidate(2, Some(100), false),
+            candidate(3, Some(100), false),
+            candidate(4, Some(200), false),
+            candidate(5, Some(200), false),
+            candidate(6, None, false),
+        ];
+        let l = balance(&candidates, &BalanceOptions { squads: 2, keep_voice_together: true, ..Default::default() });
+        assert_eq!(l.squads[0], vec![UserId::new(1), UserId::new(2), UserId::new(3)]);
+        assert_eq!(l.squads[1], vec![UserId::new(4), UserId::new(5), UserId::new(6)]);
+    }
+
+    #[test]
+    fn extra_members_go_to_the_bench() {
+        let candidates: Vec<Candidate> = (1..=7).map(|i| candidate(i, Some(100), false)).collect();
+        let l = balance(&candidates, &BalanceOptions { squads: 2, max_size: Some(3), keep_voice_together: true });
+        assert_eq!(sizes(&l), vec![3, 3]);
+        assert_eq!(l.bench, vec![UserId::new(7)]);
+    }
+}
@@ -0,0 +1,723 @@
+//Persistent poll store (SQLite), keeps polls and votes across restarts
+
+use std::sync::{Arc, Mutex};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serenity::all::{ChannelId, Context, GuildId, MessageId, UserId};
+use serenity::prelude::TypeMapKey;
+
+pub const DEFAULT_DB_PATH: &str = "pollbot.sqlite3";
+
+// a poll as it is kept in the store
+#[derive(Clone, Debug)]
+pub struct StoredPoll {
+    pub message_id: MessageId,
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub source_bot: UserId,
+    pub created_at: i64,        //unix timestamp
+    pub options: Vec<String>,
+}
+
+// how a member of the poll's channel voted and whether they showed up in voice at the event start
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttendanceRecord {
+    pub user_id: UserId,
+    pub kind: Option<String>,       //kind of the option they voted for ("accepted"...), None if they didn't vote
+    pub in_voice: bool,
+}
+
+// attendance of a member over some polls
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemberStats {
+    pub user_id: UserId,
+    pub polls: u32,         //polls they could vote in
+    pub accepted: u32,
+    pub no_shows: u32,      //accepted but not in voice
+    pub no_votes: u32,
+}
+
+// a recurring poll as it is kept in the store, see scheduler.rs
+#[cfg(feature = "scheduled_polls")]
+#[derive(Clone, Debug)]
+pub struct StoredSchedule {
+    pub id: i64,
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub created_by: UserId,
+    pub spec: String,               //"mon,thu 18:00" or "0 18 * * 1,4"
+    pub timezone: String,
+    pub title: String,              //template, "{date}" is replaced with the date of the run
+    pub description: Option<String>,
+    pub options: Option<String>,    //as typed in /new_poll, the configured options if None
+    pub paused: bool,
+    pub next_run: Option<i64>,      //unix timestamp, None if the schedule never fires again
+}
+
+pub struct PollStore {
+    conn: Mutex<Connection>,
+}
+
+impl TypeMapKey for PollStore {
+    type Value = Arc<PollStore>;
+}
+
+
+impl PollStore {
+    // opens (or creates) the database file and makes sure all the tables exist
+    pub fn open(path: &str) -> Result<PollStore, rusqlite::Error> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS polls (
+                message_id   INTEGER PRIMARY KEY,
+                guild_id     INTEGER NOT NULL,
+                channel_id   INTEGER NOT NULL,
+                source_bot   INTEGER NOT NULL,
+                created_at   INTEGER NOT NULL,
+                votes_synced INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE INDEX IF NOT EXISTS polls_channel ON polls (channel_id, created_at);
+            CREATE TABLE IF NOT EXISTS poll_options (
+                message_id   INTEGER NOT NULL,
+                idx          INTEGER NOT NULL,
+                label        TEXT NOT NULL,
+                PRIMARY KEY (message_id, idx)
+            );
+            CREATE TABLE IF NOT EXISTS votes (
+                message_id   INTEGER NOT NULL,
+                user_id      INTEGER NOT NULL,
+                option_idx   INTEGER NOT NULL,
+                PRIMARY KEY (message_id, user_id, option_idx)
+            );
+            CREATE TABLE IF NOT EXISTS vote_changes (
+                id           INTEGER PRIMARY KEY AUTOINCREMENT,
+                message_id   INTEGER NOT NULL,
+                user_id      INTEGER,
+                option_idx   INTEGER NOT NULL,
+                change       TEXT NOT NULL,
+                changed_at   INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS schedules (
+                id           INTEGER PRIMARY KEY AUTOINCREMENT,
+                guild_id     INTEGER NOT NULL,
+                channel_id   INTEGER NOT NULL,
+                created_by   INTEGER NOT NULL,
+                spec         TEXT NOT NULL,
+                timezone     TEXT NOT NULL,
+                title        TEXT NOT NULL,
+                description  TEXT,
+                options      TEXT,
+                paused       INTEGER NOT NULL DEFAULT 0,
+                next_run     INTEGER,
+                last_run     INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS schedules_next_run ON schedules (next_run);
+            CREATE TABLE IF NOT EXISTS reminder_runs (
+                message_id   INTEGER NOT NULL,
+                offset_secs  INTEGER NOT NULL,
+                ran_at       INTEGER NOT NULL,
+                PRIMARY KEY (message_id, offset_secs)
+            );
+            CREATE TABLE IF NOT EXISTS reminders (
+                message_id   INTEGER NOT NULL,
+                user_id      INTEGER NOT NULL,
+                offset_secs  INTEGER NOT NULL,
+                sent_at      INTEGER NOT NULL,
+                PRIMARY KEY (message_id, user_id, offset_secs)
+            );
+            CREATE TABLE IF NOT EXISTS attendance_taken (
+                message_id   INTEGER PRIMARY KEY,
+                guild_id     INTEGER NOT NULL,
+                taken_at     INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS attendance (
+                message_id   INTEGER NOT NULL,
+                user_id      INTEGER NOT NULL,
+                kind         TEXT,
+                in_voice     INTEGER NOT NULL,
+                PRIMARY KEY (message_id, user_id)
+            );",
+        )?;
+        // columns added after the first release
+        for column in ["deadline", "closed_at", "event_at"] {
+            let exists = conn.prepare("SELECT 1 FROM pragma_table_info('polls') WHERE name = ?1")?.exists([column])?;
+            if !exists {
+                conn.execute_batch(&format!("ALTER TABLE polls ADD COLUMN {column} INTEGER;"))?;
+            }
+        }
+        Ok(PollStore { conn: Mutex::new(conn) })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Connection> {
+        // a panic while holding the lock doesn't leave the connection in a broken state
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // records the poll, does nothing if it is already known
+    pub fn record_poll(&self, poll: &StoredPoll) -> Result<(), rusqlite::Error> {
+        let mut conn = self.lock();
+        let tx = conn.transaction()?;
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO polls (message_id, guild_id, channel_id, source_bot, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![poll.message_id.get() as i64, poll.guild_id.get() as i64, poll.channel_id.get() as i64,
+                poll.source_bot.get() as i64, poll.created_at],
+        )?;
+        if inserted > 0 {
+            for (i, o) in poll.options.iter().enumerate() {
+                tx.execute("INSERT INTO poll_options (message_id, idx, label) VALUES (?1, ?2, ?3)",
+                    params![poll.message_id.get() as i64, i as i64, o])?;
+            }
+        }
+        tx.commit()
+    }
+
+    pub fn get_poll(&self, message_id: MessageId) -> Result<Option<StoredPoll>, rusqlite::Error> {
+        let conn = self.lock();
+        let row = conn.query_row(
+            "SELECT message_id, guild_id, channel_id, source_bot, created_at FROM polls WHERE message_id = ?1",
+            params![message_id.get() as i64],
+            poll_from_row,
+        ).optional()?;
+        match row {
+            Some(mut p) => {
+                p.options = options_of(&conn, p.message_id)?;
+                Ok(Some(p))
+            },
+            None => Ok(None),
+        }
+    }
+
+    // the most recent poll posted in the channel
+    pub fn last_poll_in_channel(&self, channel_id: ChannelId) -> Result<Option<StoredPoll>, rusqlite::Error> {
+        let conn = self.lock();
+        let row = conn.query_row(
+            "SELECT message_id, guild_id, channel_id, source_bot, created_at FROM polls
+                WHERE channel_id = ?1 ORDER BY created_at DESC, message_id DESC LIMIT 1",
+            params![channel_id.get() as i64],
+            poll_from_row,
+        ).optional()?;
+        match row {
+            Some(mut p) => {
+                p.options = options_of(&conn, p.message_id)?;
+                Ok(Some(p))
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn forget_poll(&self, message_id: MessageId) -> Result<(), rusqlite::Error> {
+        let mut conn = self.lock();
+        let tx = conn.transaction()?;
+        for table in ["polls", "poll_options", "votes", "vote_changes", "reminder_runs", "reminders", "attendance_taken", "attendance"] {
+            tx.execute(&format!("DELETE FROM {table} WHERE message_id = ?1"), params![message_id.get() as i64])?;
+        }
+        tx.commit()
+    }
+
+    // None clears the deadline, returns false if the poll is not in the store
+    pub fn set_deadline(&self, message_id: MessageId, deadline: Option<i64>) -> Result<bool, rusqlite::Error> {
+        let conn = self.lock();
+        let n = conn.execute("UPDATE polls SET deadline = ?1 WHERE message_id = ?2", params![deadline, message_id.get() as i64])?;
+        Ok(n > 0)
+    }
+
+    // polls with a deadline in (from, to], with their deadlines
+    pub fn polls_with_deadline_between(&self, from: i64, to: i64) -> Result<Vec<(StoredPoll, i64)>, rusqlite::Error> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare(
+            "SELECT message_id, guild_id, channel_id, source_bot, created_at, deadline FROM polls
+                WHERE deadline > ?1 AND deadline <= ?2 AND closed_at IS NULL ORDER BY deadline")?;
+        let rows = stmt.query_map(params![from, to], |r| Ok((poll_from_row(r)?, r.get::<_, i64>(5)?)))?;
+        let mut result: Vec<(StoredPoll, i64)> = rows.collect::<Result<_, _>>()?;
+        for (p, _) in result.iter_mut() {
+            p.options = options_of(&conn, p.message_id)?;
+        }
+        Ok(result)
+    }
+
+    // when the event the poll is about starts, None clears it, returns false if the poll is not in the store
+    pub fn set_event_at(&self, message_id: MessageId, event_at: Option<i64>) -> Result<bool, rusqlite::Error> {
+        let conn = self.lock();
+        let n = conn.execute("UPDATE polls SET event_at = ?1 WHERE message_id = ?2", params![event_at, message_id.get() as i64])?;
+        Ok(n > 0)
+    }
+
+    // polls whose event started before the given time and whose attendance wasn't taken yet, with their event times
+    pub fn polls_to_take_attendance(&self, started_before: i64) -> Result<Vec<(StoredPoll, i64)>, rusqlite::Error> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare(
+            "SELECT message_id, guild_id, channel_id, source_bot, created_at, event_at FROM polls
+                WHERE event_at <= ?1 AND message_id NOT IN (SELECT message_id FROM attendance_taken) ORDER BY event_at")?;
+        let rows = stmt.query_map(params![started_before], |r| Ok((poll_from_row(r)?, r.get::<_, i64>(5)?)))?;
+        rows.collect()
+    }
+
+    // records who voted what and who showed up, an empty list just marks the attendance as taken
+    pub fn record_attendance(&self, message_id: MessageId, guild_id: GuildId, taken_at: i64, records: &[AttendanceRecord])
+        -> Result<(), rusqlite::Error>
+    {
+        let mut conn = self.lock();
+        let tx = conn.transaction()?;
+        let m_id = message_id.get() as i64;
+        tx.execute("INSERT OR REPLACE INTO attendance_taken (message_id, guild_id, taken_at) VALUES (?1, ?2, ?3)",
+            params![m_id, guild_id.get() as i64, taken_at])?;
+        tx.execute("DELETE FROM attendance WHERE message_id = ?1", params![m_id])?;
+        for r in records {
+            tx.execute("INSERT INTO attendance (message_id, user_id, kind, in_voice) VALUES (?1, ?2, ?3, ?4)",
+                params![m_id, r.user_id.get() as i64, r.kind, r.in_voice])?;
+        }
+        tx.commit()
+    }
+
+    // per-member attendance over the polls of the guild whose attendance was taken since the given time
+    pub fn attendance_stats(&self, guild_id: GuildId, since: i64) -> Result<Vec<MemberStats>, rusqlite::Error> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare(
+            "SELECT a.user_id, COUNT(*),
+                    SUM(a.kind IS 'accepted'),
+                    SUM(a.kind IS 'accepted' AND a.in_voice = 0),
+                    SUM(a.kind IS NULL)
+                FROM attendance a JOIN attendance_taken t ON a.message_id = t.message_id
+                WHERE t.guild_id = ?1 AND t.taken_at >= ?2
+                GROUP BY a.user_id ORDER BY a.user_id")?;
+        let rows = stmt.query_map(params![guild_id.get() as i64, since], |r| Ok(MemberStats {
+            user_id: UserId::new(r.get::<_, i64>(0)? as u64),
+            polls: r.get(1)?,
+            accepted: r.get(2)?,
+            no_shows: r.get(3)?,
+            no_votes: r.get(4)?,
+        }))?;
+        rows.collect()
+    }
+
+    // returns false if the poll is not in the store or is already closed
+    #[cfg(feature = "poll_creation")]
+    pub fn close_poll(&self, message_id: MessageId, closed_at: i64) -> Result<bool, rusqlite::Error> {
+        let conn = self.lock();
+        let n = conn.execute("UPDATE polls SET closed_at = ?1 WHERE message_id = ?2 AND closed_at IS NULL",
+            params![closed_at, message_id.get() as i64])?;
+        Ok(n > 0)
+    }
+
+    #[cfg(feature = "poll_creation")]
+    pub fn closed_at(&self, message_id: MessageId) -> Result<Option<i64>, rusqlite::Error> {
+        let conn = self.lock();
+        let c: Option<Option<i64>> = conn.query_row("SELECT closed_at FROM polls WHERE message_id = ?1",
+            params![message_id.get() as i64], |r| r.get(0)).optional()?;
+        Ok(c.flatten())
+    }
+
+    // open polls posted by source_bot whose deadline has passed
+    #[cfg(feature = "poll_creation")]
+    pub fn polls_to_close(&self, source_bot: UserId, now: i64) -> Result<Vec<StoredPoll>, rusqlite::Error> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare(
+            "SELECT message_id, guild_id, channel_id, source_bot, created_at FROM polls
+                WHERE source_bot = ?1 AND deadline <= ?2 AND closed_at IS NULL ORDER BY deadline")?;
+        let rows = stmt.query_map(params![source_bot.get() as i64, now], poll_from_row)?;
+        rows.collect()
+    }
+
+    // true if the reminder for this offset was already sent (to whoever needed it)
+    pub fn reminder_ran(&self, message_id: MessageId, offset_secs: i64) -> Result<bool, rusqlite::Error> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare("SELECT 1 FROM reminder_runs WHERE message_id = ?1 AND offset_secs = ?2")?;
+        stmt.exists(params![message_id.get() as i64, offset_secs])
+    }
+
+    // users who already got the reminder for this offset
+    pub fn reminded(&self, message_id: MessageId, offset_secs: i64) -> Result<Vec<UserId>, rusqlite::Error> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare("SELECT user_id FROM reminders WHERE message_id = ?1 AND offset_secs = ?2")?;
+        let rows = stmt.query_map(params![message_id.get() as i64, offset_secs], |r| Ok(UserId::new(r.get::<_, i64>(0)? as u64)))?;
+        rows.collect()
+    }
+
+    // marks the reminder for this offset as sent to the users
+    pub fn record_reminders(&self, message_id: MessageId, offset_secs: i64, users: &[UserId], sent_at: i64)
+        -> Result<(), rusqlite::Error>
+    {
+        let mut conn = self.lock();
+        let tx = conn.transaction()?;
+        let m_id = message_id.get() as i64;
+        tx.execute("INSERT OR IGNORE INTO reminder_runs (message_id, offset_secs, ran_at) VALUES (?1, ?2, ?3)",
+            params![m_id, offset_secs, sent_at])?;
+        for u in users {
+            tx.execute("INSERT OR IGNORE INTO reminders (message_id, user_id, offset_secs, sent_at) VALUES (?1, ?2, ?3, ?4)",
+                params![m_id, u.get() as i64, offset_secs, sent_at])?;
+        }
+        tx.commit()
+    }
+
+    // appends to the vote history and updates the current votes
+    // user_id is None when the whole option was cleared (e.g. all the reactions with that emoji were removed)
+    #[cfg(feature = "poll_creation")]
+    pub fn record_vote_change(&self, message_id: MessageId, user_id: Option<UserId>, option_idx: usize, change: &str,
+        changed_at: i64) -> Result<(), rusqlite::Error>
+    {
+        let mut conn = self.lock();
+        let tx = conn.transaction()?;
+        let m_id = message_id.get() as i64;
+        let u_id = user_id.map(|u| u.get() as i64);
+        tx.execute(
+            "INSERT INTO vote_changes (message_id, user_id, option_idx, change, changed_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![m_id, u_id, option_idx as i64, change, changed_at],
+        )?;
+        match (change, u_id) {
+            ("add", Some(u)) => {
+                tx.execute("INSERT OR IGNORE INTO votes (message_id, user_id, option_idx) VALUES (?1, ?2, ?3)",
+                    params![m_id, u, option_idx as i64])?;
+            },
+            (_, Some(u)) => {
+                tx.execute("DELETE FROM votes WHERE message_id = ?1 AND user_id = ?2 AND option_idx = ?3",
+                    params![m_id, u, option_idx as i64])?;
+            },
+            (_, None) => {
+                tx.execute("DELETE FROM votes WHERE message_id = ?1 AND option_idx = ?2", params![m_id, option_idx as i64])?;
+            },
+        }
+        tx.commit()
+    }
+
+    // makes option_idx the only vote of the user (None clears their vote), the history gets what actually changed
+    // returns false if nothing changed
+    #[cfg(feature = "poll_creation")]
+    pub fn set_vote(&self, message_id: MessageId, user_id: UserId, option_idx: Option<usize>, changed_at: i64)
+        -> Result<bool, rusqlite::Error>
+    {
+        let mut conn = self.lock();
+        let tx = conn.transaction()?;
+        let m_id = message_id.get() as i64;
+        let u_id = user_id.get() as i64;
+        let current: Vec<i64> = {
+            let mut stmt = tx.prepare("SELECT option_idx FROM votes WHERE message_id = ?1 AND user_id = ?2")?;
+            let rows = stmt.query_map(params![m_id, u_id], |r| r.get(0))?;
+            rows.collect::<Result<_, _>>()?
+        };
+        let new = option_idx.map(|o| o as i64);
+        let mut changed = false;
+        for o in current.iter().filter(|o| Some(**o) != new) {
+            tx.execute("DELETE FROM votes WHERE message_id = ?1 AND user_id = ?2 AND option_idx = ?3", params![m_id, u_id, o])?;
+            tx.execute(
+                "INSERT INTO vote_changes (message_id, user_id, option_idx, change, changed_at) VALUES (?1, ?2, ?3, 'remove', ?4)",
+                params![m_id, u_id, o, changed_at],
+            )?;
+            changed = true;
+        }
+        if let Some(o) = new {
+            if !current.contains(&o) {
+                tx.execute("INSERT INTO votes (message_id, user_id, option_idx) VALUES (?1, ?2, ?3)", params![m_id, u_id, o])?;
+                tx.execute(
+                    "INSERT INTO vote_changes (message_id, user_id, option_idx, change, changed_at) VALUES (?1, ?2, ?3, 'add', ?4)",
+                    params![m_id, u_id, o, changed_at],
+                )?;
+                changed = true;
+            }
+        }
+        tx.commit()?;
+        Ok(changed)
+    }
+
+    // replaces the current votes with a full snapshot (e.g. read from the reactions) and marks them as complete
+    pub fn replace_votes(&self, message_id: MessageId, votes: &[Vec<UserId>]) -> Result<(), rusqlite::Error> {
+        let mut conn = self.lock();
+        let tx = conn.transaction()?;
+        let m_id = message_id.get() as i64;
+        tx.execute("DELETE FROM votes WHERE message_id = ?1", params![m_id])?;
+        for (i, option_votes) in votes.iter().enumerate() {
+            for u in option_votes {
+                tx.execute("INSERT OR IGNORE INTO votes (message_id, user_id, option_idx) VALUES (?1, ?2, ?3)",
+                    params![m_id, u.get() as i64, i as i64])?;
+            }
+        }
+        tx.execute("UPDATE polls SET votes_synced = 1 WHERE message_id = ?1", params![m_id])?;
+        tx.commit()
+    }
+
+    // current votes for the poll, None if the store doesn't have a complete picture
+    pub fn votes(&self, message_id: MessageId, num_options: usize) -> Result<Option<Vec<Vec<UserId>>>, rusqlite::Error> {
+        let conn = self.lock();
+        let m_id = message_id.get() as i64;
+        let synced: Option<i64> = conn.query_row("SELECT votes_synced FROM polls WHERE message_id = ?1",
+            params![m_id], |r| r.get(0)).optional()?;
+        if synced != Some(1) {return Ok(None);}
+
+        let mut result: Vec<Vec<UserId>> = vec![Vec::new(); num_options];
+        let mut stmt = conn.prepare("SELECT user_id, option_idx FROM votes WHERE message_id = ?1 ORDER BY rowid")?;
+        let rows = stmt.query_map(params![m_id], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, i64>(1)?)))?;
+        for row in rows {
+            let (u, i) = row?;
+            if let Some(v) = result.get_mut(i as usize) {
+                v.push(UserId::new(u as u64));
+            }
+        }
+        Ok(Some(result))
+    }
+
+    // returns the id of the new schedule
+    #[cfg(feature = "scheduled_polls")]
+    pub fn add_schedule(&self, s: &StoredSchedule) -> Result<i64, rusqlite::Error> {
+        let conn = self.lock();
+        conn.execute(
+            "INSERT INTO schedules (guild_id, channel_id, created_by, spec, timezone, title, description, options, paused, next_run)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![s.guild_id.get() as i64, s.channel_id.get() as i64, s.created_by.get() as i64, s.spec, s.timezone,
+                s.title, s.description, s.options, s.paused, s.next_run],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    #[cfg(feature = "scheduled_polls")]
+    pub fn get_schedule(&self, guild_id: GuildId, id: i64) -> Result<Option<StoredSchedule>, rusqlite::Error> {
+        let conn = self.lock();
+        conn.query_row(
+            &format!("SELECT {SCHEDULE_COLUMNS} FROM schedules WHERE guild_id = ?1 AND id = ?2"),
+            params![guild_id.get() as i64, id],
+            schedule_from_row,
+        ).optional()
+    }
+
+    #[cfg(feature = "scheduled_polls")]
+    pub fn guild_schedules(&self, guild_id: GuildId) -> Result<Vec<StoredSchedule>, rusqlite::Error> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare(&format!("SELECT {SCHEDULE_COLUMNS} FROM schedules WHERE guild_id = ?1 ORDER BY id"))?;
+        let rows = stmt.query_map(params![guild_id.get() as i64], schedule_from_row)?;
+        rows.collect()
+    }
+
+    // schedules that are not paused and should have fired by now
+    #[cfg(feature = "scheduled_polls")]
+    pub fn due_schedules(&self, now: i64) -> Result<Vec<StoredSchedule>, rusqlite::Error> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {SCHEDULE_COLUMNS} FROM schedules WHERE paused = 0 AND next_run IS NOT NULL AND next_run <= ?1 ORDER BY next_run"))?;
+        let rows = stmt.query_map(params![now], schedule_from_row)?;
+        rows.collect()
+    }
+
+    // pausing or resuming also sets the next run (resumed schedules don't catch up on what they missed)
+    // returns false if there is no such schedule in the guild
+    #[cfg(feature = "scheduled_polls")]
+    pub fn set_schedule_paused(&self, guild_id: GuildId, id: i64, paused: bool, next_run: Option<i64>)
+        -> Result<bool, rusqlite::Error>
+    {
+        let conn = self.lock();
+        let n = conn.execute("UPDATE schedules SET paused = ?1, next_run = ?2 WHERE guild_id = ?3 AND id = ?4",
+            params![paused, next_run, guild_id.get() as i64, id])?;
+        Ok(n > 0)
+    }
+
+    #[cfg(feature = "scheduled_polls")]
+    pub fn set_schedule_run(&self, id: i64, next_run: Option<i64>, last_run: i64) -> Result<(), rusqlite::Error> {
+        let conn = self.lock();
+        conn.execute("UPDATE schedules SET next_run = ?1, last_run = ?2 WHERE id = ?3", params![next_run, last_run, id])?;
+        Ok(())
+    }
+
+    // returns false if there is no such schedule in the guild
+    #[cfg(feature = "scheduled_polls")]
+    pub fn delete_schedule(&self, guild_id: GuildId, id: i64) -> Result<bool, rusqlite::Error> {
+        let conn = self.lock();
+        let n = conn.execute("DELETE FROM schedules WHERE guild_id = ?1 AND id = ?2", params![guild_id.get() as i64, id])?;
+        Ok(n > 0)
+    }
+}
+
+
+fn poll_from_row(r: &rusqlite::Row) -> Result<StoredPoll, rusqlite::Error> {
+    Ok(StoredPoll {
+        message_id: MessageId::new(r.get::<_, i64>(0)? as u64),
+        guild_id: GuildId::new(r.get::<_, i64>(1)? as u64),
+        channel_id: ChannelId::new(r.get::<_, i64>(2)? as u64),
+        source_bot: UserId::new(r.get::<_, i64>(3)? as u64),
+        created_at: r.get(4)?,
+        options: Vec::new(),
+    })
+}
+
+#[cfg(feature = "scheduled_polls")]
+const SCHEDULE_COLUMNS: &str =
+    "id, guild_id, channel_id, created_by, spec, timezone, title, description, options, paused, next_run";
+
+#[cfg(feature = "scheduled_polls")]
+fn schedule_from_row(r: &rusqlite::Row) -> Result<StoredSchedule, rusqlite::Error> {
+    Ok(StoredSchedule {
+        id: r.get(0)?,
+        guild_id: GuildId::new(r.get::<_, i64>(1)? as u64),
+        channel_id: ChannelId::new(r.get::<_, i64>(2)? as u64),
+        created_by: UserId::new(r.get::<_, i64>(3)? as u64),
+        spec: r.get(4)?,
+        timezone: r.get(5)?,
+        title: r.get(6)?,
+        description: r.get(7)?,
+        options: r.get(8)?,
+        paused: r.get(9)?,
+        next_run: r.get(10)?,
+    })
+}
+
+fn options_of(conn: &Connection, message_id: MessageId) -> Result<Vec<String>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT label FROM poll_options WHERE message_id = ?1 ORDER BY idx")?;
+    let rows = stmt.query_map(params![message_id.get() as i64], |r| r.get(0))?;
+    rows.collect()
+}
+
+
+// returns the store if it was opened at startup
+pub async fn get(ctx: &Context) -> Option<Arc<PollStore>> {
+    let data = ctx.data.read().await;
+    data.get::<PollStore>().cloned()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store_with_poll() -> PollStore {
+        let store = PollStore::open(":memory:").unwrap();
+        store.record_poll(&StoredPoll {
+            message_id: MessageId::new(1),
+            guild_id: GuildId::new(2),
+            channel_id: ChannelId::new(3),
+            source_bot: UserId::new(4),
+            created_at: 0,
+            options: vec!["Accepted".to_string(), "Declined".to_string(), "Tentative".to_string()],
+        }).unwrap();
+        store.replace_votes(MessageId::new(1), &[vec![], vec![], vec![]]).unwrap();
+        store
+    }
+
+    #[test]
+    fn forgotten_poll_leaves_nothing_behind() {
+        let store = store_with_poll();
+        let m = MessageId::new(1);
+        store.replace_votes(m, &[vec![UserId::new(10)], vec![], vec![]]).unwrap();
+        store.lock().execute("INSERT INTO vote_changes (message_id, user_id, option_idx, change, changed_at) VALUES (1, 10, 0, 'add', 5)",
+            []).unwrap();
+        store.record_reminders(m, 3600, &[UserId::new(11)], 50).unwrap();
+        store.forget_poll(m).unwrap();
+
+        let conn = store.lock();
+        for table in ["polls", "poll_options", "votes", "vote_changes", "reminder_runs", "reminders", "attendance_taken", "attendance"] {
+            let n: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {table} WHERE message_id = 1"), [], |r| r.get(0)).unwrap();
+            assert_eq!(n, 0, "{table}");
+        }
+    }
+
+    #[cfg(feature = "poll_creation")]
+    #[test]
+    fn set_vote_keeps_one_option_per_user() {
+        let store = store_with_poll();
+        let (m, u) = (MessageId::new(1), UserId::new(10));
+        assert!(store.set_vote(m, u, Some(0), 1).unwrap());
+        assert!(!store.set_vote(m, u, Some(0), 2).unwrap());
+        assert!(store.set_vote(m, u, Some(2), 3).unwrap());
+        assert_eq!(store.votes(m, 3).unwrap().unwrap(), vec![vec![], vec![], vec![u]]);
+        assert!(store.set_vote(m, u, None, 4).unwrap());
+        assert_eq!(store.votes(m, 3).unwrap().unwrap(), vec![Vec::<UserId>::new(); 3]);
+    }
+
+    #[cfg(feature = "poll_creation")]
+    #[test]
+    fn set_vote_replaces_votes_from_reactions() {
+        let store = store_with_poll();
+        let (m, u) = (MessageId::new(1), UserId::new(10));
+        store.replace_votes(m, &[vec![u], vec![u], vec![]]).unwrap();
+        store.set_vote(m, u, Some(1), 1).unwrap();
+        assert_eq!(store.votes(m, 3).unwrap().unwrap(), vec![vec![], vec![u], vec![]]);
+    }
+
+    #[test]
+    fn deadlines_and_reminders() {
+        let store = store_with_poll();
+        let m = MessageId::new(1);
+        assert!(!store.set_deadline(MessageId::new(9), Some(100)).unwrap());
+        assert!(store.set_deadline(m, Some(100)).unwrap());
+        assert_eq!(store.polls_with_deadline_between(0, 99).unwrap().len(), 0);
+        let due = store.polls_with_deadline_between(0, 100).unwrap();
+        assert_eq!((due[0].0.message_id, due[0].1, due[0].0.options.len()), (m, 100, 3));
+        assert!(store.polls_with_deadline_between(100, 200).unwrap().is_empty());
+
+        assert!(!store.reminder_ran(m, 3600).unwrap());
+        store.record_reminders(m, 3600, &[UserId::new(10), UserId::new(11)], 50).unwrap();
+        store.record_reminders(m, 3600, &[UserId::new(10)], 51).unwrap();
+        assert!(store.reminder_ran(m, 3600).unwrap());
+        assert!(!store.reminder_ran(m, 7200).unwrap());
+        assert_eq!(store.reminded(m, 3600).unwrap().len(), 2);
+
+        store.set_deadline(m, None).unwrap();
+        assert!(store.polls_with_deadline_between(0, 200).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "poll_creation")]
+    #[test]
+    fn closing_at_the_deadline() {
+        let store = store_with_poll();
+        let m = MessageId::new(1);
+        store.set_deadline(m, Some(100)).unwrap();
+        assert!(store.polls_to_close(UserId::new(4), 99).unwrap().is_empty());
+        assert!(store.polls_to_close(UserId::new(5), 100).unwrap().is_empty());
+        assert_eq!(store.polls_to_close(UserId::new(4), 100).unwrap()[0].message_id, m);
+        assert!(store.close_poll(m, 100).unwrap());
+        assert!(!store.close_poll(m, 101).unwrap());
+        assert_eq!(store.closed_at(m).unwrap(), Some(100));
+        assert!(store.polls_to_close(UserId::new(4), 200).unwrap().is_empty());
+        assert!(store.polls_with_deadline_between(0, 200).unwrap().is_empty());
+    }
+
+    #[test]
+    fn attendance_stats() {
+        let store = store_with_poll();
+        let (m, g) = (MessageId::new(1), GuildId::new(2));
+        store.set_event_at(m, Some(100)).unwrap();
+        assert!(store.polls_to_take_attendance(99).unwrap().is_empty());
+        assert_eq!(store.polls_to_take_attendance(100).unwrap()[0].1, 100);
+        let record = |u: u64, kind: Option<&str>, in_voice: bool| AttendanceRecord {
+            user_id: UserId::new(u), kind: kind.map(|k| k.to_string()), in_voice,
+        };
+        store.record_attendance(m, g, 110, &[
+            record(10, Some("accepted"), true),
+            record(11, Some("accepted"), false),
+            record(12, Some("declined"), false),
+            record(13, None, true),
+        ]).unwrap();
+        assert!(store.polls_to_take_attendance(200).unwrap().is_empty());
+
+        let stats = store.attendance_stats(g, 0).unwrap();
+        assert_eq!(stats.len(), 4);
+        assert_eq!(stats[1], MemberStats { user_id: UserId::new(11), polls: 1, accepted: 1, no_shows: 1, no_votes: 0 });
+        assert_eq!(stats[3], MemberStats { user_id: UserId::new(13), polls: 1, accepted: 0, no_shows: 0, no_votes: 1 });
+        assert!(store.attendance_stats(g, 111).unwrap().is_empty());
+        assert!(store.attendance_stats(GuildId::new(5), 0).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "scheduled_polls")]
+    #[test]
+    fn schedules_are_per_guild() {
+        let store = PollStore::open(":memory:").unwrap();
+        let (g, other) = (GuildId::new(2), GuildId::new(5));
+        let id = store.add_schedule(&StoredSchedule {
+            id: 0,
+            guild_id: g,
+            channel_id: ChannelId::new(3),
+            created_by: UserId::new(4),
+            spec: "thu 18:00".to_string(),
+            timezone: "UTC".to_string(),
+            title: "Raid {date}".to_string(),
+            description: None,
+            options: None,
+            paused: false,
+            next_run: Some(100),
+        }).unwrap();
+        assert_eq!(store.due_schedules(99).unwrap().len(), 0);
+        assert_eq!(store.due_schedules(100).unwrap()[0].id, id);
+        assert!(!store.set_schedule_paused(other, id, true, None).unwrap());
+        assert!(!store.delete_schedule(other, id).unwrap());
+        assert!(store.set_schedule_paused(g, id, true, Some(50)).unwrap());
+        assert!(store.due_schedules(100).unwrap().is_empty());
+        assert_eq!(store.guild_schedules(g).unwrap().len(), 1);
+        assert!(store.delete_schedule(g, id).unwrap());
+        assert!(store.get_schedule(g, id).unwrap().is_none());
+    }
+}
@@ -1,108 +1,523 @@
-//Things to interact with third-party voting/polling bots such as Apollo or Pancake
+//Things to interact with third-party voting/polling bots such as Apollo, Pancake, Carl-bot, Juniper, Sesh or Raid-Helper
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashSet};
 
-use serenity::{all::{Member, Message, UserId},};
+use serenity::{all::{Embed, Member, UserId}, utils::parse_user_mention};
 
+use crate::config::BotConfig;
+use crate::i18n::tr;
+use crate::poll_sources;
+use crate::utils::UnattributedVoter;
 
-const APOLLO_ADT: [&str; 3] = ["<:accepted:713124484436983971>", "<:declined:713124484688642068>", "<:tentative:713214962641666109>"];
-const PANCAKE_ADT: [&str; 3] = ["✅", "❌", "❔"];
 
+// placeholders bots put into empty fields
+const EMPTY_FIELD_MARKERS: [&str; 4] = ["-", "—", "None", "Nobody"];
 
-// Parses the embed assuming it is a poll from compatible third-party bot, 
-// tries to extract the lists of names for "accepted", "declined" and "tentative" options. 
-// Some or all vecs might be empty.
-pub fn parse_tp_bot_poll(msg: &Message) -> Result<[Vec<String>; 3], String>
+
+// Parses the embed assuming it is a poll from compatible third-party bot,
+// tries to extract the lists of names for every option (one per emoji in the bot's config).
+// Some or all vecs might be empty. The errors are user-presentable (in loc)
+pub fn parse_tp_bot_embed(bot: &BotConfig, embed: &Embed, loc: &str) -> Result<Vec<Vec<String>>, String>
 {
-    fn trim_and_split_names_apollo(s: &str) -> Option<Vec<String>>{
-        if let Some(s) = s.strip_prefix(">>> "){
-            let mut v: Vec<String>= Vec::new();
-            let s = s.replace("\\\\", "\\"); //the string received from the JSON embed appears to be double-serialized for some reason
-            let mut l_iter = s.lines();
-            while let Some(l) = l_iter.next() {
-                v.push(l.to_string());
+    let Some(trim_and_split_names) = poll_sources::split_names_fn(&bot.format) else {
+        return Err(tr!(loc, "parse.unknown_format", format = bot.format, bot = bot.name));
+    };
+    let adt_symbols = &bot.emojis;
+
+    let mut result: Vec<Vec<String>> = vec![Vec::new(); adt_symbols.len()];
+    let mut has_poll = false;
+
+    for f in &embed.fields {
+        let matched = adt_symbols.iter().position(|s| f.name.starts_with(s.as_str()));
+        let option = match (matched, bot.unmatched_fields_option) {
+            (Some(i), _) => {has_poll = true; i},
+            (None, Some(i)) if i < result.len() => i,
+            (None, _) => continue,
+        };
+        if let Some(trimmed_split) = trim_and_split_names(f.value.as_str()) {
+            if !(trimmed_split.len() == 1 && EMPTY_FIELD_MARKERS.contains(&trimmed_split[0].as_str())) {
+                has_poll = true; //sign-ups in unmatched fields count too
+                result[option].extend(trimmed_split);
             }
-            if !v.is_empty() {return Some(v)};
         }
-        return None;
     }
+    if !has_poll {
+        return Err(tr!(loc, "parse.no_poll", bot = bot.name));
+    }
+    
+    Ok(result)
+}
 
-    fn trim_and_split_names_pancake(s: &str) -> Option<Vec<String>>{
-        let mut v: Vec<String>= Vec::new();
-        let s = s.replace("\\\\", "\\"); //the string received from the JSON embed appears to be double-serialized for some reason
-        let mut l_iter = s.lines();
-        while let Some(l) = l_iter.next() {
-            if let Some(l) = l.strip_prefix("> "){
-                v.push(l.to_string());
-            }
+
+// How a voter's name was matched to a member, from the most to the least reliable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchStage {
+    Mention,
+    Nick,
+    GlobalName,
+    Username,
+    Normalised,     // ignoring case, diacritics, markdown, emojis and punctuation
+    EditDistance,   // a few letters off after normalising
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameMatch {
+    pub user_id: UserId,
+    pub stage: MatchStage,
+    pub confidence: f32,    // 1.0 for exact matches, less the fuzzier it gets
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    Found(NameMatch),
+    Ambiguous(MatchStage, Vec<UserId>),     // several members match equally well
+    NotFound,
+}
+
+// The names a member can be listed under
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberNames {
+    pub user_id: UserId,
+    pub nick: Option<String>,
+    pub global_name: Option<String>,
+    pub username: String,
+}
+
+impl From<&Member> for MemberNames {
+    fn from(m: &Member) -> Self {
+        MemberNames {
+            user_id: m.user.id,
+            nick: m.nick.clone(),
+            global_name: m.user.global_name.clone(),
+            username: m.user.name.clone(),
         }
-        if !v.is_empty() {return Some(v)};
-        return None;
     }
+}
 
-    let trim_and_split_names = match msg.author.id.get() {
-        crate::BOT_ID_APOLLO => trim_and_split_names_apollo,
-        crate::BOT_ID_PANCAKE => trim_and_split_names_pancake,
-        _ => return Err("Unsupported 3rd party bot.".to_string())
-    };
+impl MemberNames {
+    fn all(&self) -> impl Iterator<Item = &str> {
+        self.nick.as_deref().into_iter().chain(self.global_name.as_deref()).chain([self.username.as_str()])
+    }
+}
 
-    let adt_symbols = match msg.author.id.get() {
-        crate::BOT_ID_APOLLO => APOLLO_ADT,
-        _ => PANCAKE_ADT, //pancake's emojis look default enough
-    };
+// How members going by the same name were told apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tiebreak {
+    AllVoted,       // the name is listed as many times as there are members going by it
+    Reaction,       // only one of them also reacted with the option's emoji
+    ChannelRole,    // only one of them has a role the channel is opened to
+}
 
-    let mut adt_strings: [String; 3] = Default::default();
-    let mut has_poll = false;
+// What else is known about the voters, for the names several members go by
+#[derive(Debug, Clone, Default)]
+pub struct Hints {
+    pub reacted: Vec<HashSet<UserId>>,          // for every option, if the bot's message has reactions too
+    pub channel_role_members: HashSet<UserId>,  // members with a role that has its own access to the channel
+}
+
+// Everything convert_names_to_ids found out
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConvertedNames {
+    pub ids: Vec<Vec<UserId>>,                          // for every option
+    pub not_found: Vec<String>,
+    pub unattributed: Vec<UnattributedVoter>,           // several members go by the name, not counted anywhere
+    pub uncertain: Vec<(String, NameMatch)>,            // counted, but matched by a normalised or similar name
+    pub told_apart: Vec<(String, UserId, Tiebreak)>,    // counted, the name was ambiguous
+}
+
+
+// Tries to find channel members for every option's list of voters' names
+// (some bots list mentions instead of names, those are looked up by id)
+pub fn convert_names_to_ids(names: Vec<Vec<String>>, channel_members: &[Member], hints: &Hints) -> ConvertedNames {
+    let members: Vec<MemberNames> = channel_members.iter().map(MemberNames::from).collect();
+    convert_names(names, &members, hints)
+}
 
-    match msg.embeds.get(0) {
-        None => {
-            println!("No embeds found. Message content in question:"); 
-            println!("{}", msg.content); 
-            return Err("No embeds found in the last 3rd party bot message.".to_string());},
-        Some (e) => {
-            for f in e.fields.clone() {
-                for i in 0..3 {
-                    if f.name.starts_with(adt_symbols[i]) {
-                        has_poll = true;
-                        adt_strings[i] = f.value.clone();
+fn convert_names(names: Vec<Vec<String>>, members: &[MemberNames], hints: &Hints) -> ConvertedNames {
+    let mut result = ConvertedNames {
+        ids: vec![Vec::new(); names.len()],
+        ..Default::default()
+    };
+    for (i, option_names) in names.into_iter().enumerate() {
+        // the names by the members they might be
+        let mut ambiguous: BTreeMap<Vec<UserId>, Vec<String>> = BTreeMap::new();
+        for n in option_names {
+            match resolve_name(&n, members) {
+                Resolution::Found(m) => {
+                    result.ids[i].push(m.user_id);
+                    if m.stage >= MatchStage::Normalised {
+                        result.uncertain.push((n, m));
                     }
-                }
+                },
+                Resolution::Ambiguous(_, mut candidates) => {
+                    candidates.sort();
+                    ambiguous.entry(candidates).or_default().push(n);
+                },
+                Resolution::NotFound => result.not_found.push(n),
             }
-        },
-    };
-    if !has_poll {
-        return Err("No poll found in the last 3rd party bot message.".to_string());
+        }
+        for (candidates, names) in ambiguous {
+            tell_apart(i, names, candidates, hints, &mut result);
+        }
     }
-    let mut result: [Vec<String>; 3] = Default::default();
+    result
+}
 
-    for i in 0..3 {
-        if let Some(trimmed_split) = trim_and_split_names(adt_strings[i].as_str()) {
-            if !(trimmed_split.len() == 1 && trimmed_split[0] == "-") {
-                result[i] = trimmed_split;
+// attributes the names that several members go by where the hints allow it, the rest goes to unattributed
+fn tell_apart(option: usize, names: Vec<String>, candidates: Vec<UserId>, hints: &Hints, result: &mut ConvertedNames) {
+    if names.len() == candidates.len() {
+        for (n, u) in names.into_iter().zip(candidates) {
+            result.ids[option].push(u);
+            result.told_apart.push((n, u, Tiebreak::AllVoted));
+        }
+        return;
+    }
+    let mut left = candidates;
+    for n in names {
+        let only = |set: &HashSet<UserId>| -> Option<UserId> {
+            let mut matching = left.iter().filter(|u| set.contains(u));
+            match (matching.next(), matching.next()) {
+                (Some(u), None) => Some(*u),
+                _ => None,
             }
+        };
+        let picked = hints.reacted.get(option).and_then(only).map(|u| (u, Tiebreak::Reaction))
+            .or_else(|| only(&hints.channel_role_members).map(|u| (u, Tiebreak::ChannelRole)));
+        match picked {
+            Some((u, how)) => {
+                left.retain(|c| *c != u);
+                result.ids[option].push(u);
+                result.told_apart.push((n, u, how));
+            },
+            None => result.unattributed.push(UnattributedVoter { name: n, option, candidates: left.clone() }),
         }
     }
-    
-    return Ok(result);
 }
 
+// Finds the member listed under the name: exact nick, global name, username, then normalised and then similar names
+pub fn resolve_name(name: &str, members: &[MemberNames]) -> Resolution {
+    if let Some(u_id) = parse_user_mention(name) {
+        return match members.iter().any(|m| m.user_id == u_id) {
+            true => Resolution::Found(NameMatch { user_id: u_id, stage: MatchStage::Mention, confidence: 1.0 }),
+            false => Resolution::NotFound,
+        };
+    }
+    // bots escape markdown in names ("Bob\_the\_builder"), the real name might have a backslash though
+    let unescaped = unescape_markdown(name);
+    let is_name = |s: Option<&str>| s.is_some_and(|s| s == name || s == unescaped);
+    for (stage, confidence) in [(MatchStage::Nick, 1.0), (MatchStage::GlobalName, 0.95), (MatchStage::Username, 0.9)] {
+        let found: Vec<UserId> = members.iter()
+            .filter(|m| match stage {
+                MatchStage::Nick => is_name(m.nick.as_deref()),
+                MatchStage::GlobalName => is_name(m.global_name.as_deref()),
+                // usernames are lowercase
+                _ => m.username == unescaped.to_lowercase(),
+            })
+            .map(|m| m.user_id)
+            .collect();
+        if let Some(r) = resolution(stage, confidence, found) {
+            return r;
+        }
+    }
+
+    let normalised = normalise(name);
+    if normalised.is_empty() {
+        return Resolution::NotFound;
+    }
+    let found: Vec<UserId> = members.iter()
+        .filter(|m| m.all().any(|n| normalise(n) == normalised))
+        .map(|m| m.user_id)
+        .collect();
+    if let Some(r) = resolution(MatchStage::Normalised, 0.8, found) {
+        return r;
+    }
+
+    // a typo or a small change of the nick: up to a quarter of the letters, not for the short names
+    let len = normalised.chars().count();
+    let max_distance = len / 4;
+    if max_distance == 0 {
+        return Resolution::NotFound;
+    }
+    let distances: Vec<(UserId, usize)> = members.iter()
+        .filter_map(|m| m.all().map(|n| edit_distance(&normalise(n), &normalised)).min().map(|d| (m.user_id, d)))
+        .filter(|(_, d)| *d <= max_distance)
+        .collect();
+    let Some(best) = distances.iter().map(|(_, d)| *d).min() else {
+        return Resolution::NotFound;
+    };
+    let found: Vec<UserId> = distances.iter().filter(|(_, d)| *d == best).map(|(u, _)| *u).collect();
+    let confidence = 0.75 * (1.0 - best as f32 / len as f32);
+    resolution(MatchStage::EditDistance, confidence, found).unwrap_or(Resolution::NotFound)
+}
+
+fn resolution(stage: MatchStage, confidence: f32, mut found: Vec<UserId>) -> Option<Resolution> {
+    found.dedup();
+    match found.len() {
+        0 => None,
+        1 => Some(Resolution::Found(NameMatch { user_id: found[0], stage, confidence })),
+        _ => Some(Resolution::Ambiguous(stage, found)),
+    }
+}
 
-// Tries to find channel members from the supplied hashmap for every position in the 3-vector of voters' names 
-// Returns 3-vector of IDs (where found) and message with user-presentable warnings (might be empty)
-pub fn convert_names_to_ids(names: [Vec<String>; 3], channel_members: &HashMap<String, Member>) -> ([Vec<UserId>; 3], String) {
-    let mut result: [Vec<UserId>; 3] = [Vec::new(), Vec::new(), Vec::new()];
-    let mut not_found = String::new();
-    for i in 0..=2 {
-        for n in &names[i] {
-            if let Some(m) = channel_members.get(n) {
-                result[i].push(m.user.id);
-            } else {
-                not_found = format!("{not_found}\n{n}");
-            }
+
+const MARKDOWN_CHARS: [char; 7] = ['\\', '*', '_', '~', '`', '|', '>'];
+
+fn unescape_markdown(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(next) if c == '\\' && MARKDOWN_CHARS.contains(next) => {},
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+// lowercase letters and digits without diacritics, single spaces between the words, the rest (emojis, markdown) is dropped
+pub fn normalise(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    for c in name.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            result.push(fold_diacritic(c));
+        } else if c.is_whitespace() && !result.is_empty() && !result.ends_with(' ') {
+            result.push(' ');
+        }
+    }
+    result.trim_end().to_string()
+}
+
+// the letter without its diacritic, for the letters used in European names
+fn fold_diacritic(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'č' => 'c',
+        'ď' | 'đ' => 'd',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => 'e',
+        'ğ' => 'g',
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => 'i',
+        'ł' | 'ľ' => 'l',
+        'ñ' | 'ń' | 'ň' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => 'o',
+        'ř' => 'r',
+        'ś' | 'š' | 'ş' | 'ß' => 's',
+        'ť' | 'ţ' => 't',
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        'ё' => 'е',
+        'й' => 'и',
+        c => c,
+    }
+}
+
+// Levenshtein distance in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + (ca != *cb) as usize;
+            cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
         }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn parse_fixture(bot_name: &str, fixture: &str) -> Result<Vec<Vec<String>>, String> {
+        let config = Config::default();
+        let bot = config.bots.iter().find(|b| b.name == bot_name).expect("bot is not in the default config");
+        let embed: Embed = serde_json::from_str(fixture).expect("fixture is not a valid embed");
+        parse_tp_bot_embed(bot, &embed, "en")
+    }
+
+    fn names(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn apollo() {
+        let r = parse_fixture("Apollo", include_str!("../tests/fixtures/tpbot/apollo.json")).unwrap();
+        assert_eq!(r, vec![names(&["Alice", "Bob\\_the\\_builder", "Женя"]), names(&["Carol"]), vec![]]);
+    }
+
+    #[test]
+    fn pancake() {
+        let r = parse_fixture("Pancake", include_str!("../tests/fixtures/tpbot/pancake.json")).unwrap();
+        assert_eq!(r, vec![names(&["Alice", "Bob"]), vec![], names(&["Dave 🐉"])]);
+    }
+
+    #[test]
+    fn carl_bot() {
+        let r = parse_fixture("Carl-bot", include_str!("../tests/fixtures/tpbot/carl.json")).unwrap();
+        assert_eq!(r, vec![names(&["Alice", "Bob"]), vec![], names(&["Carol"])]);
     }
-    if not_found.len() > 0 {
-        not_found = format!("Not found among channel members (by name):{not_found}");
+
+    #[test]
+    fn juniper() {
+        let r = parse_fixture("Juniper", include_str!("../tests/fixtures/tpbot/juniper.json")).unwrap();
+        assert_eq!(r, vec![names(&["Alice", "Bob"]), names(&["Carol"]), vec![]]);
     }
-    return (result, not_found);
-}
\ No newline at end of file
+
+    #[test]
+    fn sesh() {
+        let r = parse_fixture("Sesh", include_str!("../tests/fixtures/tpbot/sesh.json")).unwrap();
+        assert_eq!(r, vec![names(&["<@100000000000000001>", "<@!100000000000000002>", "Carol"]), vec![], names(&["Dave"])]);
+        assert_eq!(parse_user_mention(&r[0][1]), Some(UserId::new(100000000000000002)));
+    }
+
+    #[test]
+    fn raid_helper() {
+        let r = parse_fixture("Raid-Helper", include_str!("../tests/fixtures/tpbot/raid_helper.json")).unwrap();
+        assert_eq!(r, vec![names(&["Alice", "Bob", "Carol", "Eve"]), names(&["Frank"]), names(&["Dave"])]);
+    }
+
+    #[test]
+    fn custom_options() {
+        let bot: BotConfig = toml::from_str(r#"
+            name = "Roles"
+            id = 1
+            format = "carl"
+            emojis = ["🛡️", "💚", "⚔️", "❌"]
+            labels = ["Tank", "Healer", "DPS", "Can't come"]
+            kinds = ["accepted", "accepted", "accepted", "declined"]
+        "#).unwrap();
+        let embed: Embed = serde_json::from_str(r#"{"fields": [
+            {"name": "🛡️ Tank (1)", "value": "Alice"},
+            {"name": "⚔️ DPS (2)", "value": "Bob\nCarol"},
+            {"name": "❌ Can't come (1)", "value": "Dave"}
+        ]}"#).unwrap();
+        let r = parse_tp_bot_embed(&bot, &embed, "en").unwrap();
+        assert_eq!(r, vec![names(&["Alice"]), vec![], names(&["Bob", "Carol"]), names(&["Dave"])]);
+        let options = bot.options();
+        assert_eq!(options[1].label, "Healer");
+        assert_eq!(options[3].kind, crate::poll_sources::OptionKind::Declined);
+    }
+
+    #[test]
+    fn embed_without_poll_fields() {
+        let embed = r#"{"title": "Announcement", "fields": [{"name": "Info", "value": "Nothing to vote on"}]}"#;
+        assert!(parse_fixture("Apollo", embed).is_err());
+        assert!(parse_fixture("Raid-Helper", embed).is_err());
+    }
+
+    fn member(id: u64, nick: Option<&str>, global_name: Option<&str>, username: &str) -> MemberNames {
+        MemberNames {
+            user_id: UserId::new(id),
+            nick: nick.map(|n| n.to_string()),
+            global_name: global_name.map(|n| n.to_string()),
+            username: username.to_string(),
+        }
+    }
+
+    // name shapes seen in Apollo, Pancake and Raid-Helper polls
+    fn corpus() -> Vec<MemberNames> {
+        vec![
+            member(1, Some("Alice"), Some("Alice W."), "alice_w"),
+            member(2, None, Some("Bob_the_builder"), "bobtb"),
+            member(3, Some("Dave"), Some("🐉 Dave"), "dave99"),
+            member(4, None, Some("Zoë"), "zoe.k"),
+            member(5, Some("Женя"), None, "zhenya"),
+            member(6, Some("Charlotte"), None, "charlie"),
+            member(7, Some("Sam"), None, "sam_one"),
+            member(8, Some("Sam"), None, "sam_two"),
+            member(9, Some("[GLD] Frank ⚔️"), Some("Frank"), "frankie"),
+            member(10, Some("Lukas"), None, "lukas"),
+            member(11, Some("Lucas"), None, "lucas"),
+            member(12, Some("Ёжик"), None, "hedgehog"),
+        ]
+    }
+
+    fn found(name: &str) -> (u64, MatchStage) {
+        match resolve_name(name, &corpus()) {
+            Resolution::Found(m) => (m.user_id.get(), m.stage),
+            r => panic!("{name}: {r:?}"),
+        }
+    }
+
+    #[test]
+    fn exact_names() {
+        assert_eq!(found("Alice"), (1, MatchStage::Nick));
+        assert_eq!(found("Alice W."), (1, MatchStage::GlobalName));
+        assert_eq!(found("alice_w"), (1, MatchStage::Username));
+        assert_eq!(found("Bob\\_the\\_builder"), (2, MatchStage::GlobalName));
+        assert_eq!(found("🐉 Dave"), (3, MatchStage::GlobalName));
+        assert_eq!(found("Женя"), (5, MatchStage::Nick));
+        assert_eq!(found("Frank"), (9, MatchStage::GlobalName));
+        assert_eq!(found("<@4>"), (4, MatchStage::Mention));
+        assert_eq!(resolve_name("<@404>", &corpus()), Resolution::NotFound);
+    }
+
+    #[test]
+    fn normalised_names() {
+        assert_eq!(found("alice"), (1, MatchStage::Normalised));
+        assert_eq!(found("Dave 🐉"), (3, MatchStage::Normalised));
+        assert_eq!(found("Zoe"), (4, MatchStage::Normalised));
+        assert_eq!(found("ЖЕНЯ"), (5, MatchStage::Normalised));
+        assert_eq!(found("**Charlotte**"), (6, MatchStage::Normalised));
+        assert_eq!(found("[GLD]  Frank"), (9, MatchStage::Normalised));
+        assert_eq!(found("Ежик"), (12, MatchStage::Normalised));
+    }
+
+    #[test]
+    fn similar_names() {
+        let m = match resolve_name("Charlote", &corpus()) {
+            Resolution::Found(m) => m,
+            r => panic!("{r:?}"),
+        };
+        assert_eq!((m.user_id.get(), m.stage), (6, MatchStage::EditDistance));
+        assert!(m.confidence > 0.6 && m.confidence < 0.75);
+        // too short to guess
+        assert_eq!(resolve_name("Bib", &corpus()), Resolution::NotFound);
+        assert_eq!(resolve_name("Zed Zedson", &corpus()), Resolution::NotFound);
+        assert_eq!(resolve_name("🎉", &corpus()), Resolution::NotFound);
+    }
+
+    #[test]
+    fn ambiguous_names() {
+        assert_eq!(resolve_name("Sam", &corpus()), Resolution::Ambiguous(MatchStage::Nick, vec![UserId::new(7), UserId::new(8)]));
+        assert_eq!(resolve_name("Lukcas", &corpus()),
+            Resolution::Ambiguous(MatchStage::EditDistance, vec![UserId::new(10), UserId::new(11)]));
+    }
+
+    #[test]
+    fn same_names_are_told_apart() {
+        let names = vec![vec!["Sam".to_string()], vec!["Sam".to_string(), "Alice".to_string()], vec!["Sam".to_string(), "Sam".to_string()]];
+        let (sam1, sam2) = (UserId::new(7), UserId::new(8));
+        let hints = Hints {
+            reacted: vec![[sam2].into_iter().collect(), HashSet::new(), HashSet::new()],
+            channel_role_members: HashSet::new(),
+        };
+        let r = convert_names(names.clone(), &corpus(), &hints);
+        assert_eq!(r.ids, vec![vec![sam2], vec![UserId::new(1)], vec![sam1, sam2]]);
+        assert_eq!(r.told_apart, vec![
+            ("Sam".to_string(), sam2, Tiebreak::Reaction),
+            ("Sam".to_string(), sam1, Tiebreak::AllVoted),
+            ("Sam".to_string(), sam2, Tiebreak::AllVoted),
+        ]);
+        assert_eq!(r.unattributed, vec![UnattributedVoter { name: "Sam".to_string(), option: 1, candidates: vec![sam1, sam2] }]);
+
+        let hints = Hints { reacted: Vec::new(), channel_role_members: [sam1].into_iter().collect() };
+        let r = convert_names(names, &corpus(), &hints);
+        assert_eq!(r.ids[0], vec![sam1]);
+        assert_eq!(r.ids[1], vec![UserId::new(1), sam1]);
+        assert!(r.unattributed.is_empty());
+    }
+
+    #[test]
+    fn confidence_goes_down_with_the_stage() {
+        let confidence = |name: &str| match resolve_name(name, &corpus()) {
+            Resolution::Found(m) => m.confidence,
+            r => panic!("{r:?}"),
+        };
+        let scores = [confidence("Alice"), confidence("Alice W."), confidence("alice_w"), confidence("ALICE"), confidence("Alise")];
+        assert!(scores.windows(2).all(|w| w[0] > w[1]), "{scores:?}");
+    }
+}
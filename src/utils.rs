@@ -1,228 +1,195 @@
-use std::collections::HashMap;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use serenity::all::CacheHttp;
 use serenity::all::ChannelId;
 use serenity::all::ChannelType;
 use serenity::all::CommandInteraction;
 use serenity::all::Context;
+use serenity::all::CreateAttachment;
 use serenity::all::CreateInteractionResponseFollowup;
+use serenity::all::GuildChannel;
 use serenity::all::GuildId;
 use serenity::all::Member;
 use serenity::all::Message;
 use serenity::all::MessageBuilder;
-use serenity::all::PartialChannel;
+use serenity::all::MessageId;
 use serenity::all::Role;
+use serenity::all::RoleId;
+use serenity::all::Timestamp;
 use serenity::all::UserId;
-use serenity::futures::StreamExt;
-use crate::POLL_OPTS;
+use serenity::all::VoiceState;
+use crate::config::{self, OverflowMode};
+use crate::gateway::{DiscordGateway, MESSAGES_PAGE};
+use crate::i18n::{self, tr};
+use crate::mention_pages;
+use crate::poll_sources::{self, OptionKind, PollOption, PollSource, PollVoters};
+use crate::storage::{self, StoredPoll};
 
 #[cfg(feature = "third_party_bots")]
-use {crate::tpbot_utils,
-    crate::tpbot_utils::convert_names_to_ids,
+use {crate::tpbot_utils::{convert_names_to_ids, Hints, Tiebreak},
+    serenity::all::{PermissionOverwriteType, Permissions, ReactionType},
 };
 
-#[cfg(feature = "third_party_bots")]
-pub const SUPPORTED_BOTS: [u64;2]= [crate::BOT_ID_APOLLO, crate::BOT_ID_PANCAKE];  //TODO add own id here
-#[cfg(not(feature = "third_party_bots"))]
-pub const SUPPORTED_BOTS: [u64;0]= [];//and here
-
-
 #[cfg(feature = "poll_creation")]
-use {crate::ReactionChangeType, 
-    serenity::all::EditMessage,
+use {crate::ReactionChangeType,
+    crate::config::OwnPollOption,
     serenity::all::Reaction,
-    serenity::all::ReactionType,
     serenity::all::Mentionable,
 };
 
-pub const LEN_LIMIT_MSG: usize = 1996;
 pub const LEN_LIMIT_UID_MENTION: usize = 23; //<@19symbolshere>
 
 pub enum UserComparison {
-    MembersSelectedOption,
-    MembersNotSelectedOption,
-    MembersSelectedOptionNotInVoice,
+    SelectedOption(OptionQuery),
+    NotSelectedOption,
+    SelectedOptionNotInVoice(OptionQuery, Option<ChannelId>),   //the voice channel or category to look in, if any
 }
 
 
 // Find an active guild thread by its parent_id, will check against the name if given
-pub async fn find_thread_by_parent_id(ctx: &Context, guild_id: &GuildId, parent_id: &ChannelId, thr_name: Option<&String>)
+pub async fn find_thread_by_parent_id(gw: &dyn DiscordGateway, guild_id: &GuildId, parent_id: &ChannelId, thr_name: Option<&String>)
     -> Option<ChannelId>
 {
-    let td = guild_id.get_active_threads(&ctx).await.ok()?;
-    println!("Found {} active threads", td.threads.len());
-    for t in td.threads {
+    let threads = gw.active_threads(*guild_id).await.ok()?;
+    println!("Found {} active threads", threads.len());
+    for t in threads {
         if let Some(tpid) = t.parent_id
         {
             if &tpid == parent_id {
                 if let Some(n) = thr_name {
                     if t.name != *n {continue;}
                 }
-                println!("Found {}", tpid.to_string());
+                println!("Found {}", tpid);
                 return Some(t.id);
             }
         }
     }
-    return None;
+    None
 }
 
 
 // Sends the log_message string to the log thread as a normal message. If there is no log thread, creates one attached to gch_id channel.
 // Names the thread with a number (poll msg id by default)
-pub async fn log_to_thread(ctx: &Context, log_message: &String, g_id: &GuildId, gch_id: &ChannelId, 
+pub async fn log_to_thread(gw: &dyn DiscordGateway, log_message: &str, g_id: &GuildId, gch_id: &ChannelId, 
     thread_number: &String) -> Result<String, serenity::Error>
 {
     let thr_name = format!("log-{}", thread_number);
-    let t_id = match find_thread_by_parent_id(&ctx, g_id, &gch_id, Some(&thr_name)).await
+    let t_id = match find_thread_by_parent_id(gw, g_id, gch_id, Some(&thr_name)).await
     {
         Some(t_id) => t_id,
         None => {
             //creating thread
-            let builder = serenity::builder::CreateThread::new(thr_name)
-            .kind(ChannelType::PrivateThread);
-            let thr = gch_id.create_thread(&ctx, builder).await?;
+            let thr_id = gw.create_thread(*gch_id, thr_name, ChannelType::PrivateThread).await?;
             let thr_msg_text = MessageBuilder::new()
-            .push("Can't find an existing log thread, created a new one.")
+            .push(tr!(gw.guild_locale(*g_id), "log.thread_created"))
             .build();
-            thr.say(&ctx.http, thr_msg_text).await?;
-            thr.id
+            gw.send_message(thr_id, thr_msg_text).await?;
+            thr_id
         },
     };
-    if let Err(why) = t_id.say(&ctx.http, log_message).await {
+    if let Err(why) = gw.send_message(t_id, log_message.to_string()).await {
             println!("Error sending message: {why:?}");
     }
     Ok("".to_string())
 }
 
 
-// given partial channel, either returns its id (if the channel kind is Text) or parent_id (if it's a text Thread)
-// can be used to find a channel with the poll (suitable for the poll)
-pub fn find_suitable_channel (pch: &PartialChannel) -> Option<ChannelId>
-{
-    Some(match pch.kind {
-        ChannelType::Text => pch.id,
-        ChannelType::PrivateThread | ChannelType::PublicThread => {
-            match pch.parent_id {
-                Some(id) => id,
-                _ => return None,
-            }
-        },
-        _ => return None,
-    })
-}
-
-
 // returns guild-specific nickname for a user or none
-pub fn nick_in_from_cache(ctx: &Context, u_id: &UserId, g_id: &GuildId) -> Option<String>{
-    if let Some(cache) = ctx.cache() {
-        if let Some(guild) = g_id.to_guild_cached(cache) {
-            if let Some(member) = guild.members.get(u_id) {
-                return member.nick.clone();
-            }
-        }
+#[cfg(feature = "poll_creation")]
+pub fn nick_in_from_cache(gw: &dyn DiscordGateway, u_id: &UserId, g_id: &GuildId) -> Option<String>{
+    if let Some(member) = gw.cached_member(*g_id, *u_id) {
+        return member.nick;
     }
     println!("No nickname in cache for {}", u_id);
-    return None;
+    None
 }
 
 
-pub fn get_all_members_in_voice_cached(ctx: &Context,  g_id: &GuildId) -> Option<std::collections::HashMap<UserId, serenity::model::voice::VoiceState>>
+pub fn get_all_members_in_voice_cached(gw: &dyn DiscordGateway,  g_id: &GuildId) -> Option<std::collections::HashMap<UserId, serenity::model::voice::VoiceState>>
 {
-    if let Some(g) = g_id.to_guild_cached(&ctx)
-    {
-        return Some(g.voice_states.clone());
-    } else {
-        println!("Can't get guild from cache.");
-    }
-    return None;
+    gw.voice_states(*g_id)
 }
 
 
-pub fn get_members_from_channelid_cached(ctx: &Context, ch_id: &ChannelId, g_id: &GuildId)
-    -> Result<Vec<Member>, String>
+// Who counts as in voice: not in the AFK channel, not deafened (unless the config says otherwise),
+// in the given voice channel or category, else in the server's event channels from the config, else anywhere
+// None if the guild is not in the cache
+pub fn get_members_in_voice(gw: &dyn DiscordGateway, g_id: GuildId, scope: Option<ChannelId>) -> Option<HashSet<UserId>>
 {
-    if let Some(g) = g_id.to_guild_cached(&ctx)
-    {
-        if let Some(g_ch) = g.channels.get(ch_id)
-        {
-            match g_ch.members(&ctx) {
-                Ok(result) => return Ok(result),
-                Err(e) => {println!("get_members_from_channel_cached error: {e}"); return Err(e.to_string());},
-            }
-        } else {
-            println!("Can't get guild channel from cache.");
-            return Err("Can't get guild channel from cache.".to_string());
-        }
+    let states = gw.voice_states(g_id)?;
+    let cfg = &config::get().voice;
+    let picked: Vec<ChannelId> = match scope {
+        Some(ch_id) => vec![ch_id],
+        None => config::get().event_channels(g_id.get()).iter().map(|id| ChannelId::new(*id)).collect(),
+    };
+    let channels = if picked.is_empty() {
+        None
     } else {
-        println!("Can't get guild from cache.");
-        return Err("Can't get guild from cache.".to_string());
-    }
+        Some(voice_channels_in(&gw.voice_channels(g_id), &picked))
+    };
+    let afk = if cfg.exclude_afk {gw.afk_channel(g_id)} else {None};
+    Some(filter_voice_states(&states, afk, cfg.count_deafened, channels.as_ref()))
 }
 
+// the picked channels and the voice channels of the picked categories
+fn voice_channels_in(voice_channels: &[GuildChannel], picked: &[ChannelId]) -> HashSet<ChannelId> {
+    let mut channels: HashSet<ChannelId> = picked.iter().copied().collect();
+    channels.extend(voice_channels.iter()
+        .filter(|ch| ch.parent_id.is_some_and(|p| picked.contains(&p)))
+        .map(|ch| ch.id));
+    channels
+}
 
-pub async fn get_members_from_channelid(ctx: &Context, ch_id: &ChannelId)
-    -> Result<Vec<Member>, String>
-{
-    let Ok(ch) = ch_id.to_channel(&ctx).await else {
-        return Err("Can't get channel from channel_id.".to_string());
-    };
-    let Some(g_ch) = ch.guild() else {
-         return Err("Can't get guild channel from channel.".to_string());
-    };
-    match g_ch.members(&ctx) {
-        Ok(members)=> return Ok(members),
-        Err(e) => return Err(format!("Can't get members from guild channel: {}", e.to_string())),
-    }
+fn filter_voice_states(
+    states: &HashMap<UserId, VoiceState>,
+    afk: Option<ChannelId>,
+    count_deafened: bool,
+    channels: Option<&HashSet<ChannelId>>,
+) -> HashSet<UserId> {
+    states.iter()
+        .filter(|(_, v)| match v.channel_id {
+            Some(ch_id) => Some(ch_id) != afk && channels.is_none_or(|c| c.contains(&ch_id)),
+            None => false,
+        })
+        .filter(|(_, v)| count_deafened || !(v.deaf || v.self_deaf))
+        .map(|(u, _)| *u)
+        .collect()
 }
 
 
-// find the last own message in the channel
-pub async fn find_last_own_message(ctx: &Context, ch_id: ChannelId) -> Option<Message>
+pub async fn get_members_from_channelid(gw: &dyn DiscordGateway, ch_id: &ChannelId)
+    -> Result<Vec<Member>, String>
 {
-    let own_id = ctx.cache.current_user().id;
-    let mut messages = ch_id.messages_iter(&ctx).boxed();
-    while let Some(message_result) = messages.next().await {
-        match message_result {
-            Ok(msg) => if msg.author.id == own_id {return Some(msg)},
-            Err(error) => {
-                println!("Error getting last own message: {}", error);
-                return  None;
-            }
-        }
+    let Some(g_ch) = gw.guild_channel(*ch_id).await else {
+         return Err("Can't get guild channel from channel_id.".to_string());
+    };
+    match gw.channel_members(&g_ch) {
+        Ok(members)=> Ok(members),
+        Err(e) => Err(format!("Can't get members from guild channel: {}", e)),
     }
-    return None;
 }
 
 
-// returns true if the user in question has at least one common role with ourselves in the guild
-// returns false otherwise
-pub async fn do_we_have_to_listen_to_this_guy(ctx: &Context, command: &CommandInteraction) -> bool
+// goes back through the channel's messages until one of them matches
+pub async fn find_last_message(gw: &dyn DiscordGateway, ch_id: ChannelId, matches: impl Fn(&Message) -> bool + Send)
+    -> Result<Option<Message>, serenity::Error>
 {
-    //get own roles
-    if let Some(member) = &command.member {
-        let g_id = member.guild_id;
-        let own_id = ctx.cache.current_user().id;
-        // Get own roles in this guild
-        match g_id.member(&ctx, own_id).await {
-            Ok(own_member) => {
-                // Check if there's any common role
-                for role_id in &member.roles {
-                    if own_member.roles.contains(role_id) {
-                        return true;
-                    }
-                }
-            },
-            Err(e) => println!("Error fetching own member {e} within the guild {g_id}"),
+    let mut before: Option<MessageId> = None;
+    loop {
+        let page = gw.messages(ch_id, before, MESSAGES_PAGE).await?;
+        if let Some(msg) = page.iter().find(|m| matches(m)) {
+            return Ok(Some(msg.clone()));
+        }
+        if page.len() < MESSAGES_PAGE as usize {
+            return Ok(None);
         }
+        before = page.last().map(|m| m.id);
     }
-    return false;
 }
 
 
 // makes all the checks and decides whether or not to do anything on reaction add event 
 #[cfg(feature = "poll_creation")]
-pub async fn handle_reaction_change(ctx: &Context, reaction: Reaction, change: ReactionChangeType) -> Result<String, serenity::Error>{
+pub async fn handle_reaction_change(gw: &dyn DiscordGateway, reaction: Reaction, change: ReactionChangeType) -> Result<String, serenity::Error>{
     
     use std::time::Instant;
     let now = Instant::now();
@@ -233,24 +200,31 @@ pub async fn handle_reaction_change(ctx: &Context, reaction: Reaction, change: R
     //     Some(m) => m.clone(), //trying the cache first
     //     None => reaction.message(&ctx).await?,
     // };
-    let msg =reaction.message(&ctx).await?;
+    let msg = gw.message(reaction.channel_id, reaction.message_id).await?;
     let msgidstring = msg.id.to_string();
 
-    // ignoring custom reactions
-    let r_emoji = match reaction.emoji {
-        ReactionType::Unicode(s) => s,
-        _ => return Ok("custom reaction".to_string()),
+    // ignoring reactions that aren't poll options
+    let options = poll_sources::own::own_poll_options(&msg);
+    let Some(option_idx) = options.iter().position(|o| poll_sources::own::reaction_of(&o.emoji) == reaction.emoji) else {
+        return Ok("ignored reaction".to_string());
     };
-
-    // ignoring other reactions
-    if r_emoji != crate::REACTION_A.to_string()
-        && r_emoji != crate::REACTION_D.to_string()
-        && r_emoji != crate::REACTION_T.to_string() {
-            return Ok("ignored reaction".to_string())
-        }
+    let r_emoji = options[option_idx].emoji.clone();
  
     // return if the bot is not the author
-    if msg.author.id != ctx.cache.current_user().id { return Ok("Reacted on someone else's message".to_string()) }
+    if msg.author.id != gw.current_user_id() { return Ok("Reacted on someone else's message".to_string()) }
+
+    // closed polls don't take new votes, the new reactions are removed (if we are allowed to)
+    if poll_sources::own::is_closed(gw, &msg).await {
+        if let ReactionChangeType::Add = change {
+            if let Err(e) = gw.delete_reaction(&msg, reaction.user_id, reaction.emoji.clone()).await {
+                println!("Can't remove a reaction from a closed poll: {e}");
+            }
+        }
+        return Ok("Reacted on a closed poll".to_string());
+    }
+
+    // button polls don't use the reactions
+    if crate::poll_buttons::is_button_poll(&msg) { return Ok("Reacted on a button poll".to_string()) }
 
     // get GuildId from reaction (faster)
     let g_id = match reaction.guild_id {
@@ -259,23 +233,38 @@ pub async fn handle_reaction_change(ctx: &Context, reaction: Reaction, change: R
     };
 
     let u_id_added = match change {
-        ReactionChangeType::ADD => reaction.user_id,
+        ReactionChangeType::Add => reaction.user_id,
         _ => None,        
     };
 
-    let r_emoji_char = r_emoji.chars().next();
+    if let Some(store) = gw.poll_store().await {
+        let change_str = match change {
+            ReactionChangeType::Add => "add",
+            ReactionChangeType::Remove => "remove",
+            ReactionChangeType::RemoveEmoji => "remove_emoji",
+        };
+        let user_id = match change {
+            ReactionChangeType::RemoveEmoji => None,
+            _ => reaction.user_id,
+        };
+        if let Err(e) = store.record_poll(&stored_poll_from_message(&msg, g_id, &poll_sources::own::OwnPoll))
+            .and_then(|_| store.record_vote_change(msg.id, user_id, option_idx, change_str, Timestamp::now().unix_timestamp())) {
+            println!("handle_reaction_change: store error {e}");
+        }
+    }
 
-    println!("edit_msg_with_reactions: {}", edit_msg_with_reactions(&ctx, msg, &g_id, u_id_added, r_emoji_char).await?); //TODO run concurrently with the rest of this fn
+    println!("edit_msg_with_reactions: {}", edit_msg_with_reactions(gw, msg, &g_id, u_id_added, Some(option_idx), None).await?); //TODO run concurrently with the rest of this fn
     
         
     // name the user that reacted
+    let gloc = gw.guild_locale(g_id);
     let user_string = match reaction.user_id {
         Some(r_user_id) => {
-            let r_u = r_user_id.to_user(&ctx).await?; //uses the cache first
+            let r_u = gw.user(r_user_id).await?; //uses the cache first
             // get their nickname 
             let r_name = (r_u.display_name()).to_string();
             // try for server-specific
-            let r_g_name = match nick_in_from_cache(&ctx, &r_user_id, &g_id)
+            let r_g_name = match nick_in_from_cache(gw, &r_user_id, &g_id)
             {
                 Some(n) => format!(" ({n})"),
                 None => "".to_string(),
@@ -283,30 +272,30 @@ pub async fn handle_reaction_change(ctx: &Context, reaction: Reaction, change: R
             let u_mention = r_user_id.mention();
             format!("{r_name}{r_g_name} `{u_mention}`").to_string()
         },
-        None => "Someone (no user_id)".to_string(),
+        None => tr!(gloc, "log.someone"),
     };
 
     let log_message = match change {
-        ReactionChangeType::ADD => format!("{user_string} reacted with {r_emoji}"),
-        ReactionChangeType::REMOVE => format!("{user_string} removed {r_emoji}"),
-        ReactionChangeType::REMOVEEMOJI => format!("{user_string} removed emoji {r_emoji}"),
-        //_ => format!("{user_string} did something else with {r_emoji}"),        
+        ReactionChangeType::Add => tr!(gloc, "log.reacted", user = user_string, emoji = r_emoji),
+        ReactionChangeType::Remove => tr!(gloc, "log.reaction_removed", user = user_string, emoji = r_emoji),
+        ReactionChangeType::RemoveEmoji => tr!(gloc, "log.emoji_removed", user = user_string, emoji = r_emoji),
     };
-    log_to_thread(&ctx, &log_message, &g_id, &reaction.channel_id, &msgidstring).await?;
+    log_to_thread(gw, &log_message, &g_id, &reaction.channel_id, &msgidstring).await?;
     
     let elapsed = now.elapsed();
     println!("handle_reaction_change(): {:.2?}", elapsed);
 
-    Ok(format!("{log_message}"))
+    Ok(log_message)
 }
 
 
 // replaces the contents of the message with lists of users who reacted to this message with predefined reactions
-// if supplied with both UserId and the reaction they added, removes the user from other reaction lists 
+// if supplied with both UserId and the option they reacted with, removes the user from other reaction lists 
 // and removes corresponding emoji reactions from the message
+// closed_at marks the poll as closed in the header (the final update of a closed poll)
 #[cfg(feature = "poll_creation")]
-async fn edit_msg_with_reactions(ctx: &Context, mut msg: Message, g_id: &GuildId, u_id_added: Option<UserId>, 
-    added_reaction: Option<char>) -> Result<String, serenity::Error> {
+pub async fn edit_msg_with_reactions(gw: &dyn DiscordGateway, msg: Message, g_id: &GuildId, u_id_added: Option<UserId>, 
+    added_option: Option<usize>, closed_at: Option<i64>) -> Result<String, serenity::Error> {
     
     use std::time::Instant;
     let now = Instant::now();
@@ -315,71 +304,70 @@ async fn edit_msg_with_reactions(ctx: &Context, mut msg: Message, g_id: &GuildId
     // ✅ Accepted (14):
     // Nickname1
     // ServerNick2
-    async fn create_text_for_reaction(ctx: &Context, msg: &Message, react: char, react_descr: String, own_id: &UserId, 
-        g_id: &GuildId, u_id_reacted: Option<UserId>, added_reaction: Option<char>)
-        -> Result<String, serenity::Error>
+    // voted_for is the user who has just picked an option and that option
+    async fn create_text_for_reaction(gw: &dyn DiscordGateway, msg: &Message, option: &OwnPollOption, option_idx: usize, own_id: &UserId, 
+        g_id: &GuildId, voted_for: Option<(UserId, usize)>)
+        -> Result<(String, Vec<String>, Vec<UserId>), serenity::Error>
     {
-        let users_p = msg.reaction_users(&ctx, react, Some(100u8), None).await?;
+        let react = poll_sources::own::reaction_of(&option.emoji);
+        let users_p = gw.reaction_users(msg, react.clone()).await?;
         let mut cnt = 0;
-        let mut text= String::from("");
+        let mut voted: Vec<UserId> = Vec::new();
+        let mut names: Vec<String> = Vec::new();
         for u in &users_p {
             if &u.id == own_id {continue;} //skipping own reactions
-            if let Some(uidr) = u_id_reacted
+            if let Some((uidr, ao)) = voted_for
             {
-                if let Some(ar) = added_reaction {
-                    if u.id == uidr && ar != react {
-                        msg.delete_reaction(&ctx, u_id_reacted, react).await?;
-                        continue;   //preemptively removing the user from other reactions lists
-                    } 
-                }                                               
+                if u.id == uidr && ao != option_idx {
+                    gw.delete_reaction(msg, Some(uidr), react.clone()).await?;
+                    continue;   //preemptively removing the user from other reactions lists
+                }
             }
-            text += MessageBuilder::new()
-            .push_line( match nick_in_from_cache(&ctx, &u.id, g_id)
+            names.push(MessageBuilder::new()
+            .push( match nick_in_from_cache(gw, &u.id, g_id)
             {
                 Some(n) => n.to_string(),
                 None => u.display_name().to_string(),
             })
-            .build().as_str();
+            .build());
+            voted.push(u.id);
             cnt+=1;
         }
-        let cnt_str = {if cnt == 0 {"".to_string()} else {format!(" ({})", cnt)}};
-            
-        let header = MessageBuilder::new()
-        .push(react)
-        .push_bold_line_safe(format!(" __{}__{}:", react_descr, cnt_str))
-        .build();
+        let header = poll_section_header(&option.emoji, &option.label, cnt);
 
-        Ok(format!("{header}{text}"))
+        Ok((header, names, voted))
     }
-    let own_id = ctx.cache.current_user().id;
+    let own_id = gw.current_user_id();
+    let options = poll_sources::own::own_poll_options(&msg);
 
     let now1 = Instant::now();
 
     // concurrency
-    let (text_a, text_d, text_t) = tokio::join!(
-        create_text_for_reaction(ctx, &msg, crate::REACTION_A, "Accepted".to_string(), &own_id, g_id, u_id_added, added_reaction),
-        create_text_for_reaction(ctx, &msg, crate::REACTION_D, "Declined".to_string(), &own_id, g_id, u_id_added, added_reaction),
-        create_text_for_reaction(ctx, &msg, crate::REACTION_T, "Tentative".to_string(), &own_id, g_id, u_id_added, added_reaction),
-    );
-
-    let text_a = text_a?;
-    let text_d = text_d?;
-    let text_t = text_t?;
-
-    let fulltext = MessageBuilder::new()
-    .push_line("_ _")
-    .push_line(text_a)
-    .push_line(text_d)
-    .push_line(text_t)
-    .push_line("_ _")
-    .build();
+    let texts = serenity::futures::future::join_all(options.iter().enumerate()
+        .map(|(i, o)| create_text_for_reaction(gw, &msg, o, i, &own_id, g_id, u_id_added.zip(added_option)))).await;
+
+    let mut sections: Vec<(String, Vec<String>)> = Vec::new();
+    let mut votes: Vec<Vec<UserId>> = Vec::new();
+    for t in texts {
+        let (header, names, voted) = t?;
+        sections.push((header, names));
+        votes.push(voted);
+    }
+
+    // the reactions are the source of truth, keeping the store in sync with them
+    if let Some(store) = gw.poll_store().await {
+        if let Err(e) = store.replace_votes(msg.id, &votes) {
+            println!("edit_msg_with_reactions: store error {e}");
+        }
+    }
+
+    let fulltext = render_poll_text(&sections, closed_at, config::get().limits.message_length, gw.guild_locale(*g_id));
 
     let elapsed1 = now1.elapsed();
-    println!("edit_msg_with_reactions() - a-d-t: {:.2?}", elapsed1);
+    println!("edit_msg_with_reactions() - all options: {:.2?}", elapsed1);
     
     // replace message contents
-    let builder = EditMessage::new().content(fulltext);
-    msg.edit(&ctx, builder).await?;
+    gw.edit_message(msg.channel_id, msg.id, fulltext).await?;
 
     let elapsed = now.elapsed();
     println!("edit_msg_with_reactions(): {:.2?}", elapsed);
@@ -388,19 +376,96 @@ async fn edit_msg_with_reactions(ctx: &Context, mut msg: Message, g_id: &GuildId
 }
 
 
-pub async fn find_last_message_from_user_with_embed(ctx: &Context, ch_id: &ChannelId, u_id: &UserId) -> Option<Message>
+// "✅ **__Accepted__ (14):**"
+#[cfg(any(feature = "poll_creation", test))]
+pub fn poll_section_header(react: &str, react_descr: &str, cnt: usize) -> String
 {
-    let mut messages = ch_id.messages_iter(&ctx).boxed();
-    while let Some(message_result) = messages.next().await {
-        match message_result {
-            Ok(msg) => if msg.author.id == *u_id && msg.embeds.len() > 0 {return Some(msg)},
-            Err(error) => {
-                println!("Error getting last message from the user {}: {}", u_id, error);
-                return  None;
-            }
+    let cnt_str = {if cnt == 0 {"".to_string()} else {format!(" ({})", cnt)}};
+    MessageBuilder::new()
+    .push(react)
+    .push_bold_line_safe(format!(" __{}__{}:", react_descr, cnt_str))
+    .build()
+}
+
+
+// the text of own poll for the given votes
+#[cfg(feature = "poll_creation")]
+pub async fn render_own_poll(ctx: &Context, g_id: &GuildId, votes: &[Vec<UserId>], options: &[OwnPollOption],
+    closed_at: Option<i64>) -> String
+{
+    let mut sections: Vec<(String, Vec<String>)> = Vec::new();
+    for (o, voted) in options.iter().zip(votes) {
+        let mut names: Vec<String> = Vec::new();
+        for u_id in voted {
+            names.push(voter_name(ctx, g_id, u_id).await);
         }
+        sections.push((poll_section_header(&o.emoji, &o.label, voted.len()), names));
     }
-    return None;
+    render_poll_text(&sections, closed_at, config::get().limits.message_length, i18n::guild_locale(ctx, *g_id))
+}
+
+
+// server nickname or display name, from the cache if possible
+#[cfg(feature = "poll_creation")]
+pub async fn voter_name(ctx: &Context, g_id: &GuildId, u_id: &UserId) -> String
+{
+    if let Some(g) = g_id.to_guild_cached(&ctx) {
+        if let Some(m) = g.members.get(u_id) {
+            return m.display_name().to_string();
+        }
+    }
+    match g_id.member(&ctx, *u_id).await {
+        Ok(m) => m.display_name().to_string(),
+        Err(e) => {
+            println!("Can't get member {u_id}: {e}");
+            format!("<@{u_id}>")
+        },
+    }
+}
+
+
+// Renders the poll message: a header and the voters' names for every option, marked as closed if closed_at is given
+// One name per line if everything fits, comma-separated if not, and if even that is too long
+// every option gets an equal share of the message and the rest of the names is counted as "+N more"
+#[cfg(feature = "poll_creation")]
+pub fn render_poll_text(sections: &[(String, Vec<String>)], closed_at: Option<i64>, limit: usize, loc: &str) -> String
+{
+    // closed polls say so in the first line
+    let top = match closed_at {
+        Some(t) => format!("{} <t:{t}:f>\n", tr!(loc, "poll.closed")),
+        None => "_ _\n".to_string(),
+    };
+    let render = |separator: &str, max_section_len: Option<usize>| -> String {
+        let mut text = top.clone();
+        for (header, names) in sections {
+            let mut section = header.clone();
+            for (i, n) in names.iter().enumerate() {
+                if let Some(max) = max_section_len {
+                    let more = tr!(loc, "poll.more", n = names.len() - i);
+                    // leaving room for the "+N more" of the next names
+                    let reserve = if i + 1 < names.len() {separator.chars().count() + more.chars().count() + 5} else {0};
+                    if section.chars().count() + n.chars().count() + separator.chars().count() + reserve > max {
+                        section += &more;
+                        break;
+                    }
+                }
+                section += n;
+                section += if i + 1 < names.len() {separator} else {"\n"};
+            }
+            if !section.ends_with('\n') {section.push('\n');}
+            text += &section;
+            text.push('\n');
+        }
+        text += "_ _\n";
+        text
+    };
+
+    let text = render("\n", None);
+    if text.chars().count() <= limit {return text;}
+    let text = render(", ", None);
+    if text.chars().count() <= limit {return text;}
+    let max_section_len = (limit.saturating_sub(top.chars().count() + 4) / sections.len().max(1)).saturating_sub(1);
+    render(", ", Some(max_section_len))
 }
 
 
@@ -414,244 +479,991 @@ pub async fn send_ephemeral_followup(ctx: &Context, text: &String, ci: &CommandI
 }
 
 
-// sends up to 9 follow-up ephemeral messages containing uids formatted as code with preceding text message
-pub async fn send_ephemeral_followups_with_uids(ctx: &Context, text: &String, uids: &Vec<UserId>, ci: &CommandInteraction) {
-    fn join_uids(uids: &[UserId]) -> String{
-        let mut res = String::new();
-        for uid in uids {
-            res += format!("<@{}>", uid.to_string()).as_str();
+// sends up to limits.max_followups (9 by default) follow-up ephemeral messages containing uids formatted as code with preceding text message
+// longer lists are sent the limits.overflow way
+pub async fn send_ephemeral_followups_with_uids(ctx: &Context, text: &String, uids: &[UserId], ci: &CommandInteraction) {
+    let limits = &config::get().limits;
+    let Some(chunks) = mention_chunks(text.len(), uids.len(), limits.message_length) else {
+        println!("limits.message_length is too small");
+        return;
+    };
+    if chunks.len() > limits.max_followups {
+        match limits.overflow {
+            OverflowMode::Attachment => send_uids_as_attachment(ctx, text, uids, ci).await,
+            OverflowMode::Pages => mention_pages::send(ctx, text, uids, ci).await,
         }
-        return res;
-    }
-    //1st msg
-    let num_mentions_possible = (LEN_LIMIT_MSG - text.len()).saturating_sub(6) / LEN_LIMIT_UID_MENTION;
-    if num_mentions_possible <= 0 {println!("LEN_LIMIT_MSG is too small"); return;}
-    let mut num_mentions_sent = num_mentions_possible.clamp(0, uids.len());
-    let followup_msg_1 = CreateInteractionResponseFollowup::new()
-        .content(format!("{text}```{}```", join_uids(&uids[0..num_mentions_sent])))
-        .ephemeral(true);
-    if let Err(why) = ci.create_followup(&ctx.http, followup_msg_1).await {
-        println!("Cannot respond to slash command: {why}");
         return;
     }
-    let mut msgs_sent = 1;
-    let uids_fit_in_one_message = LEN_LIMIT_MSG.saturating_sub(6) / LEN_LIMIT_UID_MENTION;
 
-    //other messages if needed
-    while num_mentions_sent < uids.len() && msgs_sent < 9 {
-        let num_mentions_sent_new = (num_mentions_sent + uids_fit_in_one_message).clamp(0, uids.len());
+    let mut num_mentions_sent = 0;
+    for (i, n) in chunks.iter().enumerate() {
+        let mentions = join_mentions(&uids[num_mentions_sent..num_mentions_sent + n]);
+        let content = if i == 0 {format!("{text}```{mentions}```")} else {format!("```{mentions}```")};
         let followup_msg = CreateInteractionResponseFollowup::new()
-            .content(format!("```{}```", join_uids(&uids[num_mentions_sent..num_mentions_sent_new])))
+            .content(content)
             .ephemeral(true);
         if let Err(why) = ci.create_followup(&ctx.http, followup_msg).await {
             println!("Cannot respond to slash command: {why}");
             return;
         }
-        num_mentions_sent = num_mentions_sent_new;
-        msgs_sent += 1;
+        num_mentions_sent += n;
     }
 }
 
+// the whole list in a text file, one mention per line
+async fn send_uids_as_attachment(ctx: &Context, text: &String, uids: &[UserId], ci: &CommandInteraction) {
+    let file: String = uids.iter().map(|u| format!("<@{u}>\n")).collect();
+    let followup_msg = CreateInteractionResponseFollowup::new()
+        .content(format!("{text}\n{}", tr!(i18n::command_locale(ci), "mentions.attached", n = uids.len())))
+        .add_file(CreateAttachment::bytes(file.into_bytes(), "members.txt"))
+        .ephemeral(true);
+    if let Err(why) = ci.create_followup(&ctx.http, followup_msg).await {
+        println!("Cannot respond to slash command: {why}");
+    }
+}
 
-pub async fn compare_channel_members_to_poll_and_respond(
-    ctx: &Context, 
-    ci: &CommandInteraction, 
-    g_id: GuildId, 
-    comp_type: UserComparison,
-    comp_option: Option<usize>,
-    limit_to_role: Option<&Role>,
-) {
-    // get all non-bot users from the channel
-    //let non_bots_vec: Vec<Member> = match get_members_from_channelid_cached(ctx, &ci.channel_id, &g_id) {
-    let mut non_bots_vec: Vec<Member> = match get_members_from_channelid(ctx, &ci.channel_id).await {
-        Ok(mv) => mv.into_iter()
-                                .filter(|m| m.user.bot==false)
-                                .collect(),
-        Err(e) => {
-            send_ephemeral_followup(ctx,&format!("Can't get members from this channel: {}", e), ci).await; return;
-        },
-    };
+pub fn join_mentions(uids: &[UserId]) -> String {
+    uids.iter().map(|u| format!("<@{u}>")).collect()
+}
 
-    // get message with the poll
-    let msg = match find_last_message_from_supported_bot_with_embed(ctx, &ci.channel_id).await {
-        Some(m) => m,
-        None => {
-            send_ephemeral_followup(ctx,&"Poll not found!".to_string(), ci).await; return;
-        },
+// how many mentions go in each message: the first one starts with the text, all of them have the mentions
+// in a code block (6 more characters), None if not even one mention fits in the first message
+pub fn mention_chunks(text_len: usize, n_uids: usize, len_limit_msg: usize) -> Option<Vec<usize>> {
+    let first = len_limit_msg.saturating_sub(text_len).saturating_sub(6) / LEN_LIMIT_UID_MENTION;
+    let other = len_limit_msg.saturating_sub(6) / LEN_LIMIT_UID_MENTION;
+    if first == 0 {
+        return None;
+    }
+    let mut chunks = vec![first.min(n_uids)];
+    let mut left = n_uids - chunks[0];
+    while left > 0 {
+        let n = other.min(left);
+        chunks.push(n);
+        left -= n;
+    }
+    Some(chunks)
+}
+
+
+// Describes the poll message the way it is kept in the store
+pub fn stored_poll_from_message(msg: &Message, g_id: GuildId, source: &dyn PollSource) -> StoredPoll
+{
+    StoredPoll {
+        message_id: msg.id,
+        guild_id: g_id,
+        channel_id: msg.channel_id,
+        source_bot: msg.author.id,
+        created_at: msg.timestamp.unix_timestamp(),
+        options: source.options(msg).into_iter().map(|o| o.label).collect(),
+    }
+}
+
+
+// Keeps only the voters who are among channel_members, lists the rest in the returned warning (might be empty)
+fn keep_channel_members(voted: Vec<Vec<UserId>>, options: &[PollOption], channel_members: &[Member], loc: &str) -> (Vec<Vec<UserId>>, String)
+{
+    let member_ids: HashSet<UserId> = channel_members.iter().map(|m| m.user.id).collect();
+    let mut result: Vec<Vec<UserId>> = vec![Vec::new(); voted.len()];
+    let mut left = MessageBuilder::new();
+    let mut left_cnt = 0;
+    for i in 0..voted.len() {
+        for u in &voted[i] {
+            if member_ids.contains(u) {
+                result[i].push(*u);
+            } else {
+                let label = options.get(i).map(|o| o.label.as_str()).unwrap_or_default();
+                left.push_safe(format!("{label}: ")).mention(u).push_line("");
+                left_cnt += 1;
+            }
+        }
+    }
+    let warn = match left_cnt {
+        0 => String::new(),
+        _ => tr!(loc, "results.left_channel", n = left_cnt, members = left.build()),
     };
+    (result, warn)
+}
 
-    let mut poll_responses: [Vec<UserId>; 3] = Default::default(); // poll results end up here
-    let mut warn_reply = String::new(); //any warnings to present to the command user should be added here
 
-    // get all users from poll results
-    // could produce a message we want to show the user if something's wrong with the results
-    if msg.id.get() == crate::BOT_ID_POLLBOT {
-        //parse own msg 
-        //check if someone left the channel here (since for 3rd party it's being done in convert_names_to_ids)
-        send_ephemeral_followup(ctx,&"Parsing own polls is not supported yet!".to_string(), ci).await; return; 
-    } else {
-        //parse 3rd party bot msg
-        #[cfg(feature = "third_party_bots")]
-        match tpbot_utils::parse_tp_bot_poll(&msg) {
-            Ok(names_arr) => {
-                let mut same_names = MessageBuilder::new();
-                let mut member_name_map: HashMap<String, Member>= HashMap::new();
-                for m in &non_bots_vec { //can't do this by chaining .map and .collect sadly
-                    if let Some(old_m) = member_name_map.insert(m.display_name().to_string(), m.clone()) { 
-                        same_names.mention(&old_m).push(" and ".to_string()).mention(m);
-                    }
-                }
-                let same_names = same_names.build();
-                if same_names.len() > 0 {
-                    warn_reply += format!("The following members have identical display names:\n{same_names}\n").as_str();
+// Matches the voters' names against the channel members' names
+// Returns poll results as UserIds, a message with user-presentable warnings (might be empty) and the votes that could not be attributed
+#[cfg(feature = "third_party_bots")]
+async fn match_names_to_members(gw: &dyn DiscordGateway, msg: &Message, g_id: GuildId, names_arr: Vec<Vec<String>>, options: &[PollOption],
+    channel_members: &[Member], loc: &str) -> (Vec<Vec<UserId>>, String, Vec<UnattributedVoter>)
+{
+    let mut converted = convert_names_to_ids(names_arr.clone(), channel_members, &Hints::default());
+    // looking closer only if some members go by the same name
+    if !converted.unattributed.is_empty() {
+        let hints = name_hints(gw, msg, g_id, channel_members).await;
+        converted = convert_names_to_ids(names_arr, channel_members, &hints);
+    }
+
+    let mut warn_reply = String::new();
+    let mentions = |uids: &[UserId]| uids.iter().map(|u| format!("<@{u}>")).collect::<Vec<String>>().join(", ");
+    if !converted.told_apart.is_empty() {
+        let lines: Vec<String> = converted.told_apart.iter()
+            .map(|(n, u, how)| {
+                let how = match how {
+                    Tiebreak::AllVoted => tr!(loc, "tiebreak.all_voted"),
+                    Tiebreak::Reaction => tr!(loc, "tiebreak.reaction"),
+                    Tiebreak::ChannelRole => tr!(loc, "tiebreak.channel_role"),
+                };
+                format!("{n} → <@{u}> ({how})")
+            })
+            .collect();
+        warn_reply += &tr!(loc, "results.names_told_apart", names = lines.join("\n"));
+        warn_reply += "\n";
+    }
+    if !converted.unattributed.is_empty() {
+        let lines: Vec<String> = converted.unattributed.iter()
+            .map(|v| format!("{} ({}): {}", v.name, options.get(v.option).map_or("?", |o| o.label.as_str()), mentions(&v.candidates)))
+            .collect();
+        warn_reply += &tr!(loc, "results.unattributed", names = lines.join("\n"));
+        warn_reply += "\n";
+    }
+    if !converted.uncertain.is_empty() {
+        let lines: Vec<String> = converted.uncertain.iter()
+            .map(|(n, m)| format!("{n} → <@{}> ({:.0}%)", m.user_id, m.confidence * 100.0))
+            .collect();
+        warn_reply += &tr!(loc, "results.names_uncertain", names = lines.join("\n"));
+        warn_reply += "\n";
+    }
+    if !converted.not_found.is_empty() {
+        warn_reply += &tr!(loc, "results.names_not_found", names = converted.not_found.join("\n"));
+    }
+    (converted.ids, warn_reply, converted.unattributed)
+}
+
+// who reacted to the bot's message with every option's emoji, and who has a role the channel is opened to
+#[cfg(feature = "third_party_bots")]
+async fn name_hints(gw: &dyn DiscordGateway, msg: &Message, g_id: GuildId, channel_members: &[Member]) -> Hints
+{
+    let mut hints = Hints::default();
+    let emojis = config::get().bot(msg.author.id.get()).map(|b| b.emojis.clone()).unwrap_or_default();
+    for emoji in &emojis {
+        let same = |r: &ReactionType| r.to_string().trim_end_matches('\u{fe0f}') == emoji.trim_end_matches('\u{fe0f}');
+        let mut reacted = HashSet::new();
+        if let Some(r) = msg.reactions.iter().find(|r| same(&r.reaction_type)) {
+            match gw.reaction_users(msg, r.reaction_type.clone()).await {
+                Ok(users) => reacted.extend(users.iter().map(|u| u.id)),
+                Err(e) => println!("Can't get the reactions of {}: {e}", msg.link()),
+            }
+        }
+        hints.reacted.push(reacted);
+    }
+
+    let roles: HashSet<_> = gw.guild_channel(msg.channel_id).await
+        .map(|ch| ch.permission_overwrites)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|o| o.allow.contains(Permissions::VIEW_CHANNEL))
+        .filter_map(|o| match o.kind {
+            PermissionOverwriteType::Role(r) if r.get() != g_id.get() => Some(r),   // not @everyone
+            _ => None,
+        })
+        .collect();
+    hints.channel_role_members = channel_members.iter()
+        .filter(|m| m.roles.iter().any(|r| roles.contains(r)))
+        .map(|m| m.user.id)
+        .collect();
+    hints
+}
+
+// A vote listed under a name several channel members go by
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnattributedVoter {
+    pub name: String,
+    pub option: usize,
+    pub candidates: Vec<UserId>,
+}
+
+
+// Everything we know about the poll after matching its voters against the members of its channel
+pub struct PollResults {
+    pub msg: Message,
+    pub source_name: String,
+    pub channel_members: Vec<Member>,   // non-bot members who can see the poll
+    pub options: Vec<PollOption>,
+    pub responses: Vec<Vec<UserId>>,    // voters for every option
+    pub unattributed: Vec<UnattributedVoter>,   // the votes that could not be attributed to one member, not in responses
+    pub warnings: String,               // anything the command user should know about (might be empty)
+}
+
+impl PollResults {
+    // one line telling which poll was analysed
+    pub fn describe(&self, loc: &str) -> String {
+        tr!(loc, "results.poll_by", source = self.source_name, link = self.msg.link())
+    }
+
+    // indices of the options the query is about
+    pub fn select(&self, query: &OptionQuery) -> Vec<usize> {
+        match query {
+            OptionQuery::Kind(kind) => (0..self.options.len()).filter(|i| self.options[*i].kind == *kind).collect(),
+            OptionQuery::Name(name) => {
+                let name = name.trim().to_lowercase();
+                match self.options.iter().position(|o| o.label.to_lowercase() == name) {
+                    Some(i) => vec![i],
+                    None => Vec::new(),
                 }
-                let r: String;
-                (poll_responses, r) = convert_names_to_ids(names_arr, &member_name_map);
-                if r.len() > 0 {warn_reply+= format!("{r}\n").as_str();};
-            },
-            Err(e) => {
-                send_ephemeral_followup(ctx, &format!("Failed to parse 3rd party bot's poll:\n{}", e), ci).await; return;
             },
         }
     }
 
+    // voters of the selected options, everyone is listed once
+    pub fn voters(&self, selected: &[usize]) -> Vec<UserId> {
+        let mut result: Vec<UserId> = Vec::new();
+        for i in selected {
+            for u in &self.responses[*i] {
+                if !result.contains(u) {result.push(*u);}
+            }
+        }
+        result
+    }
+
+    // the members who haven't selected anything (the ones who might have, see might_have_voted(), are not counted)
+    pub fn did_not_vote(&self, members: &[Member]) -> Vec<UserId> {
+        let voted: HashSet<&UserId> = self.responses.iter().flatten().collect();
+        let maybe = self.might_have_voted(members);
+        members.iter().map(|m| m.user.id).filter(|u| !voted.contains(u) && !maybe.contains(u)).collect()
+    }
+
+    // the members with no vote of their own who go by the name of a vote that could not be attributed
+    pub fn might_have_voted(&self, members: &[Member]) -> Vec<UserId> {
+        let voted: HashSet<&UserId> = self.responses.iter().flatten().collect();
+        let candidates: HashSet<&UserId> = self.unattributed.iter().flat_map(|v| &v.candidates).collect();
+        members.iter().map(|m| m.user.id).filter(|u| candidates.contains(u) && !voted.contains(u)).collect()
+    }
+
+    // "Tank/Healer/DPS"
+    pub fn labels(&self, selected: &[usize]) -> String {
+        selected.iter().map(|i| self.options[*i].label.as_str()).collect::<Vec<&str>>().join("/")
+    }
+}
+
+
+// Which option(s) a command is about
+pub enum OptionQuery {
+    Kind(OptionKind),   // every option of the kind, e.g. all the "accepted" ones
+    Name(String),       // the option with this label (case-insensitive)
+}
+
+
+// Which poll a command is about
+pub enum PollRef<'a> {
+    LastInChannel,          // the last poll in the channel the command was used in
+    Given(&'a str),         // message link or id given by the user
+    Message(&'a Message),   // the message itself (e.g. the target of a context menu command)
+}
+
+impl<'a> PollRef<'a> {
+    // from the "poll" option of a slash command
+    pub fn from_option(poll_ref: Option<&'a str>) -> PollRef<'a> {
+        match poll_ref {
+            Some(r) => PollRef::Given(r),
+            None => PollRef::LastInChannel,
+        }
+    }
+}
+
+
+// Finds the poll message (by poll_ref if given, the last one in ch_id otherwise)
+// Returns a user-presentable error if there is none
+pub async fn find_poll(gw: &dyn DiscordGateway, g_id: GuildId, ch_id: ChannelId, poll_ref: PollRef<'_>, loc: &str) -> Result<Message, String>
+{
+    match poll_ref {
+        PollRef::Given(r) => resolve_poll_message(gw, g_id, ch_id, r, loc).await,
+        PollRef::Message(m) => Ok(m.clone()),
+        PollRef::LastInChannel => match find_poll_message(gw, &ch_id).await {
+            Some(m) => Ok(m),
+            None => Err(tr!(loc, "poll_ref.not_found")),
+        },
+    }
+}
+
+
+// Finds the poll (by poll_ref if given, the last one in ch_id otherwise), gets its voters and the members of its channel
+// Returns a user-presentable error if something's wrong
+pub async fn get_poll_results(gw: &dyn DiscordGateway, g_id: GuildId, ch_id: ChannelId, poll_ref: PollRef<'_>, loc: &str)
+    -> Result<PollResults, String>
+{
+    // get message with the poll
+    let msg = find_poll(gw, g_id, ch_id, poll_ref, loc).await?;
+
+    // get all non-bot users from the poll's channel
+    let non_bots_vec: Vec<Member> = match get_members_from_channelid(gw, &msg.channel_id).await {
+        Ok(mv) => mv.into_iter()
+                                .filter(|m| !m.user.bot)
+                                .collect(),
+        Err(e) => return Err(tr!(loc, "results.no_members", error = e)),
+    };
+
+    let mut warn_reply = String::new(); //any warnings to present to the command user should be added here
+
+    // get all users from poll results (poll_responses)
+    // could produce a message we want to show the user if something's wrong with the results
+    let own_id = gw.current_user_id();
+    let Some(source) = poll_sources::find_source(&msg, own_id) else {
+        return Err(tr!(loc, "poll_ref.unsupported"));
+    };
+    let options = source.options(&msg);
+    let (poll_responses, unattributed) = match source.extract(gw, &msg, loc).await {
+        Ok((voters, r)) => {
+            if !r.is_empty() {warn_reply+= format!("{r}\n").as_str();};
+            let (mut responses, r, unattributed) = match voters {
+                PollVoters::Ids(ids) => {
+                    let (responses, r) = keep_channel_members(ids, &options, &non_bots_vec, loc);
+                    (responses, r, Vec::new())
+                },
+                #[cfg(feature = "third_party_bots")]
+                PollVoters::Names(names) => match_names_to_members(gw, &msg, g_id, names, &options, &non_bots_vec, loc).await,
+            };
+            if !r.is_empty() {warn_reply+= format!("{r}\n").as_str();};
+            responses.resize(options.len(), Vec::new());
+            (responses, unattributed)
+        },
+        Err(e) => return Err(tr!(loc, "results.parse_failed", source = source.name(), error = e)),
+    };
+
+    Ok(PollResults {
+        msg,
+        source_name: source.name().to_string(),
+        channel_members: non_bots_vec,
+        options,
+        responses: poll_responses,
+        unattributed,
+        warnings: warn_reply,
+    })
+}
+
+
+// What a comparison found: the reply, the members it lists (might be none) and the warnings about the poll
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub text: String,
+    pub uids: Vec<UserId>,
+    pub warnings: String,
+}
+
+
+// Compares the members of the poll's channel (with the role, if given) to the poll's voters
+// Returns a user-presentable error if there is nothing to compare
+pub async fn compare_channel_members_to_poll(
+    gw: &dyn DiscordGateway,
+    g_id: GuildId,
+    ch_id: ChannelId,
+    comp_type: UserComparison,
+    limit_to_role: Option<RoleId>,
+    poll_ref: PollRef<'_>,
+    loc: &str,
+) -> Result<Comparison, String> {
+    let results = get_poll_results(gw, g_id, ch_id, poll_ref, loc).await?;
+    let poll_line = results.describe(loc);
+    let mut non_bots_vec = results.channel_members.clone();
+    let mut warnings = results.warnings.clone();
+
     //role filtering
-    if let Some(role) = limit_to_role {
-        non_bots_vec.retain(|m| m.roles.contains(&role.id)); //might be a bit slow
+    if let Some(role_id) = limit_to_role {
+        non_bots_vec.retain(|m| m.roles.contains(&role_id)); //might be a bit slow
     }
-    
+
     // do a comparison
-    match comp_type {
-        comp_type @ (UserComparison::MembersSelectedOption | UserComparison::MembersSelectedOptionNotInVoice) => {
-            //check comp_option for validity
-            if comp_option.is_none() {println!("comp_option is None"); return};
-            let selected = comp_option.unwrap();
-            if selected > 3 {println!("comp_option > 3"); return};
-            let members_reacted = &poll_responses[selected];
-            let react = POLL_OPTS[selected];
-            let reacted_n = poll_responses[selected].len();
-
-            match comp_type {
-                UserComparison::MembersSelectedOption => {
-                    if members_reacted.len() > 0 {
-                        if ci.locale == "ru" {
-                            send_ephemeral_followups_with_uids(
-                                ctx,
-                                &format!("Пользователи, выбравшие \"{}\" `{}`:", react, reacted_n ),
-                                &members_reacted,
-                                ci).await;
-                        }else{
-                            send_ephemeral_followups_with_uids(
-                                ctx,
-                                &format!("The following members selected \"{}\" `{}`:", react, reacted_n ),
-                                &members_reacted,
-                                ci).await;
-                            }
-                        
-                    } else {
-                        send_ephemeral_followup(
-                            ctx,
-                            &format!("Nobody selected \"{}\".", react),
-                            ci).await;
-                    }
-                },
-                _ => { //should be UserComparison::MembersSelectedOptionNotInVoice only but who knows?
-                    println!("UserComparison::MembersSelectedOptionNotInVoice");
-                    let mut cnt_in_v = 0;
-                    //TODO not from the cache
-                    let in_voice = get_all_members_in_voice_cached(ctx, &g_id).unwrap_or(Default::default());
-                    let mut not_in_voice: Vec<UserId> = Vec::new();
-                    for m in members_reacted {
-                        if in_voice.contains_key(m) {
-                            cnt_in_v +=1;
-                        } else {
-                            not_in_voice.push(m.clone());
-                        }
-                    }
-                    if reacted_n == 0 {
-                        send_ephemeral_followup(
-                            ctx,
-                            &format!("Nobody selected \"{}\".", react),
-                            ci).await;
-                    } else if cnt_in_v == reacted_n {
-                        send_ephemeral_followup(
-                            ctx,
-                            &format!("Everyone's in voice 👌 `{}/{}`", cnt_in_v, reacted_n),
-                            ci).await;
+    let (text, uids) = match &comp_type {
+        UserComparison::SelectedOption(query) | UserComparison::SelectedOptionNotInVoice(query, _) => {
+            let selected = results.select(query);
+            if selected.is_empty() {
+                return Err(match query {
+                    OptionQuery::Name(n) => tr!(loc, "results.no_such_option", poll = poll_line, option = n,
+                        options = results.options.iter().map(|o| o.label.as_str()).collect::<Vec<&str>>().join(", ")),
+                    OptionQuery::Kind(k) => tr!(loc, "results.no_options_of_kind", poll = poll_line,
+                        kind = tr!(loc, &format!("kinds.{}", k.name()))),
+                });
+            }
+            let members_reacted = results.voters(&selected);
+            let react = results.labels(&selected);
+            let reacted_n = members_reacted.len();
+
+            if let UserComparison::SelectedOptionNotInVoice(_, voice_scope) = comp_type {
+                let mut cnt_in_v = 0;
+                let in_voice = get_members_in_voice(gw, g_id, voice_scope).unwrap_or_default();
+                let mut not_in_voice: Vec<UserId> = Vec::new();
+                for m in &members_reacted {
+                    if in_voice.contains(m) {
+                        cnt_in_v +=1;
                     } else {
-                        send_ephemeral_followups_with_uids(
-                            ctx,
-                            &format!("The following members selected \"{}\" and are not present in any of the voice channels right now 🔇 `{}/{}`:",
-                                react,
-                                not_in_voice.len(),
-                                reacted_n),
-                            &not_in_voice,
-                            ci).await;
+                        not_in_voice.push(*m);
                     }
-                },
-            }
-        },
-        UserComparison::MembersNotSelectedOption => {
-            //convert poll result to set
-            let mut voted: HashSet<UserId> = HashSet::new();
-            for i in poll_responses{
-                for r in i {
-                    voted.insert(r); //TODO find a better way to do this
                 }
-            }
-            //for each channel member try to find them in poll results
-            let mut did_not_vote: Vec<UserId> = Vec::new();
-            for m in &non_bots_vec {
-                if !voted.contains(&m.user.id) {
-                    did_not_vote.push(m.user.id.clone());
+                if reacted_n == 0 {
+                    (tr!(loc, "results.nobody_selected", poll = poll_line, option = react), Vec::new())
+                } else if cnt_in_v == reacted_n {
+                    (tr!(loc, "results.everyone_in_voice", poll = poll_line, n = cnt_in_v, of = reacted_n), Vec::new())
+                } else {
+                    (tr!(loc, "results.not_in_voice", poll = poll_line, option = react, n = not_in_voice.len(), of = reacted_n),
+                        not_in_voice)
                 }
+            } else if !members_reacted.is_empty() {
+                (tr!(loc, "results.selected", poll = poll_line, option = react, n = reacted_n), members_reacted)
+            } else {
+                (tr!(loc, "results.nobody_selected", poll = poll_line, option = react), Vec::new())
             }
-            if did_not_vote.len() > 0 {
-                send_ephemeral_followups_with_uids(
-                    ctx,
-                    &format!("The following members haven't selected anything `{}/{}`:", did_not_vote.len(), non_bots_vec.len()),
-                    &did_not_vote,
-                    ci).await;
+        },
+        UserComparison::NotSelectedOption => {
+            let did_not_vote = results.did_not_vote(&non_bots_vec);
+            let maybe = results.might_have_voted(&non_bots_vec);
+            if !maybe.is_empty() {
+                let mentions: Vec<String> = maybe.iter().map(|u| format!("<@{u}>")).collect();
+                warnings = format!("{}\n{warnings}", tr!(loc, "results.might_have_voted", n = maybe.len(), members = mentions.join(", ")));
+            }
+            if !did_not_vote.is_empty() {
+                (tr!(loc, "results.no_vote", poll = poll_line, n = did_not_vote.len(), of = non_bots_vec.len()), did_not_vote)
             } else {
-                 send_ephemeral_followups_with_uids(
-                    ctx,
-                    &format!("Everyone has selected a poll option 👌 `{}/{}`:", non_bots_vec.len(), non_bots_vec.len()),
-                    &did_not_vote,
-                    ci).await;
+                let voted = non_bots_vec.len() - maybe.len();
+                (tr!(loc, "results.everyone_voted", poll = poll_line, n = voted, of = non_bots_vec.len()), Vec::new())
             }
         },
+    };
+    Ok(Comparison { text, uids, warnings })
+}
 
-        _ => return,
-    }
+
+pub async fn compare_channel_members_to_poll_and_respond(
+    ctx: &Context, 
+    ci: &CommandInteraction, 
+    g_id: GuildId, 
+    comp_type: UserComparison,
+    limit_to_role: Option<&Role>,
+    poll_ref: PollRef<'_>,
+) {
+    let loc = i18n::command_locale(ci);
+    let comparison = compare_channel_members_to_poll(ctx, g_id, ci.channel_id, comp_type,
+        limit_to_role.map(|r| r.id), poll_ref, loc).await;
+    let comparison = match comparison {
+        Ok(c) => c,
+        Err(e) => {
+            send_ephemeral_followup(ctx, &e, ci).await; return;
+        },
+    };
 
     // show the user results as a message(s)
-    
-    if warn_reply.len() > 0 {
-            send_ephemeral_followup(ctx,&warn_reply, ci).await; 
+    if !comparison.uids.is_empty() {
+        send_ephemeral_followups_with_uids(ctx, &comparison.text, &comparison.uids, ci).await;
+    } else {
+        send_ephemeral_followup(ctx, &comparison.text, ci).await;
+    }
+    if !comparison.warnings.is_empty() {
+            send_ephemeral_followup(ctx, &comparison.warnings, ci).await; 
     }
 }
 
 
-// Finds the last message with any embed authored by any id from SUPPORTED_BOTS, returns it or None
+// "2025-06-01 20:00" (UTC), "2025-06-01T20:00", unix timestamp or Discord's <t:1748808000:F>
+pub fn parse_event_time(s: &str, loc: &str) -> Result<Timestamp, String>
+{
+    let s = s.trim();
+    let unix = s.strip_prefix("<t:")
+        .map(|t| t.trim_end_matches('>').split(':').next().unwrap_or_default())
+        .unwrap_or(s);
+    if let Ok(secs) = unix.parse::<i64>() {
+        return Timestamp::from_unix_timestamp(secs).map_err(|e| tr!(loc, "time.invalid", time = s, error = e));
+    }
+    let iso = match s.len() {
+        _ if !s.is_ascii() => s.to_string(),
+        16 => format!("{}T{}:00Z", &s[..10], &s[11..]),
+        19 => format!("{}T{}Z", &s[..10], &s[11..]),
+        _ => s.to_string(),
+    };
+    match Timestamp::parse(&iso) {
+        Ok(t) => Ok(t),
+        Err(_) => Err(tr!(loc, "time.unreadable", time = s)),
+    }
+}
+
+
+// Finds the last message any of the poll sources recognises
 // Calls the API
-pub async fn find_last_message_from_supported_bot_with_embed(ctx: &Context, ch_id: &ChannelId) -> Option<Message>
+pub async fn find_last_poll_message(gw: &dyn DiscordGateway, ch_id: &ChannelId) -> Option<Message>
 {
-    let mut messages = ch_id.messages_iter(&ctx).boxed();
-    while let Some(message_result) = messages.next().await {
-        match message_result {
-            Ok(msg) => {
-                if msg.embeds.len() > 0 {
-                    for s_id in SUPPORTED_BOTS {
-                        if msg.author.id.get() == s_id {return Some(msg)}
-                    }
-                }
+    let own_id = gw.current_user_id();
+    match find_last_message(gw, *ch_id, |msg| poll_sources::find_source(msg, own_id).is_some()).await {
+        Ok(Some(msg)) => return Some(msg),
+        Ok(None) => {},
+        Err(error) => {
+            println!("Error getting next message from ch_id {}: {}", ch_id, error);
+            return None;
+        }
+    }
+    println!("No suitable messages found in ch_id {}.", ch_id);
+    None
+}
+
+
+// Finds the last poll in the channel: the newest of the stored one and the ones posted after it
+// (the store misses the polls posted while the bot was offline), goes through the whole channel if the store has nothing
+// Polls found through the API are recorded in the store
+pub async fn find_poll_message(gw: &dyn DiscordGateway, ch_id: &ChannelId) -> Option<Message>
+{
+    let own_id = gw.current_user_id();
+    let store = gw.poll_store().await;
+    let stored = match store.as_ref().map(|s| s.last_poll_in_channel(*ch_id)) {
+        Some(Ok(p)) => p.map(|p| p.message_id),
+        Some(Err(e)) => {println!("find_poll_message: store error {e}"); None},
+        None => None,
+    };
+
+    // going back until a poll or the stored one
+    let is_poll = |msg: &Message| poll_sources::find_source(msg, own_id).is_some();
+    let found = match find_last_message(gw, *ch_id, |msg| is_poll(msg) || stored.is_some_and(|s| msg.id <= s)).await {
+        Ok(msg) => msg.filter(|msg| is_poll(msg)),
+        Err(e) => {println!("Error getting the messages of ch_id {}: {}", ch_id, e); None},
+    };
+    let msg = match (found, stored) {
+        (Some(msg), _) => msg,
+        (None, Some(s)) => match gw.message(*ch_id, s).await {
+            Ok(msg) => return Some(msg),
+            Err(e) => {
+                println!("Stored poll {} is not available anymore: {e}", s);
+                if let Some(Err(e)) = store.as_ref().map(|store| store.forget_poll(s)) {println!("find_poll_message: store error {e}");}
+                find_last_poll_message(gw, ch_id).await?
             },
-            Err(error) => {
-                println!("Error getting next message from ch_id {}: {}", ch_id, error);
-                return None;
+        },
+        (None, None) => return None,
+    };
+    if stored == Some(msg.id) {
+        return Some(msg);
+    }
+    if let (Some(store), Some(g_id), Some(source)) = (&store, msg.guild_id, poll_sources::find_source(&msg, own_id)) {
+        if let Err(e) = store.record_poll(&stored_poll_from_message(&msg, g_id, source)) {
+            println!("find_poll_message: store error {e}");
+        }
+    }
+    Some(msg)
+}
+
+
+// Parses a message link (https://discord.com/channels/guild/channel/message) or a bare message id
+// Returns the guild and the channel if they were in the link
+pub fn parse_message_ref(s: &str) -> Option<(Option<GuildId>, Option<ChannelId>, MessageId)>
+{
+    let s = s.trim().trim_start_matches('<').trim_end_matches('>');
+    if let Ok(id) = s.parse::<u64>() {
+        if id == 0 {return None;}
+        return Some((None, None, MessageId::new(id)));
+    }
+    let path = s.trim_start_matches("https://").trim_start_matches("http://");
+    let path = ["discord.com", "ptb.discord.com", "canary.discord.com", "discordapp.com"].iter()
+        .find_map(|h| path.strip_prefix(h))?;
+    let ids: Vec<&str> = path.strip_prefix("/channels/")?.split('/').collect();
+    if ids.len() != 3 {return None;}
+    let g_id = match ids[0] {
+        "@me" => None,
+        g => Some(GuildId::new(g.parse::<u64>().ok().filter(|id| *id != 0)?)),
+    };
+    let ch_id = ChannelId::new(ids[1].parse::<u64>().ok().filter(|id| *id != 0)?);
+    let m_id = MessageId::new(ids[2].parse::<u64>().ok().filter(|id| *id != 0)?);
+    Some((g_id, Some(ch_id), m_id))
+}
+
+
+// Finds the poll message given by a link or an id within the guild
+// Looks in the linked channel, then in the store, then in ch_hint, then in every text channel of the guild
+// Returns a user-presentable error if there is no such poll
+pub async fn resolve_poll_message(gw: &dyn DiscordGateway, g_id: GuildId, ch_hint: ChannelId, poll_ref: &str, loc: &str)
+    -> Result<Message, String>
+{
+    let Some((link_g_id, link_ch_id, m_id)) = parse_message_ref(poll_ref) else {
+        return Err(tr!(loc, "poll_ref.not_a_link", poll = poll_ref));
+    };
+    if link_g_id.is_some_and(|id| id != g_id) {
+        return Err(tr!(loc, "poll_ref.other_server"));
+    }
+
+    let mut channels: Vec<ChannelId> = Vec::new();
+    if let Some(ch_id) = link_ch_id {channels.push(ch_id);}
+    if let Some(store) = gw.poll_store().await {
+        match store.get_poll(m_id) {
+            Ok(Some(p)) => if p.guild_id == g_id {channels.push(p.channel_id)},
+            Ok(None) => {},
+            Err(e) => println!("resolve_poll_message: store error {e}"),
+        }
+    }
+    channels.push(ch_hint);
+    if link_ch_id.is_none() {
+        channels.extend(gw.guild_channels(g_id));
+    }
+
+    let mut tried: HashSet<ChannelId> = HashSet::new();
+    for ch_id in channels {
+        if !tried.insert(ch_id) {continue;}
+        // the message has to be in this guild
+        match gw.guild_channel(ch_id).await {
+            Some(gc) if gc.guild_id == g_id => {},
+            _ => continue,
+        }
+        let Ok(msg) = gw.message(ch_id, m_id).await else {continue;};
+        let own_id = gw.current_user_id();
+        let Some(source) = poll_sources::find_source(&msg, own_id) else {
+            return Err(tr!(loc, "poll_ref.not_a_poll", link = msg.link()));
+        };
+        if let Some(store) = gw.poll_store().await {
+            if let Err(e) = store.record_poll(&stored_poll_from_message(&msg, g_id, source)) {
+                println!("resolve_poll_message: store error {e}");
             }
         }
+        return Ok(msg);
     }
-    println!("No suitable messages found in ch_id {}.", ch_id);
-    return None;
-}
\ No newline at end of file
+    Err(tr!(loc, "poll_ref.no_message", id = m_id))
+}
+
+
+// Records the message in the store if any of the poll sources recognises it
+pub async fn record_poll_if_supported(ctx: &Context, msg: &Message)
+{
+    let own_id = ctx.cache.current_user().id;
+    let Some(source) = poll_sources::find_source(msg, own_id) else {return;};
+    let Some(g_id) = msg.guild_id else {return;};
+    if let Some(store) = storage::get(ctx).await {
+        match store.record_poll(&stored_poll_from_message(msg, g_id, source)) {
+            Ok(_) => println!("Recorded a poll from {} in ch_id {}", msg.author.id, msg.channel_id),
+            Err(e) => println!("record_poll_if_supported: store error {e}"),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "poll_creation")]
+    #[test]
+    fn long_polls_are_shortened() {
+        let names: Vec<String> = (0..50).map(|i| format!("Member{i}")).collect();
+        let sections: Vec<(String, Vec<String>)> = (0..10)
+            .map(|i| (poll_section_header("✅", &format!("Option {i}"), names.len()), names.clone()))
+            .collect();
+        let text = render_poll_text(&sections, None, 1000, "en");
+        assert!(text.chars().count() <= 1000, "{text}");
+        assert!(text.contains("Member0, Member1"), "{text}");
+
+        // not even the headers fit, every section is just "+N more"
+        let text = render_poll_text(&sections, Some(1_750_000_000), 20, "en");
+        assert_eq!(text.matches(&tr!("en", "poll.more", n = names.len())).count(), 10, "{text}");
+        let text = render_poll_text(&sections[..1], Some(1_750_000_000), 20, "en");
+        assert!(text.contains(&tr!("en", "poll.more", n = names.len())), "{text}");
+    }
+
+    #[test]
+    fn mentions_fit_in_the_message() {
+        // (2000 - 6) / 23 = 86 mentions in a message without text
+        assert_eq!(mention_chunks(0, 86, 2000), Some(vec![86]));
+        assert_eq!(mention_chunks(0, 87, 2000), Some(vec![86, 1]));
+    }
+
+    #[test]
+    fn text_takes_room_in_the_first_message() {
+        // (1996 - 100 - 6) / 23 = 82 in the first one, 86 in the others
+        assert_eq!(mention_chunks(100, 200, 1996), Some(vec![82, 86, 32]));
+        assert_eq!(mention_chunks(100, 0, 1996), Some(vec![0]));
+        assert_eq!(mention_chunks(1996 - 6 - 22, 10, 1996), None);
+        assert_eq!(mention_chunks(1996 - 6 - 23, 10, 1996), Some(vec![1, 9]));
+    }
+
+    #[test]
+    fn large_guild_needs_many_messages() {
+        let chunks = mention_chunks(150, 3000, 1996).unwrap();
+        assert_eq!(chunks.iter().sum::<usize>(), 3000);
+        assert_eq!(chunks.len(), 35);
+        assert!(chunks.len() > config::LimitsConfig::default().max_followups);
+    }
+
+    // a channel with four members (Ann and Dan have a role) and an own poll in it:
+    // Ann and Ben accepted, Cat declined, Dan didn't vote, only Ann is in voice
+    mod end_to_end {
+        use serenity::all::User;
+
+        use super::super::*;
+        use crate::gateway::fake::{self, FakeGateway};
+        use crate::poll_sources::own::reaction_of;
+
+        const GUILD: GuildId = GuildId::new(1);
+        const CHANNEL: ChannelId = ChannelId::new(10);
+        const EMPTY_CHANNEL: ChannelId = ChannelId::new(11);
+        const VOICE: ChannelId = ChannelId::new(20);
+        const POLL: MessageId = MessageId::new(500);
+        const ROLE: RoleId = RoleId::new(50);
+        const OWN_ID: u64 = 100;
+
+        fn users() -> Vec<User> {
+            vec![fake::user(1, "Ann"), fake::user(2, "Ben"), fake::user(3, "Cat"), fake::user(4, "Dan")]
+        }
+
+        fn guild() -> FakeGateway {
+            let mut gw = FakeGateway::new(OWN_ID);
+            let mut members: Vec<Member> = users().into_iter().map(|u| fake::member(GUILD, u)).collect();
+            members[0].roles.push(ROLE);
+            members[3].roles.push(ROLE);
+            gw.add_channel(GUILD, CHANNEL, members);
+            gw.add_channel(GUILD, EMPTY_CHANNEL, Vec::new());
+            gw.join_voice(GUILD, UserId::new(1), VOICE);
+            gw
+        }
+
+        fn message(id: MessageId, author: User, content: &str) -> Message {
+            let mut msg = Message::default();
+            msg.id = id;
+            msg.channel_id = CHANNEL;
+            msg.guild_id = Some(GUILD);
+            msg.author = author;
+            msg.content = content.to_string();
+            msg
+        }
+
+        fn own_poll() -> FakeGateway {
+            let mut gw = guild();
+            let options = config::get().own_poll_options();
+            let own = fake::user(OWN_ID, "pollbot");
+            let content: String = options.iter().map(|o| poll_section_header(&o.emoji, &o.label, 0)).collect();
+            gw.post(message(POLL, own.clone(), &content));
+            // the poll is not the last message in the channel
+            gw.post(message(MessageId::new(600), users()[2].clone(), "see you there"));
+            let u = users();
+            gw.react(POLL, reaction_of(&options[0].emoji), vec![own.clone(), u[0].clone(), u[1].clone()]);
+            gw.react(POLL, reaction_of(&options[1].emoji), vec![own.clone(), u[2].clone()]);
+            gw.react(POLL, reaction_of(&options[2].emoji), vec![own]);
+            gw
+        }
+
+        async fn compare(gw: &FakeGateway, comp_type: UserComparison, role: Option<RoleId>) -> Result<Comparison, String>
+        {
+            compare_channel_members_to_poll(gw, GUILD, CHANNEL, comp_type, role, PollRef::LastInChannel, "en").await
+        }
+
+        fn ids(ids: &[u64]) -> Vec<UserId> {
+            ids.iter().map(|id| UserId::new(*id)).collect()
+        }
+
+        #[tokio::test]
+        async fn members_selected_option() {
+            let gw = own_poll();
+            let c = compare(&gw, UserComparison::SelectedOption(OptionQuery::Kind(OptionKind::Accepted)), None)
+                .await.unwrap();
+            assert_eq!(c.uids, ids(&[1, 2]));
+            assert!(c.text.contains("Accepted"), "{}", c.text);
+            assert_eq!(c.warnings, "");
+
+            let c = compare(&gw, UserComparison::SelectedOption(OptionQuery::Name("tentative".to_string())), None)
+                .await.unwrap();
+            assert_eq!(c.uids, ids(&[]));
+
+            let e = compare(&gw, UserComparison::SelectedOption(OptionQuery::Name("Tank".to_string())), None)
+                .await.unwrap_err();
+            assert!(e.contains("Tank"), "{e}");
+        }
+
+        #[tokio::test]
+        async fn members_not_selected_option() {
+            let gw = own_poll();
+            let c = compare(&gw, UserComparison::NotSelectedOption, None).await.unwrap();
+            assert_eq!(c.uids, ids(&[4]));
+            assert!(c.text.contains("`1/4`"), "{}", c.text);
+
+            // Ann and Dan have the role, Dan is still the one who didn't vote
+            let c = compare(&gw, UserComparison::NotSelectedOption, Some(ROLE)).await.unwrap();
+            assert_eq!(c.uids, ids(&[4]));
+            assert!(c.text.contains("`1/2`"), "{}", c.text);
+        }
+
+        #[tokio::test]
+        async fn members_selected_option_not_in_voice() {
+            let mut gw = own_poll();
+            let accepted = OptionQuery::Kind(OptionKind::Accepted);
+            let c = compare(&gw, UserComparison::SelectedOptionNotInVoice(accepted, None), None).await.unwrap();
+            assert_eq!(c.uids, ids(&[2]));
+
+            gw.join_voice(GUILD, UserId::new(2), VOICE);
+            let accepted = OptionQuery::Kind(OptionKind::Accepted);
+            let c = compare(&gw, UserComparison::SelectedOptionNotInVoice(accepted, None), None).await.unwrap();
+            assert_eq!(c.uids, ids(&[]));
+            assert!(c.text.contains("`2/2`"), "{}", c.text);
+        }
+
+        #[tokio::test]
+        async fn afk_and_deafened_members_are_not_in_voice() {
+            let mut gw = own_poll();
+            let afk = ChannelId::new(21);
+            gw.set_afk_channel(GUILD, afk);
+            gw.join_voice(GUILD, UserId::new(2), afk);
+            assert_eq!(get_members_in_voice(&gw, GUILD, None), Some(HashSet::from([UserId::new(1)])));
+
+            gw.join_voice_deafened(GUILD, UserId::new(1), VOICE, true);
+            gw.join_voice(GUILD, UserId::new(3), VOICE);
+            assert_eq!(get_members_in_voice(&gw, GUILD, None), Some(HashSet::from([UserId::new(3)])));
+            let accepted = OptionQuery::Kind(OptionKind::Accepted);
+            let c = compare(&gw, UserComparison::SelectedOptionNotInVoice(accepted, None), None).await.unwrap();
+            assert_eq!(c.uids, ids(&[1, 2]));
+        }
+
+        #[tokio::test]
+        async fn voice_is_narrowed_to_the_channel_or_category() {
+            let mut gw = own_poll();
+            let category = ChannelId::new(30);
+            let other = ChannelId::new(22);
+            gw.add_voice_channel(GUILD, VOICE, Some(category));
+            gw.add_voice_channel(GUILD, other, None);
+            gw.join_voice(GUILD, UserId::new(2), other);
+
+            assert_eq!(get_members_in_voice(&gw, GUILD, Some(category)), Some(HashSet::from([UserId::new(1)])));
+            assert_eq!(get_members_in_voice(&gw, GUILD, Some(other)), Some(HashSet::from([UserId::new(2)])));
+            let in_voice = UserComparison::SelectedOptionNotInVoice(OptionQuery::Kind(OptionKind::Accepted), Some(VOICE));
+            let c = compare_channel_members_to_poll(&gw, GUILD, CHANNEL, in_voice, None, PollRef::LastInChannel, "en").await.unwrap();
+            assert_eq!(c.uids, ids(&[2]));
+        }
+
+        #[tokio::test]
+        async fn poll_newer_than_the_stored_one_is_found() {
+            let mut gw = own_poll();
+            let content = gw.message(CHANNEL, POLL).await.unwrap().content;
+            let old = message(MessageId::new(400), fake::user(OWN_ID, "pollbot"), &content);
+            let store = std::sync::Arc::new(storage::PollStore::open(":memory:").unwrap());
+            store.record_poll(&stored_poll_from_message(&old, GUILD, &poll_sources::own::OwnPoll)).unwrap();
+            gw.post(old);
+            gw.store = Some(store.clone());
+
+            assert_eq!(find_poll_message(&gw, &CHANNEL).await.map(|m| m.id), Some(POLL));
+            assert_eq!(store.last_poll_in_channel(CHANNEL).unwrap().map(|p| p.message_id), Some(POLL));
+        }
+
+        #[tokio::test]
+        async fn poll_is_found_by_link() {
+            let gw = own_poll();
+            let e = compare_channel_members_to_poll(&gw, GUILD, EMPTY_CHANNEL, UserComparison::NotSelectedOption, None,
+                PollRef::LastInChannel, "en").await.unwrap_err();
+            assert_eq!(e, tr!("en", "poll_ref.not_found"));
+
+            let link = POLL.link(CHANNEL, Some(GUILD));
+            let c = compare_channel_members_to_poll(&gw, GUILD, EMPTY_CHANNEL, UserComparison::NotSelectedOption, None,
+                PollRef::Given(&link), "en").await.unwrap();
+            assert_eq!(c.uids, ids(&[4]));
+        }
+
+        #[tokio::test]
+        async fn native_poll_voters_are_compared() {
+            let mut gw = guild();
+            let mut poll = message(POLL, fake::user(200, "Eve"), "");
+            poll.poll = Some(Box::new(serde_json::from_value(serde_json::json!({
+                "question": {"text": "Raid?"},
+                "answers": [
+                    {"answer_id": 1, "poll_media": {"text": "Yes"}},
+                    {"answer_id": 2, "poll_media": {"text": "No"}},
+                ],
+                "expiry": null,
+                "allow_multiselect": false,
+                "layout_type": 1,
+                "results": null,
+            })).unwrap()));
+            gw.post(poll);
+            let u = users();
+            gw.vote_native(POLL, 1, vec![u[0].clone(), u[1].clone()]);
+            gw.vote_native(POLL, 2, vec![u[2].clone()]);
+
+            let c = compare(&gw, UserComparison::SelectedOption(OptionQuery::Kind(OptionKind::Accepted)), None)
+                .await.unwrap();
+            assert_eq!(c.uids, ids(&[1, 2]));
+            let c = compare(&gw, UserComparison::SelectedOption(OptionQuery::Name("no".to_string())), None)
+                .await.unwrap();
+            assert_eq!(c.uids, ids(&[3]));
+            let c = compare(&gw, UserComparison::NotSelectedOption, None).await.unwrap();
+            assert_eq!(c.uids, ids(&[4]));
+            let c = compare(&gw, UserComparison::SelectedOptionNotInVoice(OptionQuery::Kind(OptionKind::Accepted), None), None)
+                .await.unwrap();
+            assert_eq!(c.uids, ids(&[2]));
+        }
+
+        #[cfg(feature = "poll_creation")]
+        #[tokio::test]
+        async fn reaction_moves_the_vote_and_is_logged() {
+            let mut gw = own_poll();
+            let options = config::get().own_poll_options();
+            let (accepted, declined) = (reaction_of(&options[0].emoji), reaction_of(&options[1].emoji));
+            // Ben changes his mind, his old reaction is still there
+            let u = users();
+            gw.react(POLL, declined.clone(), vec![fake::user(OWN_ID, "pollbot"), u[2].clone(), u[1].clone()]);
+            let reaction = fake::reaction(GUILD, CHANNEL, POLL, UserId::new(2), declined.clone());
+            let log = handle_reaction_change(&gw, reaction, ReactionChangeType::Add).await.unwrap();
+
+            assert_eq!(gw.reacted(POLL, &accepted), ids(&[OWN_ID, 1]));
+            let content = gw.current(POLL).unwrap().content;
+            assert!(content.contains(&poll_section_header(&options[0].emoji, &options[0].label, 1)), "{content}");
+            assert!(content.contains(&poll_section_header(&options[1].emoji, &options[1].label, 2)), "{content}");
+
+            let threads = gw.threads.lock().unwrap().clone();
+            assert_eq!(threads.len(), 1);
+            assert_eq!(threads[0].name, format!("log-{POLL}"));
+            let sent = gw.sent.lock().unwrap().clone();
+            assert_eq!(sent, vec![
+                (threads[0].id, tr!("en", "log.thread_created")),
+                (threads[0].id, log),
+            ]);
+        }
+
+        #[tokio::test]
+        async fn log_thread_is_created_once() {
+            let gw = guild();
+            log_to_thread(&gw, "first", &GUILD, &CHANNEL, &POLL.to_string()).await.unwrap();
+            log_to_thread(&gw, "second", &GUILD, &CHANNEL, &POLL.to_string()).await.unwrap();
+            let threads = gw.threads.lock().unwrap().clone();
+            assert_eq!(threads.len(), 1);
+            assert_eq!(threads[0].parent_id, Some(CHANNEL));
+            let sent: Vec<String> = gw.sent.lock().unwrap().iter().map(|(_, text)| text.clone()).collect();
+            assert_eq!(sent, vec![tr!("en", "log.thread_created"), "first".to_string(), "second".to_string()]);
+        }
+
+        #[cfg(feature = "third_party_bots")]
+        #[tokio::test]
+        async fn third_party_poll_names_are_matched() {
+            let mut gw = guild();
+            let bot = crate::poll_sources::apollo::default_config();
+            let embed: serenity::all::Embed = serde_json::from_value(serde_json::json!({
+                "title": "Raid",
+                "fields": [
+                    {"name": format!("{} Accepted", bot.emojis[0]), "value": ">>> Ann\nben", "inline": true},
+                    {"name": format!("{} Declined", bot.emojis[1]), "value": ">>> Cat\nEve", "inline": true},
+                ],
+            })).unwrap();
+            let mut poll = message(POLL, fake::user(bot.id, &bot.name), "");
+            poll.embeds.push(embed);
+            gw.post(poll);
+
+            let accepted = OptionQuery::Kind(OptionKind::Accepted);
+            let c = compare(&gw, UserComparison::SelectedOption(accepted), None).await.unwrap();
+            assert_eq!(c.uids, ids(&[1, 2]));
+            // Eve is not in the channel
+            assert!(c.warnings.contains("Eve"), "{}", c.warnings);
+            let c = compare(&gw, UserComparison::NotSelectedOption, None).await.unwrap();
+            assert_eq!(c.uids, ids(&[4]));
+        }
+
+        #[cfg(feature = "third_party_bots")]
+        #[tokio::test]
+        async fn shared_names_are_not_non_voters() {
+            let mut gw = guild();
+            let mut members: Vec<Member> = users().into_iter().map(|u| fake::member(GUILD, u)).collect();
+            members.push(fake::member(GUILD, fake::user(5, "Sam")));
+            members.push(fake::member(GUILD, fake::user(6, "Sam")));
+            gw.add_channel(GUILD, CHANNEL, members);
+            let bot = crate::poll_sources::apollo::default_config();
+            let embed: serenity::all::Embed = serde_json::from_value(serde_json::json!({
+                "title": "Raid",
+                "fields": [{"name": format!("{} Accepted", bot.emojis[0]), "value": ">>> Ann\nBen\nCat\nSam", "inline": true}],
+            })).unwrap();
+            let mut poll = message(POLL, fake::user(bot.id, &bot.name), "");
+            poll.embeds.push(embed);
+            gw.post(poll);
+
+            let c = compare(&gw, UserComparison::NotSelectedOption, None).await.unwrap();
+            assert_eq!(c.uids, ids(&[4]));
+            assert!(c.text.contains("`1/6`"), "{}", c.text);
+            assert!(c.warnings.starts_with(&tr!("en", "results.might_have_voted", n = 2, members = "<@5>, <@6>")), "{}", c.warnings);
+        }
+    }
+}